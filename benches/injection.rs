@@ -0,0 +1,158 @@
+// 注入路径各阶段的基准：手势路径生成、帧生成、input_event序列化和写入、分发到内存sink。
+// 运行：cargo bench --bench injection
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::{
+    fs::OpenOptions,
+    hint::black_box,
+    sync::{Arc, Mutex},
+};
+use touch_simulation::event_sink::MemorySink;
+use touch_simulation::gesture::GestureParams;
+use touch_simulation::input_filter::AxisRange;
+use touch_simulation::mock_device::MockProfile;
+use touch_simulation::passthrough::TouchButtonState;
+use touch_simulation::slot_emitter::SlotEmitter;
+use touch_simulation::touch_engine::{ContactAxes, EngineConfig, Protocol, TouchCommand, TouchEngine};
+use touch_simulation::touch_input::TouchContactB;
+use touch_simulation::uinput::encode_event;
+use touch_simulation::uinput_defs::*;
+
+const CONTACT_COUNTS: [usize; 3] = [1, 5, 10];
+const SLOTS: usize = 10;
+// 每次迭代分发的帧数，Flush往返的开销分摊到这些帧上
+const FRAMES_PER_BATCH: usize = 100;
+
+fn contact(slot: usize, x: i32) -> TouchContactB {
+    TouchContactB {
+        touch_major: 150,
+        touch_minor: 100,
+        width_major: 150,
+        width_minor: 100,
+        orientation: 50,
+        position_x: x,
+        position_y: 100 + slot as i32 * 150,
+        tracking_id: slot as i32,
+        pressure: 35,
+        active: true,
+    }
+}
+
+// 所有触点都移动时的一帧，加上SYN_REPORT
+fn moving_frame(contacts: usize) -> Vec<(u16, u16, i32)> {
+    let mut emitter = SlotEmitter::new(SLOTS);
+    let mut buttons = TouchButtonState::default();
+    let mut state: Vec<TouchContactB> = (0..SLOTS).map(|slot| contact(slot, 100)).collect();
+    state[contacts..].fill(TouchContactB::default());
+    emitter.emit_frame(&state, &mut buttons);
+    state[..contacts].iter_mut().for_each(|c| c.position_x += 1);
+    let mut events = emitter.emit_frame(&state, &mut buttons);
+    events.push((EV_SYN, SYN_REPORT, 0));
+    events
+}
+
+fn path_generation(c: &mut Criterion) {
+    let params = GestureParams::default();
+    let mut group = c.benchmark_group("path_generation");
+    for contacts in CONTACT_COUNTS {
+        group.throughput(Throughput::Elements(contacts as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(contacts), &contacts, |b, &contacts| {
+            b.iter(|| {
+                (0..contacts as i32)
+                    .map(|i| params.swipe(100 + i * 80, 200, 100 + i * 80, 2200))
+                    .collect::<Vec<_>>()
+            })
+        });
+    }
+    group.finish();
+}
+
+fn frame_building(c: &mut Criterion) {
+    let mut group = c.benchmark_group("frame_building");
+    for contacts in CONTACT_COUNTS {
+        group.bench_with_input(BenchmarkId::from_parameter(contacts), &contacts, |b, &contacts| {
+            let mut emitter = SlotEmitter::new(SLOTS);
+            let mut buttons = TouchButtonState::default();
+            let mut state: Vec<TouchContactB> = (0..SLOTS).map(|slot| contact(slot, 100)).collect();
+            state[contacts..].fill(TouchContactB::default());
+            emitter.emit_frame(&state, &mut buttons);
+            b.iter(|| {
+                state[..contacts].iter_mut().for_each(|c| c.position_x = (c.position_x + 1) % 1000);
+                emitter.emit_frame(black_box(&state), &mut buttons)
+            })
+        });
+    }
+    group.finish();
+}
+
+fn serialization(c: &mut Criterion) {
+    let mut group = c.benchmark_group("serialization");
+    for contacts in CONTACT_COUNTS {
+        let events = moving_frame(contacts);
+        group.throughput(Throughput::Elements(events.len() as u64));
+        group.bench_with_input(BenchmarkId::new("encode", contacts), &events, |b, events| {
+            let mut buffer = Vec::with_capacity(events.len() * 24);
+            b.iter(|| {
+                buffer.clear();
+                for &(event_type, code, value) in events {
+                    buffer.extend_from_slice(&encode_event(event_type, code, value));
+                }
+                black_box(buffer.len())
+            })
+        });
+
+        // 每个事件加锁并调用一次write，与写uinput的路径相同，只是目标换成/dev/null
+        let mut device = MockProfile::default().to_input_device().unwrap();
+        device.file = Arc::new(Mutex::new(OpenOptions::new().write(true).open("/dev/null").unwrap()));
+        group.bench_with_input(BenchmarkId::new("write_event", contacts), &events, |b, events| {
+            b.iter(|| {
+                for &(event_type, code, value) in events {
+                    device.write_event(event_type, code, value).unwrap();
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+fn dispatch(c: &mut Criterion) {
+    let mut group = c.benchmark_group("dispatch");
+    group.throughput(Throughput::Elements(FRAMES_PER_BATCH as u64));
+    for (name, protocol) in [("type_a", Protocol::TypeA), ("type_b", Protocol::TypeB)] {
+        for contacts in CONTACT_COUNTS {
+            let sink = Arc::new(Mutex::new(MemorySink::new()));
+            let engine = TouchEngine::spawn(Arc::clone(&sink), EngineConfig {
+                protocol,
+                slots: SLOTS,
+                axes: ContactAxes::default(),
+                range: AxisRange { x_min: 0, x_max: 1080, y_min: 0, y_max: 2340 },
+                tracking_ids: Arc::default(),
+                buttons: Arc::default(),
+                validator: None,
+                metrics: Arc::default(),
+            });
+            for slot in 0..contacts {
+                engine.send(TouchCommand::ContactDown { slot, x: 100, y: 100 + slot as i32 * 150 });
+            }
+            engine.send(TouchCommand::Frame);
+
+            let mut x = 100;
+            group.bench_function(BenchmarkId::new(name, contacts), |b| {
+                b.iter(|| {
+                    for _ in 0..FRAMES_PER_BATCH {
+                        x = x % 1000 + 1;
+                        for slot in 0..contacts {
+                            engine.send(TouchCommand::ContactMove { slot, x, y: 100 + slot as i32 * 150 });
+                        }
+                        engine.send(TouchCommand::Frame);
+                    }
+                    engine.flush().unwrap();
+                    sink.lock().unwrap().events.clear();
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, path_generation, frame_building, serialization, dispatch);
+criterion_main!(benches);
@@ -0,0 +1,167 @@
+use crate::gesture::{CoordinateMap, GestureError, GestureParams, GesturePlayback, GestureStep};
+use crate::passthrough::TouchButtonState;
+use crate::touch_engine::{CommandSender, TouchCommand};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::watch;
+
+// 异步手势接口：用tokio定时器控制节奏，不阻塞调用线程；
+// cancel()取消所有正在执行的手势（包括with_slot得到的句柄上的），future被drop（例如超时）时同样会抬起触点
+#[derive(Debug, Clone)]
+pub struct AsyncTouchHandle {
+    sender: CommandSender,
+    slot: usize,
+    map: CoordinateMap,
+    params: GestureParams,
+    cancel: Arc<watch::Sender<u64>>,
+    // 虚拟设备的slot数量和与转发线程共享的slot占用状态
+    slots: usize,
+    buttons: Arc<Mutex<TouchButtonState>>,
+}
+
+impl AsyncTouchHandle {
+    // slot需要已经在buttons中保留
+    pub fn new(
+        sender: CommandSender,
+        slot: usize,
+        map: CoordinateMap,
+        slots: usize,
+        buttons: Arc<Mutex<TouchButtonState>>,
+    ) -> Self {
+        Self {
+            sender,
+            slot,
+            map,
+            params: GestureParams::default(),
+            cancel: Arc::new(watch::Sender::new(0)),
+            slots,
+            buttons,
+        }
+    }
+
+    pub fn with_params(self, params: GestureParams) -> Self {
+        Self { params, ..self }
+    }
+
+    // 使用另一个slot，多个手势可以同时进行；slot为注入触点保留，转发的真实手指不会再分配到它。
+    // slot超出范围或正被真实手指使用时返回SlotUnavailable
+    pub fn with_slot(&self, slot: usize) -> Result<Self, GestureError> {
+        if slot >= self.slots || !self.buttons.lock().unwrap().reserve(slot) {
+            return Err(GestureError::SlotUnavailable(slot));
+        }
+        Ok(Self { slot, ..self.clone() })
+    }
+
+    pub async fn swipe(&self, start_x: i32, start_y: i32, end_x: i32, end_y: i32) -> Result<(), GestureError> {
+        self.play(&self.params.swipe(start_x, start_y, end_x, end_y)).await
+    }
+
+    pub async fn tap(&self, x: i32, y: i32) -> Result<(), GestureError> {
+        self.play(&self.params.tap(x, y, self.params.tap_hold)).await
+    }
+
+    pub async fn long_press(&self, x: i32, y: i32, hold: Duration) -> Result<(), GestureError> {
+        self.play(&self.params.tap(x, y, hold)).await
+    }
+
+    pub fn cancel(&self) {
+        self.cancel.send_modify(|generation| *generation += 1);
+    }
+
+    // 取消手势并抬起所有注入的触点，包括其他slot上的手势
+    pub fn lift_all(&self) -> Result<(), GestureError> {
+        self.cancel();
+        self.sender.send(TouchCommand::LiftAll).map_err(|_| GestureError::EngineStopped)
+    }
+
+    pub async fn play(&self, steps: &[GestureStep]) -> Result<(), GestureError> {
+        // 只响应开始之后的取消
+        let mut cancelled = self.cancel.subscribe();
+        cancelled.mark_unchanged();
+
+        let mut playback = GesturePlayback::new(self.sender.clone(), self.slot, self.map);
+        for step in steps {
+            playback.apply(step)?;
+            tokio::select! {
+                _ = tokio::time::sleep(step.delay) => {}
+                _ = cancelled.changed() => {
+                    playback.cancel();
+                    return Err(GestureError::Cancelled);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gesture::{AxisTransform, Rotation};
+    use crate::passthrough::{AxisScaling, SlotRemapper};
+    use crate::uinput_defs::*;
+    use std::{sync::mpsc, time::Instant};
+
+    fn handle() -> (AsyncTouchHandle, mpsc::Receiver<(Instant, TouchCommand)>) {
+        let buttons = Arc::new(Mutex::new(TouchButtonState::default()));
+        buttons.lock().unwrap().reserve(9);
+        let (sender, receiver) = CommandSender::channel(Arc::default());
+        let map = CoordinateMap {
+            display_width: 1,
+            display_height: 1,
+            rotation: Rotation::Rotation0,
+            axes: AxisTransform::default(),
+            x_min: 0,
+            x_max: 1,
+            y_min: 0,
+            y_max: 1,
+        };
+        (AsyncTouchHandle::new(sender, 9, map, 10, buttons), receiver)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn cancel_lifts_the_contact_of_an_in_flight_gesture() {
+        let (touch, receiver) = handle();
+        let gesture = tokio::spawn({
+            let touch = touch.clone();
+            async move { touch.long_press(5, 6, Duration::from_secs(10)).await }
+        });
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        touch.cancel();
+        assert_eq!(gesture.await.unwrap(), Err(GestureError::Cancelled));
+
+        let commands: Vec<String> = receiver.try_iter().map(|(_, command)| format!("{:?}", command)).collect();
+        assert_eq!(commands, vec!["ContactDown { slot: 9, x: 5, y: 6 }", "Frame", "ContactUp { slot: 9 }", "Frame"]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn parent_cancel_reaches_gestures_on_other_slots() {
+        let (touch, receiver) = handle();
+        let other = touch.with_slot(3).unwrap();
+        let gesture = tokio::spawn(async move { other.long_press(5, 6, Duration::from_secs(10)).await });
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        touch.cancel();
+        assert_eq!(gesture.await.unwrap(), Err(GestureError::Cancelled));
+
+        let commands: Vec<String> = receiver.try_iter().map(|(_, command)| format!("{:?}", command)).collect();
+        assert_eq!(commands, vec!["ContactDown { slot: 3, x: 5, y: 6 }", "Frame", "ContactUp { slot: 3 }", "Frame"]);
+    }
+
+    #[test]
+    fn with_slot_rejects_slots_out_of_range_or_held_by_real_contacts() {
+        let (touch, _receiver) = handle();
+        assert_eq!(touch.with_slot(10).unwrap_err(), GestureError::SlotUnavailable(10));
+
+        // 转发线程把slot 0分配给了真实手指
+        let mut remapper = SlotRemapper::new(10, 0, Arc::default(), AxisScaling::default());
+        let real_down = [(EV_ABS, ABS_MT_TRACKING_ID, 1)];
+        remapper.translate_frame(&real_down, &mut touch.buttons.lock().unwrap());
+        assert_eq!(touch.with_slot(0).unwrap_err(), GestureError::SlotUnavailable(0));
+
+        // 保留之后真实手指不会再分配到这个slot
+        assert!(touch.with_slot(1).is_ok());
+        let real_down = [(EV_ABS, ABS_MT_SLOT, 1), (EV_ABS, ABS_MT_TRACKING_ID, 2)];
+        let out = remapper.translate_frame(&real_down, &mut touch.buttons.lock().unwrap());
+        assert_eq!(out[0], (EV_ABS, ABS_MT_SLOT, 2));
+    }
+}
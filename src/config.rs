@@ -0,0 +1,328 @@
+use crate::display_size::{DisplaySource, DEFAULT_SOURCES};
+use crate::gesture::{GestureParams, Humanize, Rotation, STEP_INTERVAL};
+use crate::touch_input::{ContactSize, TypeMode};
+use crate::uinput::InputDevice;
+use serde::{Deserialize, Serialize};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+pub const CONFIG_ENV: &str = "TOUCHSIM_CONFIG";
+const MAX_REPORT_RATE_HZ: u32 = 1000;
+
+// 配置文件，.toml按TOML解析，其余按JSON解析；所有字段都可以省略，缺省值与命令行默认行为一致
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub device: DeviceConfig,
+    pub display: DisplayConfig,
+    pub engine: EngineSettings,
+    pub gesture: GestureConfig,
+    pub touch: ContactSize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DeviceConfig {
+    pub mode: ModeSetting,
+    // 按顺序匹配，第一条匹配到设备的规则生效；为空时交互选择
+    pub rules: Vec<DeviceRule>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModeSetting {
+    // 没有ABS_MT_SLOT的面板使用Type A，其余使用Type B
+    #[default]
+    Auto,
+    TypeA,
+    TypeARandom,
+    TypeB,
+}
+
+// 规则里的条件都要满足，name和path支持glob通配符
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DeviceRule {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vendor: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub product: Option<u16>,
+}
+
+// 当前方向下的显示尺寸（像素），省略时按detect的顺序自动检测，都失败时使用参考设备的坐标范围
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DisplayConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub width: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height: Option<i32>,
+    pub rotation: Rotation,
+    pub detect: Vec<DisplaySource>,
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        Self {
+            width: None,
+            height: None,
+            rotation: Rotation::default(),
+            detect: DEFAULT_SOURCES.to_vec(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EngineSettings {
+    // 手势每秒写出的帧数，省略时为原来的15ms间隔
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub report_rate_hz: Option<u32>,
+    pub passthrough: bool,
+    pub validate: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filters: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GestureConfig {
+    pub step_size: f32,
+    pub tap_hold_ms: u64,
+    pub humanize: HumanizeConfig,
+}
+
+impl Default for GestureConfig {
+    fn default() -> Self {
+        let params = GestureParams::default();
+        Self {
+            step_size: params.step_size,
+            tap_hold_ms: params.tap_hold.as_millis() as u64,
+            humanize: HumanizeConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HumanizeConfig {
+    pub position_jitter: i32,
+    pub timing_jitter_ms: u64,
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let text = fs::read_to_string(path).map_err(|e| format!("failed to open config {}: {}", path.display(), e))?;
+        let config = if path.extension().is_some_and(|ext| ext == "toml") {
+            toml::from_str(&text).map_err(|e| format!("invalid config {}: {}", path.display(), e))?
+        } else {
+            serde_json::from_str(&text).map_err(|e| format!("invalid config {}: {}", path.display(), e))?
+        };
+        Ok(config)
+    }
+
+    // 命令行--config优先，其次是TOUCHSIM_CONFIG
+    pub fn locate(cli_path: Option<&str>) -> Option<PathBuf> {
+        cli_path.map(PathBuf::from).or_else(|| env::var_os(CONFIG_ENV).map(PathBuf::from))
+    }
+
+    // 检查解析之后才能发现的问题，返回所有问题的描述
+    pub fn check(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        match (self.display.width, self.display.height) {
+            (Some(width), Some(height)) if width <= 0 || height <= 0 => {
+                problems.push(format!("display size {}x{} must be positive", width, height))
+            }
+            (Some(_), None) | (None, Some(_)) => problems.push("display width and height must be set together".to_string()),
+            _ => {}
+        }
+        for source in &self.display.detect {
+            if matches!(source, DisplaySource::Explicit | DisplaySource::Digitizer) {
+                problems.push(format!("display detect source {:?} cannot be detected", source));
+            }
+        }
+        if let Some(rate) = self.engine.report_rate_hz {
+            if rate == 0 || rate > MAX_REPORT_RATE_HZ {
+                problems.push(format!("report_rate_hz {} out of range 1..={}", rate, MAX_REPORT_RATE_HZ));
+            }
+        }
+        if self.gesture.step_size < 1.0 {
+            problems.push(format!("gesture step_size {} must be at least 1", self.gesture.step_size));
+        }
+        if self.gesture.humanize.position_jitter < 0 {
+            problems.push("humanize position_jitter must not be negative".to_string());
+        }
+        let size = &self.touch;
+        for (name, percent) in [
+            ("touch_major_percent", size.touch_major_percent),
+            ("touch_minor_percent", size.touch_minor_percent),
+            ("width_major_percent", size.width_major_percent),
+            ("width_minor_percent", size.width_minor_percent),
+        ] {
+            if !(0..=100).contains(&percent) {
+                problems.push(format!("touch {} {} out of range 0..=100", name, percent));
+            }
+        }
+        if let Some(filters) = &self.engine.filters {
+            if !filters.is_file() {
+                problems.push(format!("filters file {} does not exist", filters.display()));
+            }
+        }
+        for (i, rule) in self.device.rules.iter().enumerate() {
+            if rule == &DeviceRule::default() {
+                problems.push(format!("device rule {} has no conditions", i));
+            }
+            for pattern in [&rule.name, &rule.path].into_iter().flatten() {
+                if let Err(e) = glob::Pattern::new(pattern) {
+                    problems.push(format!("device rule {}: invalid pattern '{}': {}", i, pattern, e));
+                }
+            }
+        }
+        problems
+    }
+
+    pub fn gesture_params(&self) -> GestureParams {
+        let step_interval = self
+            .engine
+            .report_rate_hz
+            .filter(|rate| *rate > 0)
+            .map_or(STEP_INTERVAL, |rate| Duration::from_secs(1) / rate);
+        GestureParams {
+            step_interval,
+            step_size: self.gesture.step_size,
+            tap_hold: Duration::from_millis(self.gesture.tap_hold_ms),
+            humanize: Humanize {
+                position_jitter: self.gesture.humanize.position_jitter,
+                timing_jitter: Duration::from_millis(self.gesture.humanize.timing_jitter_ms),
+            },
+        }
+    }
+}
+
+impl DeviceConfig {
+    // 没有规则时返回None，由调用方交互选择
+    pub fn select(&self, devices: &[InputDevice]) -> Option<usize> {
+        self.rules
+            .iter()
+            .find_map(|rule| devices.iter().position(|device| rule.matches(device)))
+    }
+}
+
+impl DeviceRule {
+    pub fn matches(&self, device: &InputDevice) -> bool {
+        let glob = |pattern: &Option<String>, value: &str| {
+            pattern
+                .as_ref()
+                .is_none_or(|p| glob::Pattern::new(p).is_ok_and(|p| p.matches(value)))
+        };
+        glob(&self.name, &device.name)
+            && glob(&self.path, &device.path)
+            && self.vendor.is_none_or(|vendor| vendor == device.input_id.vendor)
+            && self.product.is_none_or(|product| product == device.input_id.product)
+    }
+}
+
+impl ModeSetting {
+    // type_b为参考设备是否有ABS_MT_SLOT，未知时按Type B处理
+    pub fn resolve(self, type_b: Option<bool>) -> TypeMode {
+        match self {
+            ModeSetting::Auto if type_b == Some(false) => TypeMode::TypeA,
+            ModeSetting::Auto => TypeMode::TypeB,
+            ModeSetting::TypeA => TypeMode::TypeA,
+            ModeSetting::TypeARandom => TypeMode::TypeARnd,
+            ModeSetting::TypeB => TypeMode::TypeB,
+        }
+    }
+}
+
+impl FromStr for ModeSetting {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(ModeSetting::Auto),
+            "a" | "type_a" => Ok(ModeSetting::TypeA),
+            "a-rnd" | "type_a_random" => Ok(ModeSetting::TypeARandom),
+            "b" | "type_b" => Ok(ModeSetting::TypeB),
+            _ => Err(format!("unknown mode {}, expected auto, a, a-rnd or b", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_toml_and_fills_defaults() {
+        let config: Config = toml::from_str(
+            r#"
+            [device]
+            mode = "type_b"
+            rules = [{ name = "*fts*" }, { vendor = 0x1234 }]
+
+            [display]
+            width = 1080
+            height = 2340
+            rotation = 90
+
+            [engine]
+            report_rate_hz = 120
+
+            [gesture.humanize]
+            position_jitter = 3
+            "#,
+        )
+        .unwrap();
+        assert!(config.check().is_empty());
+        assert_eq!(config.device.mode, ModeSetting::TypeB);
+        assert_eq!(config.display.rotation, Rotation::Rotation90);
+        assert_eq!(config.touch, ContactSize::default());
+
+        let params = config.gesture_params();
+        assert_eq!(params.step_interval, Duration::from_secs(1) / 120);
+        assert_eq!(params.tap_hold, GestureParams::default().tap_hold);
+        assert_eq!(params.humanize.position_jitter, 3);
+    }
+
+    #[test]
+    fn reports_invalid_values_and_unknown_fields() {
+        assert!(toml::from_str::<Config>("[display]\nrotation = 45").is_err());
+        assert!(serde_json::from_str::<Config>(r#"{"engine": {"rate": 60}}"#).is_err());
+
+        let config: Config = serde_json::from_str(
+            r#"{"display": {"width": 1080}, "engine": {"report_rate_hz": 0}, "device": {"rules": [{}]}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.check(),
+            vec![
+                "display width and height must be set together",
+                "report_rate_hz 0 out of range 1..=1000",
+                "device rule 0 has no conditions",
+            ]
+        );
+    }
+
+    #[test]
+    fn first_matching_rule_selects_the_device() {
+        let device = |name: &str, vendor: u16| {
+            let mut device = crate::mock_device::MockProfile::default().to_input_device().unwrap();
+            device.name = name.to_string();
+            device.input_id.vendor = vendor;
+            device
+        };
+        let devices = vec![device("gpio-keys", 0), device("fts_ts", 1), device("synaptics", 2)];
+        let rules: DeviceConfig = toml::from_str("rules = [{ name = \"syn*\" }, { vendor = 1 }]").unwrap();
+        assert_eq!(rules.select(&devices), Some(2));
+        assert_eq!(DeviceConfig::default().select(&devices), None);
+    }
+}
@@ -0,0 +1,238 @@
+use crate::uinput::DeviceInfo;
+use crate::uinput_defs::*;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum DeviceClass {
+    TouchscreenTypeA,
+    TouchscreenTypeB,
+    Touchpad,
+    Pen,
+    Keyboard,
+    Mouse,
+    Joystick,
+    Switch,
+}
+
+// 分类结果，reasons记录每一条判断依据（包括被排除的原因）
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Classification {
+    pub classes: Vec<DeviceClass>,
+    pub reasons: Vec<String>,
+}
+
+impl Classification {
+    pub fn is_touchscreen(&self) -> bool {
+        self.touchscreen_type_b().is_some()
+    }
+
+    // Some(true)为Type B，Some(false)为Type A，None表示不是触摸屏
+    pub fn touchscreen_type_b(&self) -> Option<bool> {
+        if self.classes.contains(&DeviceClass::TouchscreenTypeB) {
+            Some(true)
+        } else if self.classes.contains(&DeviceClass::TouchscreenTypeA) {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    fn accept(&mut self, class: DeviceClass, reason: String) {
+        self.classes.push(class);
+        self.reasons.push(format!("{:?}: {}", class, reason));
+    }
+
+    fn reject(&mut self, class: DeviceClass, reason: String) {
+        self.reasons.push(format!("not {:?}: {}", class, reason));
+    }
+}
+
+// 根据evdev能力位对设备分类，规则参考udev的input_id和libinput
+pub fn classify(info: &DeviceInfo) -> Classification {
+    let mut result = Classification::default();
+
+    let has_ev = |ev: u16| info.events.contains(&ev);
+    let has_abs = |abs: u16| info.abs_infos.contains_key(&abs);
+    let has_key = |key: u16| info.keys.contains(&key);
+    let has_rel = |rel: u16| info.rels.contains(&rel);
+    let has_prop = |prop: u16| info.properties.contains(&prop);
+
+    let is_direct = has_prop(INPUT_PROP_DIRECT);
+    let is_pointer = has_prop(INPUT_PROP_POINTER) || has_prop(INPUT_PROP_BUTTONPAD);
+    let is_pen = has_key(BTN_TOOL_PEN) || has_key(BTN_STYLUS);
+    let has_mt_position = has_abs(ABS_MT_POSITION_X) && has_abs(ABS_MT_POSITION_Y);
+    // ABS_MT_SLOT - 1被占用的设备（如某些手柄）会误报MT轴
+    let fake_mt = has_abs(ABS_MT_SLOT - 1);
+
+    // 触摸屏 / 触摸板，三类都记录一条结论
+    const NOT_POINTER: &str = "no INPUT_PROP_POINTER/BUTTONPAD, no BTN_TOOL_FINGER without INPUT_PROP_DIRECT";
+    let not_mt = if !has_mt_position {
+        Some("no ABS_MT_POSITION_X/Y")
+    } else if fake_mt {
+        Some("ABS_MT_SLOT - 1 is set, MT axes are not real")
+    } else if is_pen && !has_key(BTN_TOUCH) {
+        Some("pen-only MT device without BTN_TOUCH")
+    } else {
+        None
+    };
+    if let Some(reason) = not_mt {
+        for class in [DeviceClass::TouchscreenTypeA, DeviceClass::TouchscreenTypeB, DeviceClass::Touchpad] {
+            result.reject(class, reason.to_string());
+        }
+    } else if is_pointer || (!is_direct && has_key(BTN_TOOL_FINGER)) {
+        result.accept(
+            DeviceClass::Touchpad,
+            "MT position axes with INPUT_PROP_POINTER/BUTTONPAD or BTN_TOOL_FINGER without INPUT_PROP_DIRECT".to_string(),
+        );
+        for class in [DeviceClass::TouchscreenTypeA, DeviceClass::TouchscreenTypeB] {
+            result.reject(class, "pointer device (touchpad)".to_string());
+        }
+    } else if has_abs(ABS_MT_SLOT) {
+        let mut reason = "ABS_MT_SLOT with MT position axes".to_string();
+        if !has_abs(ABS_MT_TRACKING_ID) {
+            reason.push_str(", missing ABS_MT_TRACKING_ID");
+        }
+        reason.push_str(if is_direct { ", INPUT_PROP_DIRECT" } else { ", no INPUT_PROP_DIRECT (assumed direct)" });
+        if !has_key(BTN_TOUCH) {
+            reason.push_str(", missing BTN_TOUCH");
+        }
+        result.accept(DeviceClass::TouchscreenTypeB, reason);
+        result.reject(DeviceClass::TouchscreenTypeA, "has ABS_MT_SLOT".to_string());
+        result.reject(DeviceClass::Touchpad, NOT_POINTER.to_string());
+    } else {
+        let mut reason = "MT position axes without ABS_MT_SLOT".to_string();
+        reason.push_str(if is_direct { ", INPUT_PROP_DIRECT" } else { ", no INPUT_PROP_DIRECT (assumed direct)" });
+        result.accept(DeviceClass::TouchscreenTypeA, reason);
+        result.reject(DeviceClass::TouchscreenTypeB, "no ABS_MT_SLOT".to_string());
+        result.reject(DeviceClass::Touchpad, NOT_POINTER.to_string());
+    }
+
+    // 手写笔
+    if is_pen && (has_abs(ABS_X) || has_mt_position) {
+        result.accept(DeviceClass::Pen, "BTN_TOOL_PEN/BTN_STYLUS with absolute axes".to_string());
+    } else if is_pen {
+        result.reject(DeviceClass::Pen, "BTN_TOOL_PEN/BTN_STYLUS without ABS_X or MT position axes".to_string());
+    } else {
+        result.reject(DeviceClass::Pen, "no BTN_TOOL_PEN/BTN_STYLUS".to_string());
+    }
+
+    // 鼠标
+    if has_ev(EV_REL) && has_rel(REL_X) && has_rel(REL_Y) {
+        if has_key(BTN_LEFT) {
+            result.accept(DeviceClass::Mouse, "REL_X/REL_Y with BTN_LEFT".to_string());
+        } else {
+            result.reject(DeviceClass::Mouse, "REL_X/REL_Y without BTN_LEFT".to_string());
+        }
+    } else {
+        result.reject(DeviceClass::Mouse, "no REL_X/REL_Y".to_string());
+    }
+
+    // 手柄 / 摇杆
+    let has_joystick_buttons = info.keys.iter().any(|&k| (BTN_JOYSTICK..BTN_TOOL_PEN).contains(&k));
+    if has_joystick_buttons {
+        result.accept(DeviceClass::Joystick, "BTN_JOYSTICK/BTN_GAMEPAD range buttons".to_string());
+    } else if has_abs(ABS_X) && has_abs(ABS_Y) && !has_key(BTN_TOUCH) && !is_pen && !is_direct && !is_pointer {
+        result.accept(DeviceClass::Joystick, "ABS_X/ABS_Y without BTN_TOUCH or input properties".to_string());
+    } else if has_abs(ABS_X) && has_abs(ABS_Y) {
+        result.reject(
+            DeviceClass::Joystick,
+            "ABS_X/ABS_Y with BTN_TOUCH, pen buttons or input properties, no joystick buttons".to_string(),
+        );
+    } else {
+        result.reject(DeviceClass::Joystick, "no BTN_JOYSTICK/BTN_GAMEPAD range buttons or ABS_X/ABS_Y".to_string());
+    }
+
+    // 键盘（包括只有电源/音量键的gpio-keys）
+    let keyboard_keys = info.keys.iter().filter(|&&k| k != 0 && k < BTN_MISC).count();
+    if keyboard_keys > 0 {
+        result.accept(DeviceClass::Keyboard, format!("{} keys below BTN_MISC", keyboard_keys));
+    } else {
+        result.reject(DeviceClass::Keyboard, "no keys below BTN_MISC".to_string());
+    }
+
+    // 开关（耳机插入、盖子等）
+    if has_ev(EV_SW) {
+        result.accept(DeviceClass::Switch, format!("EV_SW with {} switches", info.switches.len()));
+    } else {
+        result.reject(DeviceClass::Switch, "no EV_SW".to_string());
+    }
+
+    if result.classes.is_empty() {
+        result.reasons.push("no known capability combination".to_string());
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device(events: &[u16], abs: &[u16], keys: &[u16], properties: &[u16]) -> DeviceInfo {
+        DeviceInfo {
+            events: events.to_vec(),
+            abs_infos: abs.iter().map(|&code| (code, AbsInfo::default())).collect(),
+            keys: keys.to_vec(),
+            properties: properties.to_vec(),
+            ..DeviceInfo::default()
+        }
+    }
+
+    const MT_AXES: [u16; 4] = [ABS_MT_TOUCH_MAJOR, ABS_MT_POSITION_X, ABS_MT_POSITION_Y, ABS_MT_TRACKING_ID];
+
+    #[test]
+    fn type_b_panel() {
+        let abs = [&MT_AXES[..], &[ABS_MT_SLOT]].concat();
+        let result = classify(&device(&[EV_SYN, EV_KEY, EV_ABS], &abs, &[BTN_TOUCH], &[INPUT_PROP_DIRECT]));
+        assert_eq!(result.classes, vec![DeviceClass::TouchscreenTypeB]);
+        assert_eq!(result.touchscreen_type_b(), Some(true));
+    }
+
+    #[test]
+    fn type_a_panel_without_slots() {
+        // 老的Type A驱动常常不声明INPUT_PROP_DIRECT
+        let result = classify(&device(&[EV_SYN, EV_KEY, EV_ABS], &MT_AXES, &[BTN_TOUCH], &[]));
+        assert_eq!(result.classes, vec![DeviceClass::TouchscreenTypeA]);
+        assert_eq!(result.touchscreen_type_b(), Some(false));
+    }
+
+    #[test]
+    fn touchpad_is_not_a_touchscreen() {
+        let abs = [&MT_AXES[..], &[ABS_MT_SLOT, ABS_X, ABS_Y]].concat();
+        let keys = [BTN_LEFT, BTN_TOOL_FINGER, BTN_TOUCH];
+        let result = classify(&device(&[EV_SYN, EV_KEY, EV_ABS], &abs, &keys, &[INPUT_PROP_POINTER]));
+        assert_eq!(result.classes, vec![DeviceClass::Touchpad]);
+        assert!(!result.is_touchscreen());
+    }
+
+    #[test]
+    fn keyboard() {
+        let result = classify(&device(&[EV_SYN, EV_KEY], &[], &[KEY_VOLUMEDOWN, KEY_VOLUMEUP, KEY_POWER], &[]));
+        assert_eq!(result.classes, vec![DeviceClass::Keyboard]);
+        assert!(!result.is_touchscreen());
+    }
+
+    #[test]
+    fn joystick_with_fake_mt_axes() {
+        // 手柄占用了ABS_MT_SLOT - 1，之后的MT轴不是真的
+        let abs = [&MT_AXES[..], &[ABS_X, ABS_Y, ABS_MT_SLOT - 1]].concat();
+        // BTN_SOUTH、BTN_EAST
+        let result = classify(&device(&[EV_SYN, EV_KEY, EV_ABS], &abs, &[BTN_GAMEPAD, BTN_GAMEPAD + 1], &[]));
+        assert_eq!(result.classes, vec![DeviceClass::Joystick]);
+        assert!(result.reasons.iter().any(|reason| reason.contains("MT axes are not real")));
+    }
+
+    #[test]
+    fn every_class_gets_one_reason() {
+        let abs = [&MT_AXES[..], &[ABS_MT_SLOT]].concat();
+        let devices = [
+            device(&[EV_SYN, EV_KEY, EV_ABS], &abs, &[BTN_TOUCH], &[INPUT_PROP_DIRECT]),
+            device(&[EV_SYN, EV_KEY], &[], &[KEY_POWER], &[]),
+        ];
+        for info in &devices {
+            let result = classify(info);
+            assert_eq!(result.reasons.len(), 8, "{:?}", result.reasons);
+            let rejected = result.reasons.iter().filter(|reason| reason.starts_with("not ")).count();
+            assert_eq!(rejected, 8 - result.classes.len(), "{:?}", result.reasons);
+        }
+    }
+}
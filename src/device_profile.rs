@@ -0,0 +1,172 @@
+use crate::config::{DeviceRule, ModeSetting};
+use crate::gesture::AxisTransform;
+use crate::uinput::{InputDevice, VirtualTouchOptions};
+use serde::{Deserialize, Serialize};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+pub const PROFILES_ENV: &str = "TOUCHSIM_PROFILES";
+const BUILTIN_PROFILES: &str = include_str!("../profiles/builtin.toml");
+
+// 针对某一型号触摸屏的修正，所有字段都可以省略
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DeviceProfile {
+    pub id: String,
+    // 按设备名称（支持通配符）和input id匹配
+    #[serde(rename = "match")]
+    pub matcher: DeviceRule,
+    // 配置文件和命令行没有指定协议时使用
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mode: Option<ModeSetting>,
+    pub axes: AxisTransform,
+    // 注入触点的压力乘以这个系数
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pressure_scale: Option<f32>,
+    // 虚拟设备ABS_MT_TOUCH_MAJOR等面积轴的最大值
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub touch_major_max: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pressure_max: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub virtual_name: Option<String>,
+}
+
+impl DeviceProfile {
+    pub fn virtual_touch_options(&self) -> VirtualTouchOptions {
+        VirtualTouchOptions {
+            name: self.virtual_name.clone(),
+            touch_major_max: self.touch_major_max,
+            pressure_max: self.pressure_max,
+        }
+    }
+
+    pub fn scale_pressure(&self, pressure: i32) -> i32 {
+        match self.pressure_scale {
+            Some(scale) if pressure >= 0 => (pressure as f32 * scale).round() as i32,
+            _ => pressure,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProfileFile {
+    #[serde(default)]
+    profiles: Vec<DeviceProfile>,
+}
+
+// 档案库：用户档案在前，内置档案在后，第一个匹配的档案生效
+#[derive(Debug, Clone, Default)]
+pub struct ProfileDb {
+    profiles: Vec<DeviceProfile>,
+}
+
+impl ProfileDb {
+    pub fn builtin() -> Self {
+        let file: ProfileFile = toml::from_str(BUILTIN_PROFILES).expect("built-in profiles are valid");
+        Self { profiles: file.profiles }
+    }
+
+    // 命令行--profiles优先，其次是TOUCHSIM_PROFILES
+    pub fn locate(cli_path: Option<&str>) -> Option<PathBuf> {
+        cli_path.map(PathBuf::from).or_else(|| env::var_os(PROFILES_ENV).map(PathBuf::from))
+    }
+
+    // 加载用户档案文件，目录则按文件名顺序加载其中所有.toml/.json文件
+    pub fn load_user(&mut self, path: &Path) -> Result<usize, Box<dyn std::error::Error>> {
+        let mut files = Vec::new();
+        if path.is_dir() {
+            let entries = fs::read_dir(path).map_err(|e| format!("failed to read profiles {}: {}", path.display(), e))?;
+            files.extend(
+                entries
+                    .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                    .filter(|p| p.extension().is_some_and(|ext| ext == "toml" || ext == "json")),
+            );
+            files.sort();
+        } else {
+            files.push(path.to_path_buf());
+        }
+
+        let mut user = Vec::new();
+        for file in &files {
+            user.extend(load_profile_file(file)?);
+        }
+        let count = user.len();
+        user.append(&mut self.profiles);
+        self.profiles = user;
+        Ok(count)
+    }
+
+    pub fn profiles(&self) -> &[DeviceProfile] {
+        &self.profiles
+    }
+
+    pub fn find(&self, device: &InputDevice) -> Option<&DeviceProfile> {
+        self.profiles.iter().find(|profile| profile.matcher.matches(device))
+    }
+
+    // 扫描结果标注匹配到的档案，设备列表和设备信息中可以看到
+    pub fn annotate(&self, devices: &mut [InputDevice]) {
+        for device in devices {
+            device.info.profile = self.find(device).map(|profile| profile.id.clone());
+        }
+    }
+}
+
+fn load_profile_file(path: &Path) -> Result<Vec<DeviceProfile>, Box<dyn std::error::Error>> {
+    let text = fs::read_to_string(path).map_err(|e| format!("failed to open profiles {}: {}", path.display(), e))?;
+    let file: ProfileFile = if path.extension().is_some_and(|ext| ext == "toml") {
+        toml::from_str(&text).map_err(|e| format!("invalid profiles {}: {}", path.display(), e))?
+    } else {
+        serde_json::from_str(&text).map_err(|e| format!("invalid profiles {}: {}", path.display(), e))?
+    };
+    Ok(file.profiles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_device::MockProfile;
+
+    fn device(name: &str, vendor: u16) -> InputDevice {
+        MockProfile {
+            name: name.to_string(),
+            vendor,
+            ..MockProfile::default()
+        }
+        .to_input_device()
+        .unwrap()
+    }
+
+    #[test]
+    fn builtin_profiles_match_by_name_and_input_id() {
+        let db = ProfileDb::builtin();
+        assert!(db.profiles().iter().all(|profile| !profile.id.is_empty()));
+
+        let mut devices = vec![device("fts_ts", 0), device("goodix-ts", 0x0416), device("goodix-ts", 0x27c6)];
+        db.annotate(&mut devices);
+        let ids: Vec<Option<&str>> = devices.iter().map(|d| d.info.profile.as_deref()).collect();
+        assert_eq!(ids, vec![Some("focaltech-fts"), Some("goodix-tablet"), None]);
+
+        let goodix = db.find(&devices[1]).unwrap();
+        assert!(goodix.axes.swap_xy && goodix.axes.flip_x && !goodix.axes.flip_y);
+    }
+
+    #[test]
+    fn user_profiles_take_precedence() {
+        let path = env::temp_dir().join(format!("touchsim-profiles-{}.json", std::process::id()));
+        fs::write(&path, r#"{"profiles": [{"id": "my-fts", "match": {"name": "fts_ts"}, "pressure_scale": 2.0}]}"#).unwrap();
+        let mut db = ProfileDb::builtin();
+        let loaded = db.load_user(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap(), 1);
+
+        let profile = db.find(&device("fts_ts", 0)).unwrap();
+        assert_eq!(profile.id, "my-fts");
+        assert_eq!(profile.scale_pressure(35), 70);
+        assert_eq!(profile.scale_pressure(-1), -1);
+    }
+}
@@ -0,0 +1,121 @@
+use crate::device_classifier::classify;
+use crate::uinput::InputDevice;
+use crate::uinput_defs::abs_code_name;
+use serde_json::json;
+
+// 设备信息的JSON表示，file等运行时字段不输出
+pub fn device_json(device: &InputDevice) -> serde_json::Value {
+    let abs_infos: serde_json::Map<String, serde_json::Value> = device
+        .info
+        .abs_infos
+        .iter()
+        .map(|(code, abs)| (axis_name(*code), json!(abs)))
+        .collect();
+
+    json!({
+        "name": device.name,
+        "path": device.path,
+        "mock": device.info.mock,
+        "profile": device.info.profile,
+        "input_id": device.input_id,
+        "driver_version": format_version(device.info.driver_version),
+        "phys": device.info.phys,
+        "uniq": device.info.uniq,
+        "sysfs_path": device.info.sysfs_path,
+        "event_node": device.event_node(),
+        "abs_unknown": device.info.abs_unknown,
+        "slots": device.slots,
+        "abs": abs_infos,
+        "properties": device.info.properties,
+        "keys": device.info.keys,
+        "rels": device.info.rels,
+        "switches": device.info.switches,
+        "classification": classify(&device.info),
+    })
+}
+
+pub fn print_device_json(devices: &[InputDevice]) {
+    let values: Vec<serde_json::Value> = devices.iter().map(device_json).collect();
+    println!("{}", serde_json::to_string_pretty(&values).unwrap_or_default());
+}
+
+pub fn print_device_table(device: &InputDevice) {
+    let info = &device.info;
+    println!("{}{}", device.name, if info.mock { " [MOCK]" } else { "" });
+    println!("  {:<16} {}", "path", device.path);
+    println!(
+        "  {:<16} bus=0x{:04x} vendor=0x{:04x} product=0x{:04x} version=0x{:04x}",
+        "input id", device.input_id.bus_type, device.input_id.vendor, device.input_id.product, device.input_id.version
+    );
+    println!("  {:<16} {}", "driver version", format_version(info.driver_version));
+    println!("  {:<16} {}", "phys", info.phys);
+    println!("  {:<16} {}", "uniq", info.uniq);
+    println!("  {:<16} {}", "sysfs", info.sysfs_path);
+    println!("  {:<16} {}", "event node", device.event_node().unwrap_or("-"));
+    println!("  {:<16} {}", "slots", device.slots);
+    println!("  {:<16} {}", "profile", info.profile.as_deref().unwrap_or("-"));
+    println!("  {:<16} {}", "properties", format_codes(&info.properties));
+    println!("  {:<16} {} ({})", "keys", info.keys.len(), format_codes(&info.keys));
+
+    if info.abs_unknown {
+        println!("  {:<16} unknown ({} could not be opened)", "abs ranges", device.path);
+    } else if !info.abs_infos.is_empty() {
+        println!(
+            "  {:<20} {:>8} {:>8} {:>8} {:>6} {:>6} {:>6}",
+            "axis", "value", "min", "max", "fuzz", "flat", "res"
+        );
+        for (code, abs) in &info.abs_infos {
+            println!(
+                "  {:<20} {:>8} {:>8} {:>8} {:>6} {:>6} {:>6}",
+                axis_name(*code), abs.value, abs.minimum, abs.maximum, abs.fuzz, abs.flat, abs.resolution
+            );
+        }
+    }
+}
+
+// 每个设备一行分类结果，后面逐条列出判断依据
+pub fn print_device_list(devices: &[InputDevice], json: bool) {
+    if json {
+        let values: Vec<serde_json::Value> = devices
+            .iter()
+            .map(|device| {
+                json!({
+                    "name": device.name,
+                    "path": device.path,
+                    "classification": classify(&device.info),
+                })
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&values).unwrap_or_default());
+        return;
+    }
+
+    for device in devices {
+        let classification = classify(&device.info);
+        let classes: Vec<String> = classification.classes.iter().map(|c| format!("{:?}", c)).collect();
+        let classes = if classes.is_empty() { "Unknown".to_string() } else { classes.join(", ") };
+        println!("{:<20} {:<32} {}", device.path, device.name, classes);
+        for reason in &classification.reasons {
+            println!("    - {}", reason);
+        }
+    }
+}
+
+fn axis_name(code: u16) -> String {
+    abs_code_name(code)
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("ABS_0x{:02x}", code))
+}
+
+// 驱动版本按major.minor.patch格式化，与evtest一致
+fn format_version(version: i32) -> String {
+    format!("{}.{}.{}", version >> 16, (version >> 8) & 0xff, version & 0xff)
+}
+
+fn format_codes(codes: &[u16]) -> String {
+    codes
+        .iter()
+        .map(|code| format!("0x{:x}", code))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
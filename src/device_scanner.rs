@@ -1,308 +1,323 @@
-use crate::uinput::InputDevice;
+use crate::device_classifier::classify;
+use crate::uinput::{DeviceInfo, InputDevice};
 use crate::uinput_defs::*;
+use log::{debug, info, warn};
 use std::{
-    fs,
-    collections::HashMap,
+    fs::{self, File},
+    collections::BTreeMap,
+    fmt,
+    path::Path,
     sync::{Arc, Mutex},
     os::unix::fs::OpenOptionsExt,
-    os::unix::io::AsRawFd,
+    os::unix::io::{AsRawFd, RawFd},
 };
 
-// 实现Go版本的getInputDevices功能
+// 没有找到触摸屏时返回的错误，列出所有扫描到的设备及分类结果
+#[derive(Debug)]
+pub struct NoTouchscreenError {
+    pub scanned: Vec<String>,
+}
+
+impl fmt::Display for NoTouchscreenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "no touchscreen found among {} scanned devices", self.scanned.len())?;
+        for line in &self.scanned {
+            write!(f, "\n  {}", line)?;
+        }
+        write!(f, "\n(use --mock or --mock-profile PATH to run against a simulated device)")
+    }
+}
+
+impl std::error::Error for NoTouchscreenError {}
+
+// 实现Go版本的getInputDevices功能：返回所有触摸屏（Type A或Type B）
+// 找不到时返回NoTouchscreenError，不会回退到模拟设备
 pub fn scan_input_devices() -> Result<Vec<InputDevice>, Box<dyn std::error::Error>> {
-    println!("scan_input_devices: scanning real input devices");
+    select_touchscreens(scan_all_input_devices()?)
+}
+
+// 从任意发现后端的结果中挑出触摸屏
+pub fn select_touchscreens(all_devices: Vec<InputDevice>) -> Result<Vec<InputDevice>, Box<dyn std::error::Error>> {
+    let mut devices = Vec::new();
+    let mut scanned = Vec::new();
+    for device in all_devices {
+        let classification = classify(&device.info);
+        let classes: Vec<String> = classification.classes.iter().map(|c| format!("{:?}", c)).collect();
+        scanned.push(format!("{} ({}): {}", device.path, device.name,
+                             if classes.is_empty() { "Unknown".to_string() } else { classes.join(", ") }));
+        if classification.is_touchscreen() {
+            devices.push(device);
+        } else {
+            debug!("scan_input_devices: device {} is not a touchscreen: {}",
+                     device.path, classification.reasons.join("; "));
+        }
+    }
     
-    // 扫描 /dev/input/event* 设备
-    let paths = fs::read_dir("/dev/input")?
+    if devices.is_empty() {
+        warn!("scan_input_devices: no valid touch devices found");
+        return Err(Box::new(NoTouchscreenError { scanned }));
+    }
+    info!("scan_input_devices: found {} valid touch devices", devices.len());
+    Ok(devices)
+}
+
+// 扫描 /dev/input/event* 下的全部设备，不按类型筛选
+pub fn scan_all_input_devices() -> Result<Vec<InputDevice>, Box<dyn std::error::Error>> {
+    debug!("scan_input_devices: scanning real input devices");
+    
+    let mut paths = fs::read_dir("/dev/input")?
         .filter_map(|entry| entry.ok())
         .filter(|entry| {
             let name = entry.file_name();
             name.to_string_lossy().starts_with("event")
         })
+        .map(|entry| entry.path())
         .collect::<Vec<_>>();
+    paths.sort();
     
-    println!("scan_input_devices: found {} event devices", paths.len());
+    debug!("scan_input_devices: found {} event devices", paths.len());
     
-    let mut devices = Vec::new();
+    Ok(paths.iter().filter_map(|path| probe_device(path)).collect())
+}
+
+// 读取单个event节点的全部能力和元数据，不做筛选
+pub fn probe_device(path: &Path) -> Option<InputDevice> {
+    let path_str = path.to_string_lossy().to_string();
     
-    for path_entry in paths {
-        let path = path_entry.path();
-        let path_str = path.to_string_lossy().to_string();
-        
-        // 检查是否为字符设备
-        if let Ok(metadata) = fs::metadata(&path) {
-            use std::os::unix::fs::FileTypeExt;
-            if !metadata.file_type().is_char_device() {
-                continue;
-            }
-        } else {
-            continue;
+    // 检查是否为字符设备
+    if let Ok(metadata) = fs::metadata(path) {
+        use std::os::unix::fs::FileTypeExt;
+        if !metadata.file_type().is_char_device() {
+            return None;
         }
-        
-        println!("scan_input_devices: checking device {}", path_str);
-        
-        // 打开设备文件
-        match std::fs::OpenOptions::new()
-            .read(true)
-            .write(false)
-            .custom_flags(libc::O_NONBLOCK)
-            .open(&path) 
-        {
-            Ok(device_file) => {
-                let fd = device_file.as_raw_fd();
-                
-                // 读取事件类型位图
-                let mut d_bits = [0u8; EV_CNT / 8];
-                let result = unsafe {
-                    libc::ioctl(fd, eviocgbit(0, EV_MAX as u32) as libc::c_int, &mut d_bits as *mut _ as usize)
-                };
-                if result == -1 {
-                    println!("scan_input_devices: failed to read EV bits for {}", path_str);
-                    continue;
-                }
-                
-                // 读取ABS位图
-                let mut abs_bits = [0u8; ABS_CNT / 8];
-                let result = unsafe {
-                    libc::ioctl(fd, eviocgbit(EV_ABS as u32, ABS_MAX as u32) as libc::c_int, &mut abs_bits as *mut _ as usize)
-                };
-                if result == -1 {
-                    println!("scan_input_devices: failed to read ABS bits for {}", path_str);
-                    continue;
-                }
-                
-                // 读取PROP位图
-                let mut prop_bits = [0u8; INPUT_PROP_CNT / 8];
-                let result = unsafe {
-                    libc::ioctl(fd, eviocgprop() as libc::c_int, &mut prop_bits as *mut _ as usize)
-                };
-                if result == -1 {
-                    println!("scan_input_devices: failed to read PROP bits for {}", path_str);
-                    continue;
-                }
-                
-                // 读取KEY位图
-                let mut key_bits = [0u8; KEY_CNT / 8];
-                let result = unsafe {
-                    libc::ioctl(fd, eviocgbit(EV_KEY as u32, KEY_MAX as u32) as libc::c_int, &mut key_bits as *mut _ as usize)
-                };
-                if result == -1 {
-                    println!("scan_input_devices: failed to read KEY bits for {}", path_str);
-                    continue;
-                }
-                
-                // 检查是否为多点触摸设备
-                // 条件：有ABS_MT_SLOT、ABS_MT_TRACKING_ID、ABS_MT_POSITION_X/Y、INPUT_PROP_DIRECT、BTN_TOUCH
-                let has_mt_slot = has_specific_abs(&abs_bits, ABS_MT_SLOT);
-                let has_mt_tracking_id = has_specific_abs(&abs_bits, ABS_MT_TRACKING_ID);
-                let has_mt_position_x = has_specific_abs(&abs_bits, ABS_MT_POSITION_X);
-                let has_mt_position_y = has_specific_abs(&abs_bits, ABS_MT_POSITION_Y);
-                let has_input_prop_direct = has_specific_prop(&prop_bits, INPUT_PROP_DIRECT);
-                let has_btn_touch = has_specific_key(&key_bits, BTN_TOUCH);
-                
-                // 检查ABS_MT_SLOT-1不存在（排除非MT设备）
-                let has_mt_slot_minus_1 = has_specific_abs(&abs_bits, ABS_MT_SLOT - 1);
-                
-                println!("scan_input_devices: device {} - MT_SLOT: {}, MT_TRACKING_ID: {}, MT_POSITION_X: {}, MT_POSITION_Y: {}, PROP_DIRECT: {}, BTN_TOUCH: {}, MT_SLOT-1: {}", 
-                         path_str, has_mt_slot, has_mt_tracking_id, has_mt_position_x, has_mt_position_y, 
-                         has_input_prop_direct, has_btn_touch, has_mt_slot_minus_1);
-                
-                // 应用Go版本的筛选逻辑
-                if !has_mt_slot_minus_1 && 
-                   has_mt_slot && 
-                   has_mt_tracking_id && 
-                   has_mt_position_x && 
-                   has_mt_position_y && 
-                   has_input_prop_direct && 
-                   has_btn_touch {
-                    
-                    println!("scan_input_devices: found valid touch device at {}", path_str);
-                    
-                    // 读取ABS配置信息
-                    let mut abs_infos = HashMap::new();
-                    let mut slots = 0i32;
-                    let mut touch_x_min = 0i32;
-                    let mut touch_x_max = 0i32;
-                    let mut touch_y_min = 0i32;
-                    let mut touch_y_max = 0i32;
-                    let mut has_touch_major = false;
-                    let mut has_touch_minor = false;
-                    let mut has_width_major = false;
-                    let mut has_width_minor = false;
-                    let mut has_orientation = false;
-                    let mut has_pressure = false;
-                    
-                    for abs_code in 0..=ABS_MAX {
-                        if has_specific_abs(&abs_bits, abs_code) {
-                            let mut abs_info = AbsInfo {
-                                value: 0,
-                                minimum: 0,
-                                maximum: 0,
-                                fuzz: 0,
-                                flat: 0,
-                                resolution: 0,
-                            };
-                            
-                            let result = unsafe {
-                                libc::ioctl(fd, eviocgabs(abs_code as u32) as libc::c_int, &mut abs_info as *mut _ as usize)
-                            };
-                            
-                            if result != -1 {
-                                abs_infos.insert(abs_code, abs_info);
-                                
-                                // 提取关键信息
-                                match abs_code {
-                                    ABS_MT_SLOT => {
-                                        slots = abs_info.maximum + 1;
-                                    }
-                                    ABS_MT_TRACKING_ID => {
-                                        if abs_info.maximum == abs_info.minimum {
-                                            // 特殊处理，与Go版本一致
-                                        }
-                                    }
-                                    ABS_MT_POSITION_X => {
-                                        touch_x_min = abs_info.minimum;
-                                        touch_x_max = abs_info.maximum - abs_info.minimum + 1;
-                                    }
-                                    ABS_MT_POSITION_Y => {
-                                        touch_y_min = abs_info.minimum;
-                                        touch_y_max = abs_info.maximum - abs_info.minimum + 1;
-                                    }
-                                    ABS_MT_TOUCH_MAJOR => has_touch_major = true,
-                                    ABS_MT_TOUCH_MINOR => has_touch_minor = true,
-                                    ABS_MT_WIDTH_MAJOR => has_width_major = true,
-                                    ABS_MT_WIDTH_MINOR => has_width_minor = true,
-                                    ABS_MT_ORIENTATION => has_orientation = true,
-                                    ABS_MT_PRESSURE => has_pressure = true,
-                                    _ => {}
-                                }
-                            }
-                        }
-                    }
-                    
-                    // 读取设备名称
-                    let mut name_bytes = [0u8; UINPUT_MAX_NAME_SIZE];
-                    let result = unsafe {
-                        libc::ioctl(fd, eviocgname() as libc::c_int, &mut name_bytes as *mut _ as usize)
-                    };
-                    let name = if result != -1 {
-                        let len = name_bytes.iter().position(|&b| b == 0).unwrap_or(UINPUT_MAX_NAME_SIZE);
-                        String::from_utf8_lossy(&name_bytes[..len]).to_string()
-                    } else {
-                        "Unknown".to_string()
-                    };
-                    
-                    // 读取输入ID
-                    let mut input_id = InputId {
-                        bus_type: 0,
-                        vendor: 0,
-                        product: 0,
-                        version: 0,
-                    };
-                    let _result = unsafe {
-                        libc::ioctl(fd, eviocgid() as libc::c_int, &mut input_id as *mut _ as usize)
-                    };
-                    
-                    // 读取驱动版本
-                    let mut version = 0i32;
-                    let _result = unsafe {
-                        libc::ioctl(fd, eviocgversion() as libc::c_int, &mut version as *mut _ as usize)
-                    };
-                    
-                    let device = InputDevice {
-                        name,
-                        path: path_str.clone(),
-                        slots,
-                        touch_x_min,
-                        touch_x_max,
-                        touch_y_min,
-                        touch_y_max,
-                        has_touch_major,
-                        has_touch_minor,
-                        has_width_major,
-                        has_width_minor,
-                        has_orientation,
-                        has_pressure,
-                        file: Arc::new(Mutex::new(device_file)),
-                    };
-                    
-                    devices.push(device);
-                    println!("scan_input_devices: added device {} with {} slots", path_str, slots);
-                } else {
-                    println!("scan_input_devices: device {} does not meet touch device criteria", path_str);
-                }
-            }
-            Err(e) => {
-                println!("scan_input_devices: failed to open device {}: {}", path_str, e);
-            }
+    } else {
+        return None;
+    }
+    
+    debug!("scan_input_devices: checking device {}", path_str);
+    
+    // 打开设备文件
+    let device_file = match std::fs::OpenOptions::new()
+        .read(true)
+        .write(false)
+        .custom_flags(libc::O_NONBLOCK)
+        .open(path)
+    {
+        Ok(device_file) => device_file,
+        Err(e) => {
+            debug!("scan_input_devices: failed to open device {}: {}", path_str, e);
+            return None;
+        }
+    };
+
+    let fd = device_file.as_raw_fd();
+    
+    // 读取事件类型、ABS、PROP、KEY、REL、SW位图
+    // 缓冲区按long对齐，内核按BITS_TO_LONGS拷贝，避免越界写
+    let mut ev_bits = [0u8; bitmap_len(EV_CNT)];
+    let mut abs_bits = [0u8; bitmap_len(ABS_CNT)];
+    let mut prop_bits = [0u8; bitmap_len(INPUT_PROP_CNT)];
+    let mut key_bits = [0u8; bitmap_len(KEY_CNT)];
+    let mut rel_bits = [0u8; bitmap_len(REL_CNT)];
+    let mut sw_bits = [0u8; bitmap_len(SW_CNT)];
+    let bitmaps: [(&str, u32, &mut [u8]); 6] = [
+        ("EV", eviocgbit(0, ev_bits.len() as u32), &mut ev_bits),
+        ("ABS", eviocgbit(EV_ABS as u32, abs_bits.len() as u32), &mut abs_bits),
+        ("PROP", eviocgprop(), &mut prop_bits),
+        ("KEY", eviocgbit(EV_KEY as u32, key_bits.len() as u32), &mut key_bits),
+        ("REL", eviocgbit(EV_REL as u32, rel_bits.len() as u32), &mut rel_bits),
+        ("SW", eviocgbit(EV_SW as u32, sw_bits.len() as u32), &mut sw_bits),
+    ];
+    for (label, request, bits) in bitmaps {
+        let result = unsafe {
+            libc::ioctl(fd, request as _, bits.as_mut_ptr() as usize)
+        };
+        if result == -1 {
+            debug!("scan_input_devices: failed to read {} bits for {}", label, path_str);
+            return None;
         }
     }
     
-    if !devices.is_empty() {
-        println!("scan_input_devices: found {} valid touch devices", devices.len());
-        Ok(devices)
-    } else {
-        println!("scan_input_devices: no valid touch devices found, creating mock device");
-        // 如果没有找到设备，创建模拟设备（保持向后兼容）
-        get_input_devices_mock()
+    // 读取ABS配置信息
+    let abs_codes = bits_to_codes(&abs_bits);
+    let abs_infos = read_abs_infos(fd, &abs_codes);
+    
+    // 读取设备名称、物理路径和唯一标识
+    let name = read_ioctl_string(fd, eviocgname()).unwrap_or_else(|| "Unknown".to_string());
+    let phys = read_ioctl_string(fd, eviocgphys()).unwrap_or_default();
+    let uniq = read_ioctl_string(fd, eviocguniq()).unwrap_or_default();
+    
+    // 读取输入ID
+    let mut input_id = InputId::default();
+    let _result = unsafe {
+        libc::ioctl(fd, eviocgid() as _, &mut input_id as *mut _ as usize)
+    };
+    
+    // 读取驱动版本
+    let mut version = 0i32;
+    let _result = unsafe {
+        libc::ioctl(fd, eviocgversion() as _, &mut version as *mut _ as usize)
+    };
+    
+    let info = DeviceInfo {
+        driver_version: version,
+        phys,
+        uniq,
+        sysfs_path: sysfs_path_for(path),
+        abs_infos,
+        events: bits_to_codes(&ev_bits),
+        properties: bits_to_codes(&prop_bits),
+        keys: bits_to_codes(&key_bits),
+        rels: bits_to_codes(&rel_bits),
+        switches: bits_to_codes(&sw_bits),
+        mock: false,
+        profile: None,
+        event_node: None,
+        abs_unknown: false,
+    };
+    let device = build_input_device(name, path_str.clone(), input_id, info, device_file);
+    
+    debug!("scan_input_devices: read device {} ({}) with {} slots", path_str, device.name, device.slots);
+    Some(device)
+}
+
+// 设备的稳定标识：驱动重新加载后event节点编号可能变化，但名称和vendor/product不变
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceIdentity {
+    pub name: String,
+    pub vendor: u16,
+    pub product: u16,
+}
+
+impl DeviceIdentity {
+    pub fn of(device: &InputDevice) -> Self {
+        Self {
+            name: device.name.clone(),
+            vendor: device.input_id.vendor,
+            product: device.input_id.product,
+        }
+    }
+
+    pub fn matches(&self, device: &InputDevice) -> bool {
+        *self == Self::of(device)
     }
 }
 
-// 辅助函数：检查是否有特定的ABS
-fn has_specific_abs(abs_bits: &[u8], abs_code: u16) -> bool {
-    let byte_index = (abs_code / 8) as usize;
-    let bit_index = (abs_code % 8) as usize;
-    if byte_index < abs_bits.len() {
-        (abs_bits[byte_index] & (1 << bit_index)) != 0
-    } else {
-        false
+// 重新扫描并按稳定标识查找设备
+pub fn find_device_by_identity(identity: &DeviceIdentity) -> Option<InputDevice> {
+    debug!("find_device_by_identity: looking for {:?}", identity);
+    let entries = fs::read_dir("/dev/input").ok()?;
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with("event"))
+        .filter_map(|entry| probe_device(&entry.path()))
+        .find(|device| identity.matches(device))
+}
+
+// 逐个读取ABS轴的AbsInfo，读取失败的轴跳过
+pub(crate) fn read_abs_infos(fd: RawFd, abs_codes: &[u16]) -> BTreeMap<u16, AbsInfo> {
+    let mut abs_infos = BTreeMap::new();
+    for &abs_code in abs_codes {
+        let mut abs_info = AbsInfo::default();
+        let result = unsafe {
+            libc::ioctl(fd, eviocgabs(abs_code as u32) as _, &mut abs_info as *mut _ as usize)
+        };
+        if result != -1 {
+            abs_infos.insert(abs_code, abs_info);
+        }
     }
+    abs_infos
 }
 
-// 辅助函数：检查是否有特定的PROP
-fn has_specific_prop(prop_bits: &[u8], prop_code: u16) -> bool {
-    let byte_index = (prop_code / 8) as usize;
-    let bit_index = (prop_code % 8) as usize;
-    if byte_index < prop_bits.len() {
-        (prop_bits[byte_index] & (1 << bit_index)) != 0
-    } else {
-        false
+// 由元数据推导出触摸相关字段，各发现后端共用
+pub(crate) fn build_input_device(
+    name: String,
+    path: String,
+    input_id: InputId,
+    info: DeviceInfo,
+    file: File,
+) -> InputDevice {
+    let mut slots = 0i32;
+    let mut touch_x_min = 0i32;
+    let mut touch_x_max = 0i32;
+    let mut touch_y_min = 0i32;
+    let mut touch_y_max = 0i32;
+    
+    // 提取关键信息
+    for (&abs_code, abs_info) in &info.abs_infos {
+        match abs_code {
+            ABS_MT_SLOT => {
+                slots = abs_info.maximum + 1;
+            }
+            ABS_MT_TRACKING_ID if abs_info.maximum == abs_info.minimum => {
+                // 特殊处理，与Go版本一致
+            }
+            ABS_MT_POSITION_X => {
+                touch_x_min = abs_info.minimum;
+                touch_x_max = abs_info.maximum - abs_info.minimum + 1;
+            }
+            ABS_MT_POSITION_Y => {
+                touch_y_min = abs_info.minimum;
+                touch_y_max = abs_info.maximum - abs_info.minimum + 1;
+            }
+            _ => {}
+        }
+    }
+    
+    InputDevice {
+        name,
+        path,
+        input_id,
+        slots,
+        touch_x_min,
+        touch_x_max,
+        touch_y_min,
+        touch_y_max,
+        has_touch_major: info.abs_infos.contains_key(&ABS_MT_TOUCH_MAJOR),
+        has_touch_minor: info.abs_infos.contains_key(&ABS_MT_TOUCH_MINOR),
+        has_width_major: info.abs_infos.contains_key(&ABS_MT_WIDTH_MAJOR),
+        has_width_minor: info.abs_infos.contains_key(&ABS_MT_WIDTH_MINOR),
+        has_orientation: info.abs_infos.contains_key(&ABS_MT_ORIENTATION),
+        has_pressure: info.abs_infos.contains_key(&ABS_MT_PRESSURE),
+        info,
+        file: Arc::new(Mutex::new(file)),
     }
 }
 
-// 辅助函数：检查是否有特定的KEY
-fn has_specific_key(key_bits: &[u8], key_code: u16) -> bool {
-    let byte_index = (key_code / 8) as usize;
-    let bit_index = (key_code % 8) as usize;
-    if byte_index < key_bits.len() {
-        (key_bits[byte_index] & (1 << bit_index)) != 0
-    } else {
-        false
+// 读取EVIOCGNAME/EVIOCGPHYS/EVIOCGUNIQ这类字符串ioctl
+fn read_ioctl_string(fd: RawFd, request: u32) -> Option<String> {
+    let mut bytes = [0u8; UINPUT_MAX_NAME_SIZE];
+    let result = unsafe {
+        libc::ioctl(fd, request as _, &mut bytes as *mut _ as usize)
+    };
+    if result == -1 {
+        return None;
     }
+    let len = bytes.iter().position(|&b| b == 0).unwrap_or(UINPUT_MAX_NAME_SIZE);
+    Some(String::from_utf8_lossy(&bytes[..len]).to_string())
 }
 
-// 原有的简化函数，用于回退
-pub fn get_input_devices_mock() -> Result<Vec<InputDevice>, Box<dyn std::error::Error>> {
-    println!("get_input_devices_mock: creating mock input device");
-    
-    // 创建一个模拟的触摸设备，就像Go实现中如果没有找到设备时的行为
-    let mock_device = InputDevice {
-        name: "MockTouchDevice".to_string(),
-        path: "/dev/input/event0".to_string(),
-        slots: 10,
-        touch_x_min: 0,
-        touch_x_max: 1080,
-        touch_y_min: 0,
-        touch_y_max: 2340,
-        has_touch_major: true,
-        has_touch_minor: true,
-        has_width_major: true,
-        has_width_minor: true,
-        has_orientation: true,
-        has_pressure: true,
-        file: Arc::new(Mutex::new(std::fs::File::open("/dev/null")?)),
+// 能容纳count个位的位图字节数，按long对齐
+const fn bitmap_len(count: usize) -> usize {
+    count.div_ceil(64) * 8
+}
+
+// 把位图展开为置位的编码列表
+pub(crate) fn bits_to_codes(bits: &[u8]) -> Vec<u16> {
+    (0..bits.len() * 8)
+        .filter(|&i| bits[i / 8] & (1 << (i % 8)) != 0)
+        .map(|i| i as u16)
+        .collect()
+}
+
+// /dev/input/eventN对应的sysfs目录，即/sys/devices/.../inputX
+pub(crate) fn sysfs_path_for(path: &Path) -> String {
+    let Some(node) = path.file_name() else {
+        return String::new();
     };
-    
-    println!("get_input_devices_mock: created mock device");
-    Ok(vec![mock_device])
-}
\ No newline at end of file
+    fs::canonicalize(Path::new("/sys/class/input").join(node))
+        .ok()
+        .and_then(|real| real.parent().map(|p| p.to_string_lossy().to_string()))
+        .unwrap_or_default()
+}
+
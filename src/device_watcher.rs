@@ -0,0 +1,327 @@
+use log::{debug, info};
+use std::{
+    ffi::CString,
+    fs::OpenOptions,
+    io,
+    os::unix::fs::OpenOptionsExt,
+    os::unix::io::RawFd,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+const INPUT_DIR: &str = "/dev/input";
+const POLL_TIMEOUT_MS: i32 = 200;
+// 节点刚创建时ueventd可能还没设置好权限，等它可以打开后再通知
+const NODE_READY_RETRIES: usize = 10;
+const NODE_READY_INTERVAL: Duration = Duration::from_millis(100);
+// inotify_event头部：wd、mask、cookie、len各4字节
+const INOTIFY_HEADER_SIZE: usize = 16;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeviceEvent {
+    Added(String),
+    Removed(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WatchSource {
+    // 监听/dev/input目录的节点创建/删除
+    Inotify,
+    // 监听内核uevent广播，不依赖ueventd/udev创建节点
+    Netlink,
+}
+
+// 后台线程监听输入设备热插拔，事件通过channel交给调用方
+pub struct DeviceWatcher {
+    receiver: mpsc::Receiver<DeviceEvent>,
+    stop: Arc<AtomicBool>,
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+impl DeviceWatcher {
+    pub fn start(source: WatchSource) -> io::Result<Self> {
+        info!("DeviceWatcher::start: source={:?}", source);
+        let fd = match source {
+            WatchSource::Inotify => open_inotify(INPUT_DIR)?,
+            WatchSource::Netlink => open_uevent_socket()?,
+        };
+
+        let (sender, receiver) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_clone = Arc::clone(&stop);
+        let handle = thread::spawn(move || {
+            watch_loop(fd, source, sender, stop_clone);
+        });
+
+        Ok(Self {
+            receiver,
+            stop,
+            handle: Some(handle),
+        })
+    }
+
+    pub fn try_recv(&self) -> Option<DeviceEvent> {
+        self.receiver.try_recv().ok()
+    }
+
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<DeviceEvent> {
+        self.receiver.recv_timeout(timeout).ok()
+    }
+}
+
+impl Drop for DeviceWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn open_inotify(dir: &str) -> io::Result<RawFd> {
+    let fd = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+    if fd == -1 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let c_dir = CString::new(dir).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let mask = libc::IN_CREATE | libc::IN_DELETE | libc::IN_MOVED_TO | libc::IN_MOVED_FROM;
+    let wd = unsafe { libc::inotify_add_watch(fd, c_dir.as_ptr(), mask) };
+    if wd == -1 {
+        let err = io::Error::last_os_error();
+        unsafe { libc::close(fd) };
+        return Err(err);
+    }
+    Ok(fd)
+}
+
+fn open_uevent_socket() -> io::Result<RawFd> {
+    let fd = unsafe {
+        libc::socket(
+            libc::AF_NETLINK,
+            libc::SOCK_DGRAM | libc::SOCK_CLOEXEC | libc::SOCK_NONBLOCK,
+            libc::NETLINK_KOBJECT_UEVENT,
+        )
+    };
+    if fd == -1 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let mut addr: libc::sockaddr_nl = unsafe { std::mem::zeroed() };
+    addr.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+    addr.nl_groups = 1; // 内核广播组
+    let result = unsafe {
+        libc::bind(
+            fd,
+            &addr as *const libc::sockaddr_nl as *const libc::sockaddr,
+            std::mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+        )
+    };
+    if result == -1 {
+        let err = io::Error::last_os_error();
+        unsafe { libc::close(fd) };
+        return Err(err);
+    }
+    Ok(fd)
+}
+
+fn watch_loop(fd: RawFd, source: WatchSource, sender: mpsc::Sender<DeviceEvent>, stop: Arc<AtomicBool>) {
+    debug!("watch_loop: started");
+    let mut buffer = [0u8; 8192];
+
+    'outer: while !stop.load(Ordering::SeqCst) {
+        let mut pfd = libc::pollfd {
+            fd,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let ready = unsafe { libc::poll(&mut pfd, 1, POLL_TIMEOUT_MS) };
+        if ready <= 0 {
+            continue;
+        }
+
+        let n = unsafe { libc::read(fd, buffer.as_mut_ptr() as *mut libc::c_void, buffer.len()) };
+        if n <= 0 {
+            continue;
+        }
+
+        let data = &buffer[..n as usize];
+        let events = match source {
+            WatchSource::Inotify => parse_inotify_events(data),
+            WatchSource::Netlink => parse_uevent(data).into_iter().collect(),
+        };
+        for event in events {
+            if let DeviceEvent::Added(path) = &event {
+                if !wait_until_readable(path, &stop) {
+                    debug!("watch_loop: {} is not readable yet", path);
+                }
+            }
+            info!("watch_loop: {:?}", event);
+            if sender.send(event).is_err() {
+                break 'outer;
+            }
+        }
+    }
+
+    unsafe { libc::close(fd) };
+    debug!("watch_loop: stopped");
+}
+
+// 在监听线程上等待，处理事件的一方只需要打开一次
+fn wait_until_readable(path: &str, stop: &AtomicBool) -> bool {
+    for _ in 0..NODE_READY_RETRIES {
+        if OpenOptions::new().read(true).custom_flags(libc::O_NONBLOCK).open(path).is_ok() {
+            return true;
+        }
+        if stop.load(Ordering::SeqCst) {
+            break;
+        }
+        thread::sleep(NODE_READY_INTERVAL);
+    }
+    false
+}
+
+// 解析inotify事件缓冲区，只关心event*节点
+fn parse_inotify_events(data: &[u8]) -> Vec<DeviceEvent> {
+    let mut events = Vec::new();
+    let mut offset = 0;
+
+    while offset + INOTIFY_HEADER_SIZE <= data.len() {
+        let mask = u32::from_ne_bytes(data[offset + 4..offset + 8].try_into().unwrap());
+        let len = u32::from_ne_bytes(data[offset + 12..offset + 16].try_into().unwrap()) as usize;
+        let name_start = offset + INOTIFY_HEADER_SIZE;
+        let name_end = (name_start + len).min(data.len());
+        offset = name_start + len;
+
+        let raw_name = &data[name_start..name_end];
+        let name_len = raw_name.iter().position(|&b| b == 0).unwrap_or(raw_name.len());
+        let name = String::from_utf8_lossy(&raw_name[..name_len]);
+        if !name.starts_with("event") {
+            continue;
+        }
+
+        let path = format!("{}/{}", INPUT_DIR, name);
+        if mask & (libc::IN_CREATE | libc::IN_MOVED_TO) != 0 {
+            events.push(DeviceEvent::Added(path));
+        } else if mask & (libc::IN_DELETE | libc::IN_MOVED_FROM) != 0 {
+            events.push(DeviceEvent::Removed(path));
+        }
+    }
+    events
+}
+
+// 解析内核uevent消息："ACTION@DEVPATH\0KEY=VALUE\0..."
+fn parse_uevent(data: &[u8]) -> Option<DeviceEvent> {
+    let mut action = None;
+    let mut subsystem = None;
+    let mut devname = None;
+
+    for field in data.split(|&b| b == 0).skip(1) {
+        let field = String::from_utf8_lossy(field);
+        if let Some((key, value)) = field.split_once('=') {
+            match key {
+                "ACTION" => action = Some(value.to_string()),
+                "SUBSYSTEM" => subsystem = Some(value.to_string()),
+                "DEVNAME" => devname = Some(value.to_string()),
+                _ => {}
+            }
+        }
+    }
+
+    if subsystem.as_deref() != Some("input") {
+        return None;
+    }
+    let devname = devname?;
+    let name = devname.rsplit('/').next()?;
+    if !name.starts_with("event") {
+        return None;
+    }
+
+    let path = format!("{}/{}", INPUT_DIR, name);
+    match action.as_deref() {
+        Some("add") => Some(DeviceEvent::Added(path)),
+        Some("remove") => Some(DeviceEvent::Removed(path)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 按内核格式拼一条inotify_event，name补0到4字节对齐
+    fn inotify_record(mask: u32, name: &str) -> Vec<u8> {
+        let len = if name.is_empty() { 0 } else { (name.len() + 1).div_ceil(4) * 4 };
+        let mut record = Vec::new();
+        record.extend_from_slice(&1i32.to_ne_bytes());
+        record.extend_from_slice(&mask.to_ne_bytes());
+        record.extend_from_slice(&0u32.to_ne_bytes());
+        record.extend_from_slice(&(len as u32).to_ne_bytes());
+        let mut raw_name = name.as_bytes().to_vec();
+        raw_name.resize(len, 0);
+        record.extend_from_slice(&raw_name);
+        record
+    }
+
+    #[test]
+    fn inotify_add_remove_and_unrelated_nodes() {
+        let data: Vec<u8> = [
+            inotify_record(libc::IN_CREATE, "event3"),
+            inotify_record(libc::IN_CREATE, "mouse0"),
+            inotify_record(libc::IN_MOVED_TO, "event12"),
+            inotify_record(libc::IN_DELETE, "event3"),
+            inotify_record(libc::IN_MOVED_FROM, "event4"),
+            inotify_record(libc::IN_DELETE, "js0"),
+            // 目录本身的事件没有名字
+            inotify_record(libc::IN_DELETE_SELF, ""),
+        ]
+        .concat();
+        assert_eq!(
+            parse_inotify_events(&data),
+            vec![
+                DeviceEvent::Added("/dev/input/event3".to_string()),
+                DeviceEvent::Added("/dev/input/event12".to_string()),
+                DeviceEvent::Removed("/dev/input/event3".to_string()),
+                DeviceEvent::Removed("/dev/input/event4".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn inotify_truncated_record_is_ignored() {
+        let record = inotify_record(libc::IN_CREATE, "event3");
+        assert!(parse_inotify_events(&record[..INOTIFY_HEADER_SIZE - 1]).is_empty());
+        assert!(parse_inotify_events(&[]).is_empty());
+    }
+
+    fn uevent(action: &str, fields: &[&str]) -> Vec<u8> {
+        let mut data = format!("{}@/devices/platform/soc/fts/input/input3/event3\0", action).into_bytes();
+        for field in fields {
+            data.extend_from_slice(field.as_bytes());
+            data.push(0);
+        }
+        data
+    }
+
+    #[test]
+    fn uevent_add_and_remove_for_event_nodes() {
+        let add = uevent("add", &["ACTION=add", "SUBSYSTEM=input", "DEVNAME=input/event3", "MAJOR=13", "MINOR=67"]);
+        assert_eq!(parse_uevent(&add), Some(DeviceEvent::Added("/dev/input/event3".to_string())));
+        let remove = uevent("remove", &["ACTION=remove", "DEVNAME=input/event3", "SUBSYSTEM=input"]);
+        assert_eq!(parse_uevent(&remove), Some(DeviceEvent::Removed("/dev/input/event3".to_string())));
+    }
+
+    #[test]
+    fn uevent_ignores_unrelated_records() {
+        // inputN本身没有DEVNAME
+        assert_eq!(parse_uevent(&uevent("add", &["ACTION=add", "SUBSYSTEM=input", "PRODUCT=18/2808/1015/1"])), None);
+        assert_eq!(parse_uevent(&uevent("add", &["ACTION=add", "SUBSYSTEM=input", "DEVNAME=input/mouse0"])), None);
+        assert_eq!(parse_uevent(&uevent("add", &["ACTION=add", "SUBSYSTEM=block", "DEVNAME=event3"])), None);
+        assert_eq!(parse_uevent(&uevent("change", &["ACTION=change", "SUBSYSTEM=input", "DEVNAME=input/event3"])), None);
+        assert_eq!(parse_uevent(b"libudev\0\xfe\xed\xca\xfe"), None);
+    }
+}
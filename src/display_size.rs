@@ -0,0 +1,186 @@
+use crate::gesture::Rotation;
+use serde::{Deserialize, Serialize};
+use std::{fmt, fs, process::Command};
+
+const FB0_VIRTUAL_SIZE: &str = "/sys/class/graphics/fb0/virtual_size";
+const DRM_CONNECTORS: &str = "/sys/class/drm/card*-*";
+
+// 显示尺寸的来源，按DEFAULT_SOURCES的顺序尝试
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DisplaySource {
+    // 配置文件或--display指定
+    Explicit,
+    // Android的wm size，有Override size时优先使用
+    WmSize,
+    // dumpsys display中内置屏幕的DisplayDeviceInfo
+    Dumpsys,
+    // /sys/class/graphics/fb0/virtual_size
+    Framebuffer,
+    // 已连接的DRM connector的首选模式
+    Drm,
+    // 都失败时使用参考设备的坐标范围
+    Digitizer,
+}
+
+pub const DEFAULT_SOURCES: [DisplaySource; 4] =
+    [DisplaySource::WmSize, DisplaySource::Dumpsys, DisplaySource::Framebuffer, DisplaySource::Drm];
+
+impl fmt::Display for DisplaySource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            DisplaySource::Explicit => "explicit",
+            DisplaySource::WmSize => "wm size",
+            DisplaySource::Dumpsys => "dumpsys display",
+            DisplaySource::Framebuffer => FB0_VIRTUAL_SIZE,
+            DisplaySource::Drm => "drm connector",
+            DisplaySource::Digitizer => "digitizer range",
+        };
+        f.write_str(name)
+    }
+}
+
+// 显示尺寸（像素）以及它的来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DisplaySize {
+    pub width: i32,
+    pub height: i32,
+    pub source: DisplaySource,
+}
+
+impl DisplaySource {
+    // 只尝试这一个来源，命令不存在或文件不可读时返回None
+    pub fn detect(self) -> Option<(i32, i32)> {
+        match self {
+            DisplaySource::WmSize => command_output("wm", &["size"]).and_then(|out| parse_wm_size(&out)),
+            DisplaySource::Dumpsys => command_output("dumpsys", &["display"]).and_then(|out| parse_dumpsys_display(&out)),
+            DisplaySource::Framebuffer => fs::read_to_string(FB0_VIRTUAL_SIZE).ok().and_then(|text| parse_pair(&text, ',')),
+            DisplaySource::Drm => detect_drm(),
+            DisplaySource::Explicit | DisplaySource::Digitizer => None,
+        }
+    }
+}
+
+// 按顺序尝试各个来源，返回第一个成功的结果（自然方向）
+pub fn detect_display_size(sources: &[DisplaySource]) -> Option<DisplaySize> {
+    sources.iter().find_map(|&source| {
+        let (width, height) = source.detect()?;
+        Some(DisplaySize { width, height, source })
+    })
+}
+
+// 得到当前方向下的显示尺寸：显式指定的值已经是当前方向，
+// 检测结果和参考设备的坐标范围是自然方向，旋转90/270度时交换宽高
+pub fn resolve_display_size(
+    explicit: Option<(i32, i32)>,
+    sources: &[DisplaySource],
+    rotation: Rotation,
+    digitizer: (i32, i32),
+) -> DisplaySize {
+    if let Some((width, height)) = explicit {
+        return DisplaySize {
+            width,
+            height,
+            source: DisplaySource::Explicit,
+        };
+    }
+    let natural = detect_display_size(sources).unwrap_or(DisplaySize {
+        width: digitizer.0,
+        height: digitizer.1,
+        source: DisplaySource::Digitizer,
+    });
+    match rotation {
+        Rotation::Rotation90 | Rotation::Rotation270 => DisplaySize {
+            width: natural.height,
+            height: natural.width,
+            ..natural
+        },
+        Rotation::Rotation0 | Rotation::Rotation180 => natural,
+    }
+}
+
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+// "1080x2400"、"1080,2400"这类以分隔符连接的正整数对
+fn parse_pair(text: &str, separator: char) -> Option<(i32, i32)> {
+    let (width, height) = text.trim().split_once(separator)?;
+    let width: i32 = width.trim().parse().ok()?;
+    let height: i32 = height.trim().parse().ok()?;
+    (width > 0 && height > 0).then_some((width, height))
+}
+
+// Physical size: 1080x2400
+// Override size: 720x1600
+pub fn parse_wm_size(output: &str) -> Option<(i32, i32)> {
+    let size = |prefix: &str| {
+        output
+            .lines()
+            .find_map(|line| line.trim().strip_prefix(prefix))
+            .and_then(|value| parse_pair(value, 'x'))
+    };
+    size("Override size:").or_else(|| size("Physical size:"))
+}
+
+// DisplayDeviceInfo{"Built-in Screen": uniqueId="local:0", 1080 x 2400, modeId 1, ...}
+// 第一个DisplayDeviceInfo是内置屏幕，尺寸与当前旋转无关
+pub fn parse_dumpsys_display(output: &str) -> Option<(i32, i32)> {
+    let line = output.lines().find(|line| line.contains("DisplayDeviceInfo{"))?;
+    line.split(", ").find_map(|field| {
+        let (width, height) = field.trim().split_once(" x ")?;
+        parse_pair(&format!("{}x{}", width, height), 'x')
+    })
+}
+
+// DRM connector的modes文件每行一个模式，第一行是首选模式，例如1920x1080或1920x1080i
+pub fn parse_drm_modes(modes: &str) -> Option<(i32, i32)> {
+    let mode = modes.lines().next()?.trim().trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    parse_pair(mode, 'x')
+}
+
+fn detect_drm() -> Option<(i32, i32)> {
+    glob::glob(DRM_CONNECTORS).ok()?.flatten().find_map(|connector| {
+        let status = fs::read_to_string(connector.join("status")).ok()?;
+        if status.trim() != "connected" {
+            return None;
+        }
+        parse_drm_modes(&fs::read_to_string(connector.join("modes")).ok()?)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_wm_size_and_prefers_override() {
+        assert_eq!(parse_wm_size("Physical size: 1080x2400\n"), Some((1080, 2400)));
+        assert_eq!(parse_wm_size("Physical size: 1080x2400\nOverride size: 720x1600\n"), Some((720, 1600)));
+        assert_eq!(parse_wm_size("Error: no display\n"), None);
+    }
+
+    #[test]
+    fn parses_dumpsys_display_and_sysfs_formats() {
+        let dumpsys = "DISPLAY MANAGER (dumpsys display)\n\
+            Display Devices: size=1\n  \
+            DisplayDeviceInfo{\"Built-in Screen\": uniqueId=\"local:0\", 1440 x 3200, modeId 1, defaultModeId 1}\n";
+        assert_eq!(parse_dumpsys_display(dumpsys), Some((1440, 3200)));
+        assert_eq!(parse_pair("1080,2340\n", ','), Some((1080, 2340)));
+        assert_eq!(parse_drm_modes("1920x1080i\n1280x720\n"), Some((1920, 1080)));
+        assert_eq!(parse_drm_modes(""), None);
+    }
+
+    #[test]
+    fn explicit_size_wins_and_fallback_follows_rotation() {
+        let explicit = resolve_display_size(Some((2400, 1080)), &DEFAULT_SOURCES, Rotation::Rotation90, (1080, 2400));
+        assert_eq!((explicit.width, explicit.height, explicit.source), (2400, 1080, DisplaySource::Explicit));
+
+        let fallback = resolve_display_size(None, &[], Rotation::Rotation270, (1080, 2400));
+        assert_eq!((fallback.width, fallback.height, fallback.source), (2400, 1080, DisplaySource::Digitizer));
+    }
+}
@@ -0,0 +1,453 @@
+use crate::uinput_defs::*;
+use log::{warn};
+use serde::Serialize;
+use serde_json::json;
+use std::{collections::BTreeMap, io::Read};
+
+// input_event的记录长度：64位内核的timeval为两个i64（24字节），32位为两个i32（16字节）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordFormat {
+    Long64,
+    Long32,
+}
+
+impl RecordFormat {
+    pub const NATIVE: RecordFormat = if std::mem::size_of::<InputEvent>() == 24 {
+        RecordFormat::Long64
+    } else {
+        RecordFormat::Long32
+    };
+
+    pub fn size(self) -> usize {
+        match self {
+            RecordFormat::Long64 => 24,
+            RecordFormat::Long32 => 16,
+        }
+    }
+
+    pub fn from_size(size: usize) -> Option<Self> {
+        match size {
+            24 => Some(RecordFormat::Long64),
+            16 => Some(RecordFormat::Long32),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct DecodedEvent {
+    pub sec: i64,
+    pub usec: i64,
+    pub event_type: u16,
+    pub code: u16,
+    pub value: i32,
+}
+
+impl DecodedEvent {
+    pub fn time_us(&self) -> u64 {
+        (self.sec.max(0) as u64) * 1_000_000 + self.usec.max(0) as u64
+    }
+
+    pub fn type_name(&self) -> String {
+        ev_type_name(self.event_type)
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("{:04x}", self.event_type))
+    }
+
+    pub fn code_name(&self) -> String {
+        event_code_name(self.event_type, self.code)
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("{:04x}", self.code))
+    }
+
+    // 与`getevent -lt`的输出格式一致
+    pub fn to_getevent_line(&self) -> String {
+        let value = match (self.event_type, self.value) {
+            (EV_KEY, 0) => "UP".to_string(),
+            (EV_KEY, 1) => "DOWN".to_string(),
+            (EV_KEY, 2) => "REPEAT".to_string(),
+            (_, value) => format!("{:08x}", value as u32),
+        };
+        format!(
+            "[{:>8}.{:06}] {:<12} {:<20} {}",
+            self.sec, self.usec, self.type_name(), self.code_name(), value
+        )
+    }
+
+    fn to_json(self) -> serde_json::Value {
+        json!({
+            "type": self.type_name(),
+            "code": self.code_name(),
+            "value": self.value,
+        })
+    }
+}
+
+// 解码一条记录，按长度区分24/16字节格式，字节序与write_event一致（小端）
+pub fn decode_record(record: &[u8]) -> DecodedEvent {
+    let (sec, usec) = if record.len() >= 24 {
+        (
+            i64::from_le_bytes(record[0..8].try_into().unwrap()),
+            i64::from_le_bytes(record[8..16].try_into().unwrap()),
+        )
+    } else {
+        (
+            i32::from_le_bytes(record[0..4].try_into().unwrap()) as i64,
+            i32::from_le_bytes(record[4..8].try_into().unwrap()) as i64,
+        )
+    };
+    let n = record.len();
+    DecodedEvent {
+        sec,
+        usec,
+        event_type: u16::from_le_bytes([record[n - 8], record[n - 7]]),
+        code: u16::from_le_bytes([record[n - 6], record[n - 5]]),
+        value: i32::from_le_bytes(record[n - 4..].try_into().unwrap()),
+    }
+}
+
+// 末尾不完整的记录被忽略
+pub fn decode_records(bytes: &[u8], format: RecordFormat) -> Vec<DecodedEvent> {
+    bytes.chunks_exact(format.size()).map(decode_record).collect()
+}
+
+// 一个slot的当前状态，tracking_id为-1表示没有触点；轴的值在触点之间保留，与内核一致
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlotState {
+    pub tracking_id: i32,
+    pub axes: BTreeMap<u16, i32>,
+}
+
+impl Default for SlotState {
+    fn default() -> Self {
+        Self {
+            tracking_id: -1,
+            axes: BTreeMap::new(),
+        }
+    }
+}
+
+// 以SYN_REPORT结束的一帧，以及该帧结束时重建出的触点状态
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub index: usize,
+    pub events: Vec<DecodedEvent>,
+    // Type B：活动的slot
+    pub slots: BTreeMap<i32, SlotState>,
+    // Type A：本帧以SYN_MT_REPORT分隔的触点
+    pub contacts: Vec<BTreeMap<u16, i32>>,
+    pub btn_touch: bool,
+    // 帧内出现SYN_DROPPED，之后的事件到SYN_REPORT为止被内核丢弃，状态可能不准确
+    pub dropped: bool,
+}
+
+impl Frame {
+    pub fn time_us(&self) -> u64 {
+        self.events.last().map_or(0, DecodedEvent::time_us)
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        let axes_json = |axes: &BTreeMap<u16, i32>| -> serde_json::Map<String, serde_json::Value> {
+            axes.iter()
+                .map(|(&code, &value)| (event_code_name(EV_ABS, code).map_or_else(|| format!("{:04x}", code), str::to_string), json!(value)))
+                .collect()
+        };
+        let slots: serde_json::Map<String, serde_json::Value> = self
+            .slots
+            .iter()
+            .map(|(slot, state)| {
+                let mut value = axes_json(&state.axes);
+                value.insert("tracking_id".to_string(), json!(state.tracking_id));
+                (slot.to_string(), serde_json::Value::Object(value))
+            })
+            .collect();
+        let contacts: Vec<_> = self.contacts.iter().map(axes_json).collect();
+        let events: Vec<_> = self.events.iter().map(|event| event.to_json()).collect();
+
+        json!({
+            "frame": self.index,
+            "time_us": self.time_us(),
+            "dropped": self.dropped,
+            "btn_touch": self.btn_touch,
+            "slots": slots,
+            "contacts": contacts,
+            "events": events,
+        })
+    }
+
+    pub fn print(&self, json: bool, state: bool) {
+        if json {
+            println!("{}", self.to_json());
+            return;
+        }
+        for event in &self.events {
+            println!("{}", event.to_getevent_line());
+        }
+        if !state {
+            return;
+        }
+        if self.dropped {
+            println!("    # frame {}: SYN_DROPPED, state may be stale", self.index);
+        }
+        let describe = |axes: &BTreeMap<u16, i32>| {
+            axes.iter()
+                .map(|(&code, value)| format!("{}={}", event_code_name(EV_ABS, code).unwrap_or("?"), value))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        for (slot, slot_state) in &self.slots {
+            println!("    # slot {} id={} {}", slot, slot_state.tracking_id, describe(&slot_state.axes));
+        }
+        for (index, contact) in self.contacts.iter().enumerate() {
+            println!("    # contact {} {}", index, describe(contact));
+        }
+        println!("    # frame {}: {} contacts, BTN_TOUCH={}", self.index, self.slots.len() + self.contacts.len(), self.btn_touch as i32);
+    }
+}
+
+// 按SYN_REPORT分帧并跟踪每个slot的状态
+#[derive(Debug, Default)]
+pub struct FrameDecoder {
+    frame_index: usize,
+    current_slot: i32,
+    slots: BTreeMap<i32, SlotState>,
+    events: Vec<DecodedEvent>,
+    contact: BTreeMap<u16, i32>,
+    contacts: Vec<BTreeMap<u16, i32>>,
+    // 收到过SYN_MT_REPORT即认为是Type A设备，不再维护slot状态
+    type_a: bool,
+    btn_touch: bool,
+    dropped: bool,
+    dropping: bool,
+}
+
+impl FrameDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, event: DecodedEvent) -> Option<Frame> {
+        self.events.push(event);
+        match (event.event_type, event.code) {
+            (EV_SYN, SYN_REPORT) => {
+                self.dropping = false;
+                return Some(self.take_frame());
+            }
+            (EV_SYN, SYN_DROPPED) => {
+                self.dropped = true;
+                self.dropping = true;
+            }
+            _ if self.dropping => {}
+            (EV_SYN, SYN_MT_REPORT) => {
+                if !self.type_a {
+                    self.type_a = true;
+                    self.slots.clear();
+                }
+                let contact = std::mem::take(&mut self.contact);
+                if !contact.is_empty() {
+                    self.contacts.push(contact);
+                }
+            }
+            (EV_KEY, BTN_TOUCH) => self.btn_touch = event.value != 0,
+            (EV_ABS, ABS_MT_SLOT) => self.current_slot = event.value,
+            (EV_ABS, code) if (ABS_MT_TOUCH_MAJOR..=ABS_MT_TOOL_Y).contains(&code) => {
+                if self.type_a {
+                    self.contact.insert(code, event.value);
+                    return None;
+                }
+                let slot = self.slots.entry(self.current_slot).or_default();
+                if code == ABS_MT_TRACKING_ID {
+                    slot.tracking_id = event.value;
+                } else {
+                    slot.axes.insert(code, event.value);
+                }
+                // 还没有收到SYN_MT_REPORT时同时按Type A记录，第一帧也能正确显示
+                self.contact.insert(code, event.value);
+            }
+            _ => {}
+        }
+        None
+    }
+
+    // 流结束时没有以SYN_REPORT结尾的事件
+    pub fn finish(&mut self) -> Option<Frame> {
+        if self.events.is_empty() {
+            return None;
+        }
+        Some(self.take_frame())
+    }
+
+    fn take_frame(&mut self) -> Frame {
+        if !self.type_a {
+            self.contact.clear();
+        }
+        let frame = Frame {
+            index: self.frame_index,
+            events: std::mem::take(&mut self.events),
+            slots: self
+                .slots
+                .iter()
+                .filter(|(_, state)| state.tracking_id >= 0)
+                .map(|(&slot, state)| (slot, state.clone()))
+                .collect(),
+            contacts: std::mem::take(&mut self.contacts),
+            btn_touch: self.btn_touch,
+            dropped: std::mem::take(&mut self.dropped),
+        };
+        self.frame_index += 1;
+        frame
+    }
+}
+
+// 从文件或设备节点读取事件，每解出一帧调用一次on_frame；设备节点会一直阻塞读取
+pub fn decode_stream<R: Read>(mut reader: R, format: RecordFormat, mut on_frame: impl FnMut(&Frame)) -> std::io::Result<()> {
+    let size = format.size();
+    let mut decoder = FrameDecoder::new();
+    let mut buffer = vec![0u8; size * 64];
+    let mut pending = Vec::new();
+
+    loop {
+        let n = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        pending.extend_from_slice(&buffer[..n]);
+        let complete = pending.len() / size * size;
+        for record in pending[..complete].chunks_exact(size) {
+            if let Some(frame) = decoder.push(decode_record(record)) {
+                on_frame(&frame);
+            }
+        }
+        pending.drain(..complete);
+    }
+
+    if !pending.is_empty() {
+        warn!("decode_stream: ignoring {} trailing bytes", pending.len());
+    }
+    if let Some(frame) = decoder.finish() {
+        on_frame(&frame);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(format: RecordFormat, sec: i64, usec: i64, event_type: u16, code: u16, value: i32) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        match format {
+            RecordFormat::Long64 => {
+                bytes.extend_from_slice(&sec.to_le_bytes());
+                bytes.extend_from_slice(&usec.to_le_bytes());
+            }
+            RecordFormat::Long32 => {
+                bytes.extend_from_slice(&(sec as i32).to_le_bytes());
+                bytes.extend_from_slice(&(usec as i32).to_le_bytes());
+            }
+        }
+        bytes.extend_from_slice(&event_type.to_le_bytes());
+        bytes.extend_from_slice(&code.to_le_bytes());
+        bytes.extend_from_slice(&value.to_le_bytes());
+        bytes
+    }
+
+    fn stream(format: RecordFormat, events: &[(u16, u16, i32)]) -> Vec<u8> {
+        events
+            .iter()
+            .enumerate()
+            .flat_map(|(i, &(t, c, v))| record(format, 12, 345_000 + i as i64, t, c, v))
+            .collect()
+    }
+
+    const TYPE_B: [(u16, u16, i32); 13] = [
+        (EV_ABS, ABS_MT_SLOT, 1),
+        (EV_ABS, ABS_MT_TRACKING_ID, 7),
+        (EV_ABS, ABS_MT_POSITION_X, 500),
+        (EV_ABS, ABS_MT_POSITION_Y, 900),
+        (EV_KEY, BTN_TOUCH, 1),
+        (EV_SYN, SYN_REPORT, 0),
+        (EV_ABS, ABS_MT_POSITION_X, 510),
+        (EV_SYN, SYN_REPORT, 0),
+        (EV_ABS, ABS_MT_TRACKING_ID, -1),
+        (EV_KEY, BTN_TOUCH, 0),
+        (EV_SYN, SYN_REPORT, 0),
+        (EV_ABS, ABS_MT_SLOT, 0),
+        (EV_ABS, ABS_MT_POSITION_X, 1),
+    ];
+
+    #[test]
+    fn decodes_both_record_sizes() {
+        for format in [RecordFormat::Long64, RecordFormat::Long32] {
+            let bytes = record(format, 12, 345678, EV_ABS, ABS_MT_TRACKING_ID, -1);
+            assert_eq!(bytes.len(), format.size());
+            let event = decode_record(&bytes);
+            assert_eq!(event, DecodedEvent { sec: 12, usec: 345678, event_type: EV_ABS, code: ABS_MT_TRACKING_ID, value: -1 });
+            assert_eq!(event.to_getevent_line(), "[      12.345678] EV_ABS       ABS_MT_TRACKING_ID   ffffffff");
+        }
+        let key = DecodedEvent { sec: 0, usec: 5, event_type: EV_KEY, code: KEY_BACK, value: 1 };
+        assert_eq!(key.to_getevent_line(), "[       0.000005] EV_KEY       KEY_BACK             DOWN");
+
+        // 写入uinput的记录可以按64位格式解码回来
+        let written = crate::uinput::encode_event(EV_ABS, ABS_MT_POSITION_Y, -20);
+        assert_eq!(
+            decode_record(&written),
+            DecodedEvent { sec: 0, usec: 0, event_type: EV_ABS, code: ABS_MT_POSITION_Y, value: -20 }
+        );
+    }
+
+    #[test]
+    fn reconstructs_type_b_slots() {
+        let mut frames = Vec::new();
+        decode_stream(&stream(RecordFormat::Long64, &TYPE_B)[..], RecordFormat::Long64, |frame| frames.push(frame.clone())).unwrap();
+
+        assert_eq!(frames.len(), 4);
+        assert_eq!(frames[0].events.len(), 6);
+        assert!(frames[0].btn_touch);
+        let slot = &frames[0].slots[&1];
+        assert_eq!(slot.tracking_id, 7);
+        assert_eq!(slot.axes[&ABS_MT_POSITION_X], 500);
+        assert_eq!(frames[1].slots[&1].axes[&ABS_MT_POSITION_X], 510);
+        assert_eq!(frames[1].slots[&1].axes[&ABS_MT_POSITION_Y], 900);
+        assert!(frames[2].slots.is_empty());
+        assert!(!frames[2].btn_touch);
+        // 没有SYN_REPORT结尾的事件单独成帧
+        assert_eq!(frames[3].events.len(), 2);
+        assert!(frames[3].contacts.is_empty());
+
+        let json = frames[0].to_json();
+        assert_eq!(json["slots"]["1"]["tracking_id"], 7);
+        assert_eq!(json["slots"]["1"]["ABS_MT_POSITION_Y"], 900);
+        assert_eq!(json["events"][1]["code"], "ABS_MT_TRACKING_ID");
+    }
+
+    #[test]
+    fn groups_type_a_contacts_and_marks_dropped_frames() {
+        let events = [
+            (EV_ABS, ABS_MT_POSITION_X, 10),
+            (EV_ABS, ABS_MT_POSITION_Y, 20),
+            (EV_SYN, SYN_MT_REPORT, 0),
+            (EV_ABS, ABS_MT_POSITION_X, 30),
+            (EV_ABS, ABS_MT_POSITION_Y, 40),
+            (EV_SYN, SYN_MT_REPORT, 0),
+            (EV_SYN, SYN_REPORT, 0),
+            (EV_SYN, SYN_DROPPED, 0),
+            (EV_ABS, ABS_MT_POSITION_X, 99),
+            (EV_SYN, SYN_REPORT, 0),
+        ];
+        let mut decoder = FrameDecoder::new();
+        let frames: Vec<Frame> = decode_records(&stream(RecordFormat::Long32, &events), RecordFormat::Long32)
+            .into_iter()
+            .filter_map(|event| decoder.push(event))
+            .collect();
+
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].contacts.len(), 2);
+        assert_eq!(frames[0].contacts[1][&ABS_MT_POSITION_Y], 40);
+        assert!(frames[0].slots.is_empty());
+        assert!(frames[1].dropped);
+        assert!(frames[1].contacts.is_empty());
+    }
+}
@@ -0,0 +1,77 @@
+use crate::uinput::InputDevice;
+use std::{io, thread, time::Duration};
+
+const MAX_EAGAIN_RETRIES: u32 = 3;
+const EAGAIN_BACKOFF: Duration = Duration::from_micros(200);
+
+// 事件的写入目标：uinput设备、内存缓冲或包装了其他sink的校验器
+pub trait EventSink: Send {
+    fn write_event(&mut self, event_type: u16, code: u16, value: i32) -> std::io::Result<()>;
+}
+
+impl EventSink for InputDevice {
+    fn write_event(&mut self, event_type: u16, code: u16, value: i32) -> std::io::Result<()> {
+        InputDevice::write_event(self, event_type, code, value)
+    }
+}
+
+impl<S: EventSink + ?Sized> EventSink for &mut S {
+    fn write_event(&mut self, event_type: u16, code: u16, value: i32) -> std::io::Result<()> {
+        (**self).write_event(event_type, code, value)
+    }
+}
+
+impl<S: EventSink + ?Sized> EventSink for Box<S> {
+    fn write_event(&mut self, event_type: u16, code: u16, value: i32) -> std::io::Result<()> {
+        (**self).write_event(event_type, code, value)
+    }
+}
+
+// 把事件保存在内存中，用于测试和离线校验
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MemorySink {
+    pub events: Vec<(u16, u16, i32)>,
+}
+
+impl MemorySink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl EventSink for MemorySink {
+    fn write_event(&mut self, event_type: u16, code: u16, value: i32) -> std::io::Result<()> {
+        self.events.push((event_type, code, value));
+        Ok(())
+    }
+}
+
+// uinput以O_NONBLOCK打开，内核缓冲满时write返回EAGAIN；短暂等待后重试同一个事件，
+// retries累计重试的次数，超过MAX_EAGAIN_RETRIES后把错误交给调用方
+#[derive(Debug)]
+pub struct RetryingSink<S> {
+    inner: S,
+    pub retries: u64,
+}
+
+impl<S: EventSink> RetryingSink<S> {
+    pub fn new(inner: S) -> Self {
+        Self { inner, retries: 0 }
+    }
+}
+
+impl<S: EventSink> EventSink for RetryingSink<S> {
+    fn write_event(&mut self, event_type: u16, code: u16, value: i32) -> io::Result<()> {
+        let mut attempts = 0;
+        loop {
+            match self.inner.write_event(event_type, code, value) {
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock && attempts < MAX_EAGAIN_RETRIES => {
+                    attempts += 1;
+                    self.retries += 1;
+                    thread::sleep(EAGAIN_BACKOFF);
+                }
+                result => return result,
+            }
+        }
+    }
+}
@@ -0,0 +1,430 @@
+use crate::touch_engine::{CommandSender, TouchCommand};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    sync::{Arc, Condvar, Mutex},
+    time::{Duration, Instant},
+};
+
+// 相邻两个手势步骤之间的间隔，与send_touch_move一致
+pub const STEP_INTERVAL: Duration = Duration::from_millis(15);
+pub const TAP_HOLD: Duration = Duration::from_millis(50);
+const MIN_POINT_COUNT: i32 = 2;
+const MAX_MOVE_DISTANCE: f32 = 10.0;
+
+// 单个触点的动作，坐标是显示坐标
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GestureAction {
+    Down(i32, i32),
+    Move(i32, i32),
+    Up,
+}
+
+// 每个步骤写出一帧，之后等待delay
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GestureStep {
+    pub action: GestureAction,
+    pub delay: Duration,
+}
+
+// 把两点之间拆成步长不超过10像素的中间点（含起点，不含终点）
+pub fn gen_move_points(start_x: i32, start_y: i32, end_x: i32, end_y: i32) -> Vec<(i32, i32)> {
+    move_points(start_x, start_y, end_x, end_y, MAX_MOVE_DISTANCE)
+}
+
+fn move_points(start_x: i32, start_y: i32, end_x: i32, end_y: i32, step_size: f32) -> Vec<(i32, i32)> {
+    let d_x = (end_x - start_x) as f32;
+    let d_y = (end_y - start_y) as f32;
+    let step_size = step_size.max(1.0);
+
+    let x_count = (d_x.abs() / step_size) as i32;
+    let y_count = (d_y.abs() / step_size) as i32;
+    let count = x_count.max(y_count).max(MIN_POINT_COUNT);
+
+    let act_delta_x = d_x / count as f32;
+    let act_delta_y = d_y / count as f32;
+
+    (0..count)
+        .map(|i| {
+            let x = (start_x as f32 + act_delta_x * i as f32) as i32;
+            let y = (start_y as f32 + act_delta_y * i as f32) as i32;
+            (x, y)
+        })
+        .collect()
+}
+
+pub fn swipe(start_x: i32, start_y: i32, end_x: i32, end_y: i32) -> Vec<GestureStep> {
+    GestureParams::default().swipe(start_x, start_y, end_x, end_y)
+}
+
+pub fn tap(x: i32, y: i32, hold: Duration) -> Vec<GestureStep> {
+    GestureParams::default().tap(x, y, hold)
+}
+
+// 手势生成参数，默认值与原来的固定常量一致
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GestureParams {
+    pub step_interval: Duration,
+    // 相邻两个移动点之间的最大距离（显示像素）
+    pub step_size: f32,
+    pub tap_hold: Duration,
+    pub humanize: Humanize,
+}
+
+impl Default for GestureParams {
+    fn default() -> Self {
+        Self {
+            step_interval: STEP_INTERVAL,
+            step_size: MAX_MOVE_DISTANCE,
+            tap_hold: TAP_HOLD,
+            humanize: Humanize::default(),
+        }
+    }
+}
+
+// 拟人化：给移动点和步骤间隔加上随机抖动，默认关闭
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Humanize {
+    // 中间点在x/y方向上的最大偏移（显示像素），起点和终点不抖动
+    pub position_jitter: i32,
+    pub timing_jitter: Duration,
+}
+
+impl GestureParams {
+    fn step(&self, action: GestureAction) -> GestureStep {
+        GestureStep {
+            action,
+            delay: self.step_interval,
+        }
+    }
+
+    pub fn swipe(&self, start_x: i32, start_y: i32, end_x: i32, end_y: i32) -> Vec<GestureStep> {
+        let points = move_points(start_x, start_y, end_x, end_y, self.step_size);
+        let mut steps = vec![self.step(GestureAction::Down(start_x, start_y))];
+        steps.extend(
+            points
+                .into_iter()
+                .enumerate()
+                .map(|(i, (x, y))| {
+                    let (x, y) = if i == 0 { (x, y) } else { self.humanize.jitter_point(x, y) };
+                    self.step(GestureAction::Move(x, y))
+                }),
+        );
+        steps.push(self.step(GestureAction::Move(end_x, end_y)));
+        steps.push(self.step(GestureAction::Up));
+        self.humanize.jitter_delays(&mut steps);
+        steps
+    }
+
+    pub fn tap(&self, x: i32, y: i32, hold: Duration) -> Vec<GestureStep> {
+        let mut steps = vec![
+            GestureStep {
+                action: GestureAction::Down(x, y),
+                delay: hold,
+            },
+            self.step(GestureAction::Up),
+        ];
+        self.humanize.jitter_delays(&mut steps);
+        steps
+    }
+}
+
+impl Humanize {
+    fn jitter_point(&self, x: i32, y: i32) -> (i32, i32) {
+        if self.position_jitter <= 0 {
+            return (x, y);
+        }
+        let jitter = self.position_jitter;
+        let mut rng = rand::thread_rng();
+        (x + rng.gen_range(-jitter..=jitter), y + rng.gen_range(-jitter..=jitter))
+    }
+
+    // 间隔只会变长，不会低于设定的上报间隔
+    fn jitter_delays(&self, steps: &mut [GestureStep]) {
+        if self.timing_jitter.is_zero() {
+            return;
+        }
+        let mut rng = rand::thread_rng();
+        for step in steps {
+            step.delay += self.timing_jitter.mul_f64(rng.gen_range(0.0..1.0));
+        }
+    }
+}
+
+// 显示相对于触摸屏自然方向的旋转角度，与Android的ROTATION_*一致
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "u16", into = "u16")]
+pub enum Rotation {
+    #[default]
+    Rotation0,
+    Rotation90,
+    Rotation180,
+    Rotation270,
+}
+
+impl TryFrom<u16> for Rotation {
+    type Error = String;
+
+    fn try_from(degrees: u16) -> Result<Self, Self::Error> {
+        match degrees {
+            0 => Ok(Rotation::Rotation0),
+            90 => Ok(Rotation::Rotation90),
+            180 => Ok(Rotation::Rotation180),
+            270 => Ok(Rotation::Rotation270),
+            _ => Err(format!("invalid rotation {}, expected 0, 90, 180 or 270", degrees)),
+        }
+    }
+}
+
+impl From<Rotation> for u16 {
+    fn from(rotation: Rotation) -> Self {
+        match rotation {
+            Rotation::Rotation0 => 0,
+            Rotation::Rotation90 => 90,
+            Rotation::Rotation180 => 180,
+            Rotation::Rotation270 => 270,
+        }
+    }
+}
+
+// 触摸屏坐标轴与显示自然方向不一致时的修正，先交换再翻转
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AxisTransform {
+    pub swap_xy: bool,
+    pub flip_x: bool,
+    pub flip_y: bool,
+}
+
+// 显示坐标到参考设备坐标的换算；显示尺寸是当前方向下的宽高
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CoordinateMap {
+    pub display_width: i32,
+    pub display_height: i32,
+    pub rotation: Rotation,
+    pub axes: AxisTransform,
+    pub x_min: i32,
+    pub x_max: i32,
+    pub y_min: i32,
+    pub y_max: i32,
+}
+
+impl CoordinateMap {
+    pub fn to_device(&self, x: i32, y: i32) -> (i32, i32) {
+        let (w, h) = (self.display_width.max(1), self.display_height.max(1));
+        // 先转回触摸屏自然方向下的显示坐标
+        let (x, y, w, h) = match self.rotation {
+            Rotation::Rotation0 => (x, y, w, h),
+            Rotation::Rotation90 => (h - y, x, h, w),
+            Rotation::Rotation180 => (w - x, h - y, w, h),
+            Rotation::Rotation270 => (y, w - x, h, w),
+        };
+        let (x, y, w, h) = if self.axes.swap_xy { (y, x, h, w) } else { (x, y, w, h) };
+        let x = x * self.x_max / w;
+        let y = y * self.y_max / h;
+        let x = if self.axes.flip_x { self.x_max - x } else { x };
+        let y = if self.axes.flip_y { self.y_max - y } else { y };
+        (x + self.x_min, y + self.y_min)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GestureError {
+    Cancelled,
+    // 分发线程已经退出，例如虚拟设备被停止或重建
+    EngineStopped,
+    // slot超出虚拟设备的范围，或者正在被转发的真实手指使用
+    SlotUnavailable(usize),
+}
+
+impl fmt::Display for GestureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GestureError::Cancelled => write!(f, "gesture cancelled"),
+            GestureError::EngineStopped => write!(f, "touch engine is not running"),
+            GestureError::SlotUnavailable(slot) => write!(f, "slot {} is out of range or used by a real contact", slot),
+        }
+    }
+}
+
+impl std::error::Error for GestureError {}
+
+// 同步手势的取消令牌，可以clone到其他线程（例如信号处理线程）；
+// cancel()让所有正在执行的手势在当前步骤结束等待，之前已经结束的手势不受影响
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    generation: Arc<(Mutex<u64>, Condvar)>,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        let (generation, changed) = &*self.generation;
+        *generation.lock().unwrap() += 1;
+        changed.notify_all();
+    }
+
+    // 手势开始时记录，之后用来判断是否被取消
+    pub fn generation(&self) -> u64 {
+        *self.generation.0.lock().unwrap()
+    }
+
+    // 等待delay，期间被取消时立即返回true
+    pub fn wait(&self, since: u64, delay: Duration) -> bool {
+        let (generation, changed) = &*self.generation;
+        let guard = generation.lock().unwrap();
+        let (guard, _) = changed.wait_timeout_while(guard, delay, |generation| *generation == since).unwrap();
+        *guard != since
+    }
+}
+
+// 把手势步骤转换成引擎命令，同步和异步接口共用；
+// 手势没有正常结束（取消、出错或被drop）时抬起触点，不会留下按住的手指
+#[derive(Debug)]
+pub struct GesturePlayback {
+    sender: CommandSender,
+    slot: usize,
+    map: CoordinateMap,
+    down: bool,
+    // 按上一步的delay，这一步应该开始的时间
+    due: Option<Instant>,
+}
+
+impl GesturePlayback {
+    pub fn new(sender: CommandSender, slot: usize, map: CoordinateMap) -> Self {
+        Self {
+            sender,
+            slot,
+            map,
+            down: false,
+            due: None,
+        }
+    }
+
+    pub fn apply(&mut self, step: &GestureStep) -> Result<(), GestureError> {
+        let now = Instant::now();
+        if let Some(due) = self.due {
+            self.sender.record_lateness(now.saturating_duration_since(due));
+        }
+        self.due = Some(now + step.delay);
+        let slot = self.slot;
+        let command = match step.action {
+            GestureAction::Down(x, y) => {
+                let (x, y) = self.map.to_device(x, y);
+                TouchCommand::ContactDown { slot, x, y }
+            }
+            GestureAction::Move(x, y) => {
+                let (x, y) = self.map.to_device(x, y);
+                TouchCommand::ContactMove { slot, x, y }
+            }
+            GestureAction::Up => TouchCommand::ContactUp { slot },
+        };
+        self.sender.send(command).map_err(|_| GestureError::EngineStopped)?;
+        self.sender.send(TouchCommand::Frame).map_err(|_| GestureError::EngineStopped)?;
+        self.down = step.action != GestureAction::Up;
+        Ok(())
+    }
+
+    pub fn cancel(&mut self) {
+        if self.down {
+            self.down = false;
+            let _ = self.sender.send(TouchCommand::ContactUp { slot: self.slot });
+            let _ = self.sender.send(TouchCommand::Frame);
+        }
+    }
+}
+
+impl Drop for GesturePlayback {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn swipe_steps_follow_move_points() {
+        let steps: Vec<GestureAction> = swipe(0, 0, 25, 0).into_iter().map(|step| step.action).collect();
+        assert_eq!(
+            steps,
+            vec![
+                GestureAction::Down(0, 0),
+                GestureAction::Move(0, 0),
+                GestureAction::Move(12, 0),
+                GestureAction::Move(25, 0),
+                GestureAction::Up,
+            ]
+        );
+    }
+
+    #[test]
+    fn dropped_playback_lifts_the_contact() {
+        let (sender, receiver) = CommandSender::channel(Arc::default());
+        let map = CoordinateMap {
+            display_width: 100,
+            display_height: 100,
+            rotation: Rotation::Rotation0,
+            axes: AxisTransform::default(),
+            x_min: 0,
+            x_max: 1000,
+            y_min: 0,
+            y_max: 2000,
+        };
+        let mut playback = GesturePlayback::new(sender, 9, map);
+        playback.apply(&swipe(1, 2, 3, 4)[0]).unwrap();
+        drop(playback);
+
+        let commands: Vec<String> = receiver.try_iter().map(|(_, command)| format!("{:?}", command)).collect();
+        assert_eq!(
+            commands,
+            vec!["ContactDown { slot: 9, x: 10, y: 40 }", "Frame", "ContactUp { slot: 9 }", "Frame"]
+        );
+    }
+
+    #[test]
+    fn rotated_display_maps_back_to_natural_orientation() {
+        let mut map = CoordinateMap {
+            display_width: 200,
+            display_height: 100,
+            rotation: Rotation::Rotation90,
+            axes: AxisTransform::default(),
+            x_min: 0,
+            x_max: 1000,
+            y_min: 0,
+            y_max: 2000,
+        };
+        // 横屏左下角对应自然方向的左上角
+        assert_eq!(map.to_device(0, 100), (0, 0));
+        assert_eq!(map.to_device(200, 0), (1000, 2000));
+        map.rotation = Rotation::Rotation270;
+        assert_eq!(map.to_device(200, 0), (0, 0));
+
+        // 触摸屏x轴沿显示的长边且方向相反
+        map.rotation = Rotation::Rotation0;
+        map.axes = AxisTransform { swap_xy: true, flip_x: true, flip_y: false };
+        map.display_width = 100;
+        map.display_height = 200;
+        assert_eq!(map.to_device(0, 0), (1000, 0));
+        assert_eq!(map.to_device(100, 50), (750, 2000));
+    }
+
+    #[test]
+    fn cancel_wakes_a_waiting_gesture() {
+        let token = CancelToken::new();
+        let since = token.generation();
+        let waiter = std::thread::spawn({
+            let token = token.clone();
+            move || token.wait(since, Duration::from_secs(60))
+        });
+        token.cancel();
+        assert!(waiter.join().unwrap());
+        // 取消之后开始的手势不受影响
+        assert!(!token.wait(token.generation(), Duration::from_millis(1)));
+    }
+}
@@ -0,0 +1,887 @@
+use crate::uinput::InputDevice;
+use crate::uinput_defs::*;
+use serde::Deserialize;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs::File,
+    path::Path,
+};
+
+// 转发模式下的过滤器配置，filters按数组顺序依次执行
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct FilterConfig {
+    pub filters: Vec<FilterSpec>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FilterSpec {
+    // 在这些区域内按下的触点整个被丢弃
+    DeadZone { regions: Vec<Region> },
+    // 交换/翻转X、Y轴，例如面板安装方向与显示方向不一致
+    Remap {
+        #[serde(default)]
+        swap_xy: bool,
+        #[serde(default)]
+        invert_x: bool,
+        #[serde(default)]
+        invert_y: bool,
+    },
+    // ABS_MT_TOUCH_MAJOR超过阈值的触点视为手掌，已经输出的触点补发抬起
+    PalmRejection { max_touch_major: i32 },
+    // 同一个slot抬起后window_ms内再次按下视为抖动
+    Debounce { window_ms: u64 },
+    // 以最小坐标为原点缩放
+    Scale { x: f64, y: f64 },
+    // 指数平滑，alpha越小越平滑
+    Smoothing { alpha: f64 },
+    // 每个触点移动事件的最大频率，按下和抬起不受限制
+    RateLimit { max_hz: u32 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct Region {
+    pub x_min: i32,
+    pub y_min: i32,
+    pub x_max: i32,
+    pub y_max: i32,
+}
+
+impl Region {
+    fn contains(&self, x: i32, y: i32) -> bool {
+        (self.x_min..=self.x_max).contains(&x) && (self.y_min..=self.y_max).contains(&y)
+    }
+}
+
+impl FilterConfig {
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let file = File::open(path).map_err(|e| format!("failed to open filter config {}: {}", path.display(), e))?;
+        let config = serde_json::from_reader(file)
+            .map_err(|e| format!("invalid filter config {}: {}", path.display(), e))?;
+        Ok(config)
+    }
+}
+
+// 真实设备的坐标范围，缩放和重映射都在这个范围内进行
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AxisRange {
+    pub x_min: i32,
+    pub x_max: i32,
+    pub y_min: i32,
+    pub y_max: i32,
+}
+
+impl AxisRange {
+    pub fn of(device: &InputDevice) -> Self {
+        let abs = |code: u16, min: i32, len: i32| {
+            device
+                .info
+                .abs_infos
+                .get(&code)
+                .filter(|abs| abs.maximum > abs.minimum)
+                .map_or((min, min + len), |abs| (abs.minimum, abs.maximum))
+        };
+        let (x_min, x_max) = abs(ABS_MT_POSITION_X, device.touch_x_min, device.touch_x_max);
+        let (y_min, y_max) = abs(ABS_MT_POSITION_Y, device.touch_y_min, device.touch_y_max);
+        Self { x_min, x_max, y_min, y_max }
+    }
+
+    pub fn clamp(&self, x: i32, y: i32) -> (i32, i32) {
+        (x.clamp(self.x_min, self.x_max), y.clamp(self.y_min, self.y_max))
+    }
+}
+
+// 一个slot在一帧内的变化，tracking_id为Some(-1)表示抬起
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SlotUpdate {
+    pub slot: i32,
+    pub tracking_id: Option<i32>,
+    pub axes: BTreeMap<u16, i32>,
+}
+
+impl SlotUpdate {
+    pub fn is_down(&self) -> bool {
+        self.tracking_id.is_some_and(|id| id >= 0)
+    }
+
+    pub fn is_up(&self) -> bool {
+        self.tracking_id.is_some_and(|id| id < 0)
+    }
+}
+
+// 按slot整理后的一帧，time_us取自事件时间戳
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FilterFrame {
+    pub time_us: u64,
+    pub updates: Vec<SlotUpdate>,
+}
+
+pub trait InputFilter: Send + std::fmt::Debug {
+    fn apply(&mut self, frame: &mut FilterFrame);
+
+    // 丢弃所有按slot记录的状态，SYN_DROPPED之后调用
+    fn reset(&mut self);
+
+    // 有缓存的更新时返回最早应该输出的时间
+    fn deadline(&self) -> Option<u64> {
+        None
+    }
+
+    // 把到frame.time_us为止到期的缓存更新加入frame
+    fn flush(&mut self, _frame: &mut FilterFrame) {}
+}
+
+// 按顺序执行的过滤器链，输入输出都是原始事件（不含SYN_REPORT），BTN_TOUCH按过滤后的触点重新生成
+#[derive(Debug)]
+pub struct FilterChain {
+    filters: Vec<Box<dyn InputFilter>>,
+    current_slot: i32,
+    active: HashSet<i32>,
+    button_down: bool,
+}
+
+impl FilterChain {
+    pub fn new(filters: Vec<Box<dyn InputFilter>>, current_slot: i32) -> Self {
+        Self {
+            filters,
+            current_slot,
+            active: HashSet::new(),
+            button_down: false,
+        }
+    }
+
+    pub fn from_config(config: &FilterConfig, range: AxisRange, current_slot: i32) -> Self {
+        let filters = config.filters.iter().map(|spec| build_filter(spec, range)).collect();
+        Self::new(filters, current_slot)
+    }
+
+    pub fn len(&self) -> usize {
+        self.filters.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.filters.is_empty()
+    }
+
+    pub fn apply(&mut self, events: &[(u16, u16, i32)], time_us: u64) -> Vec<(u16, u16, i32)> {
+        let mut frame = self.parse_frame(events, time_us);
+        for filter in &mut self.filters {
+            filter.apply(&mut frame);
+        }
+        self.emit(&frame)
+    }
+
+    // 事件流中断后从头开始：清空触点和按键状态，current_slot取设备当前的slot
+    pub fn reset(&mut self, current_slot: i32) {
+        self.current_slot = current_slot;
+        self.active.clear();
+        self.button_down = false;
+        for filter in &mut self.filters {
+            filter.reset();
+        }
+    }
+
+    // 过滤器缓存的更新最早需要输出的时间，与帧的time_us同一时钟
+    pub fn deadline(&self) -> Option<u64> {
+        self.filters.iter().filter_map(|filter| filter.deadline()).min()
+    }
+
+    // 没有新帧时输出到期的缓存更新，经过其后的过滤器后生成事件
+    pub fn flush(&mut self, time_us: u64) -> Vec<(u16, u16, i32)> {
+        let mut frame = FilterFrame {
+            time_us,
+            updates: Vec::new(),
+        };
+        for filter in &mut self.filters {
+            if !frame.updates.is_empty() {
+                filter.apply(&mut frame);
+            }
+            filter.flush(&mut frame);
+        }
+        self.emit(&frame)
+    }
+
+    fn emit(&mut self, frame: &FilterFrame) -> Vec<(u16, u16, i32)> {
+        let mut out = Vec::new();
+        for update in &frame.updates {
+            out.push((EV_ABS, ABS_MT_SLOT, update.slot));
+            if let Some(id) = update.tracking_id {
+                out.push((EV_ABS, ABS_MT_TRACKING_ID, id));
+                if id >= 0 {
+                    self.active.insert(update.slot);
+                } else {
+                    self.active.remove(&update.slot);
+                }
+            }
+            for (&code, &value) in &update.axes {
+                out.push((EV_ABS, code, value));
+            }
+        }
+
+        let down = !self.active.is_empty();
+        if down != self.button_down {
+            self.button_down = down;
+            out.push((EV_KEY, BTN_TOUCH, down as i32));
+        }
+        out
+    }
+
+    // 只保留MT事件，BTN_TOUCH等在输出时重新生成
+    fn parse_frame(&mut self, events: &[(u16, u16, i32)], time_us: u64) -> FilterFrame {
+        let mut frame = FilterFrame {
+            time_us,
+            updates: Vec::new(),
+        };
+        for &(event_type, code, value) in events {
+            if event_type != EV_ABS {
+                continue;
+            }
+            if code == ABS_MT_SLOT {
+                self.current_slot = value;
+                continue;
+            }
+            if !(ABS_MT_TOUCH_MAJOR..=ABS_MT_TOOL_Y).contains(&code) {
+                continue;
+            }
+
+            // 同一slot在帧内先抬起再按下时拆成两个更新
+            let slot = self.current_slot;
+            let reuse = frame
+                .updates
+                .last()
+                .is_some_and(|last| last.slot == slot && !(code == ABS_MT_TRACKING_ID && last.tracking_id.is_some()));
+            if !reuse {
+                frame.updates.push(SlotUpdate {
+                    slot,
+                    ..SlotUpdate::default()
+                });
+            }
+            let update = frame.updates.last_mut().unwrap();
+            if code == ABS_MT_TRACKING_ID {
+                update.tracking_id = Some(value);
+            } else {
+                update.axes.insert(code, value);
+            }
+        }
+        frame
+    }
+}
+
+fn build_filter(spec: &FilterSpec, range: AxisRange) -> Box<dyn InputFilter> {
+    match spec.clone() {
+        FilterSpec::DeadZone { regions } => Box::new(DeadZone::new(regions)),
+        FilterSpec::Remap { swap_xy, invert_x, invert_y } => Box::new(Remap::new(range, swap_xy, invert_x, invert_y)),
+        FilterSpec::PalmRejection { max_touch_major } => Box::new(PalmRejection::new(max_touch_major)),
+        FilterSpec::Debounce { window_ms } => Box::new(Debounce::new(window_ms)),
+        FilterSpec::Scale { x, y } => Box::new(Scale::new(range, x, y)),
+        FilterSpec::Smoothing { alpha } => Box::new(Smoothing::new(alpha)),
+        FilterSpec::RateLimit { max_hz } => Box::new(RateLimit::new(max_hz)),
+    }
+}
+
+// 拦截触点：按下时就被拦截的触点整个不输出，按下后才被拦截的触点补发抬起，之后的事件丢弃到真实抬起为止
+// reject的第一个参数是更新在帧内的下标
+fn gate_contacts(frame: &mut FilterFrame, suppressed: &mut HashSet<i32>, mut reject: impl FnMut(usize, &SlotUpdate) -> bool) {
+    let mut kept = Vec::with_capacity(frame.updates.len());
+    for (index, mut update) in frame.updates.drain(..).enumerate() {
+        if update.is_down() {
+            suppressed.remove(&update.slot);
+        }
+        if suppressed.contains(&update.slot) {
+            if update.is_up() {
+                suppressed.remove(&update.slot);
+            }
+            continue;
+        }
+        if !update.is_up() && reject(index, &update) {
+            suppressed.insert(update.slot);
+            if update.is_down() {
+                continue;
+            }
+            update.tracking_id = Some(-1);
+            update.axes.clear();
+        }
+        kept.push(update);
+    }
+    frame.updates = kept;
+}
+
+// 记录每个slot最近的X/Y，更新中没有携带的轴用它补全
+#[derive(Debug, Default)]
+struct PositionTracker {
+    positions: HashMap<i32, (i32, i32)>,
+}
+
+impl PositionTracker {
+    fn update(&mut self, update: &SlotUpdate) -> (i32, i32) {
+        let position = self.positions.entry(update.slot).or_insert((0, 0));
+        if let Some(&x) = update.axes.get(&ABS_MT_POSITION_X) {
+            position.0 = x;
+        }
+        if let Some(&y) = update.axes.get(&ABS_MT_POSITION_Y) {
+            position.1 = y;
+        }
+        *position
+    }
+}
+
+#[derive(Debug)]
+pub struct DeadZone {
+    regions: Vec<Region>,
+    positions: PositionTracker,
+    suppressed: HashSet<i32>,
+}
+
+impl DeadZone {
+    pub fn new(regions: Vec<Region>) -> Self {
+        Self {
+            regions,
+            positions: PositionTracker::default(),
+            suppressed: HashSet::new(),
+        }
+    }
+}
+
+impl InputFilter for DeadZone {
+    fn apply(&mut self, frame: &mut FilterFrame) {
+        // 内核会丢弃与上次相同的值，所以被拦截的触点也要更新位置
+        let positions: Vec<(i32, i32)> = frame.updates.iter().map(|update| self.positions.update(update)).collect();
+        let regions = &self.regions;
+        gate_contacts(frame, &mut self.suppressed, |index, update| {
+            let (x, y) = positions[index];
+            update.is_down() && regions.iter().any(|region| region.contains(x, y))
+        });
+    }
+
+    fn reset(&mut self) {
+        self.positions = PositionTracker::default();
+        self.suppressed.clear();
+    }
+}
+
+#[derive(Debug)]
+pub struct PalmRejection {
+    max_touch_major: i32,
+    suppressed: HashSet<i32>,
+}
+
+impl PalmRejection {
+    pub fn new(max_touch_major: i32) -> Self {
+        Self {
+            max_touch_major,
+            suppressed: HashSet::new(),
+        }
+    }
+}
+
+impl InputFilter for PalmRejection {
+    fn apply(&mut self, frame: &mut FilterFrame) {
+        let max_touch_major = self.max_touch_major;
+        gate_contacts(frame, &mut self.suppressed, |_, update| {
+            update.axes.get(&ABS_MT_TOUCH_MAJOR).is_some_and(|&major| major > max_touch_major)
+        });
+    }
+
+    fn reset(&mut self) {
+        self.suppressed.clear();
+    }
+}
+
+#[derive(Debug)]
+pub struct Debounce {
+    window_us: u64,
+    last_up: HashMap<i32, u64>,
+    suppressed: HashSet<i32>,
+}
+
+impl Debounce {
+    pub fn new(window_ms: u64) -> Self {
+        Self {
+            window_us: window_ms * 1000,
+            last_up: HashMap::new(),
+            suppressed: HashSet::new(),
+        }
+    }
+}
+
+impl InputFilter for Debounce {
+    fn apply(&mut self, frame: &mut FilterFrame) {
+        let time_us = frame.time_us;
+        let (window_us, last_up) = (self.window_us, &self.last_up);
+        // 只有输出过的抬起才算，被拦截的抖动不会延长窗口
+        gate_contacts(frame, &mut self.suppressed, |_, update| {
+            update.is_down() && last_up.get(&update.slot).is_some_and(|&up| time_us.saturating_sub(up) < window_us)
+        });
+        for update in frame.updates.iter().filter(|update| update.is_up()) {
+            self.last_up.insert(update.slot, time_us);
+        }
+    }
+
+    fn reset(&mut self) {
+        self.last_up.clear();
+        self.suppressed.clear();
+    }
+}
+
+#[derive(Debug)]
+pub struct Remap {
+    range: AxisRange,
+    swap_xy: bool,
+    invert_x: bool,
+    invert_y: bool,
+    positions: PositionTracker,
+}
+
+impl Remap {
+    pub fn new(range: AxisRange, swap_xy: bool, invert_x: bool, invert_y: bool) -> Self {
+        Self {
+            range,
+            swap_xy,
+            invert_x,
+            invert_y,
+            positions: PositionTracker::default(),
+        }
+    }
+}
+
+impl InputFilter for Remap {
+    fn apply(&mut self, frame: &mut FilterFrame) {
+        let range = self.range;
+        let normalize = |value: i32, min: i32, max: i32| (value - min) as f64 / (max - min).max(1) as f64;
+        let denormalize = |n: f64, min: i32, max: i32| min + (n * (max - min) as f64).round() as i32;
+
+        for update in &mut frame.updates {
+            if !update.axes.contains_key(&ABS_MT_POSITION_X) && !update.axes.contains_key(&ABS_MT_POSITION_Y) {
+                continue;
+            }
+            // 交换轴时一个轴的变化会影响两个输出轴，所以总是同时输出X和Y
+            let (x, y) = self.positions.update(update);
+            let mut nx = normalize(x, range.x_min, range.x_max);
+            let mut ny = normalize(y, range.y_min, range.y_max);
+            if self.swap_xy {
+                std::mem::swap(&mut nx, &mut ny);
+            }
+            if self.invert_x {
+                nx = 1.0 - nx;
+            }
+            if self.invert_y {
+                ny = 1.0 - ny;
+            }
+            update.axes.insert(ABS_MT_POSITION_X, denormalize(nx, range.x_min, range.x_max));
+            update.axes.insert(ABS_MT_POSITION_Y, denormalize(ny, range.y_min, range.y_max));
+        }
+    }
+
+    fn reset(&mut self) {
+        self.positions = PositionTracker::default();
+    }
+}
+
+#[derive(Debug)]
+pub struct Scale {
+    range: AxisRange,
+    x: f64,
+    y: f64,
+}
+
+impl Scale {
+    pub fn new(range: AxisRange, x: f64, y: f64) -> Self {
+        Self { range, x, y }
+    }
+}
+
+impl InputFilter for Scale {
+    fn apply(&mut self, frame: &mut FilterFrame) {
+        let range = self.range;
+        let scale = |value: i32, min: i32, max: i32, factor: f64| (min + ((value - min) as f64 * factor).round() as i32).clamp(min, max);
+        for update in &mut frame.updates {
+            if let Some(x) = update.axes.get_mut(&ABS_MT_POSITION_X) {
+                *x = scale(*x, range.x_min, range.x_max, self.x);
+            }
+            if let Some(y) = update.axes.get_mut(&ABS_MT_POSITION_Y) {
+                *y = scale(*y, range.y_min, range.y_max, self.y);
+            }
+        }
+    }
+
+    fn reset(&mut self) {}
+}
+
+#[derive(Debug)]
+pub struct Smoothing {
+    alpha: f64,
+    state: HashMap<(i32, u16), f64>,
+}
+
+impl Smoothing {
+    pub fn new(alpha: f64) -> Self {
+        Self {
+            alpha: alpha.clamp(0.0, 1.0),
+            state: HashMap::new(),
+        }
+    }
+}
+
+impl InputFilter for Smoothing {
+    fn apply(&mut self, frame: &mut FilterFrame) {
+        for update in &mut frame.updates {
+            // 新触点从原始位置开始，不与上一个触点的位置平滑
+            if update.tracking_id.is_some() {
+                self.state.retain(|&(slot, _), _| slot != update.slot);
+            }
+            for code in [ABS_MT_POSITION_X, ABS_MT_POSITION_Y] {
+                let Some(value) = update.axes.get_mut(&code) else {
+                    continue;
+                };
+                let smoothed = match self.state.get(&(update.slot, code)) {
+                    Some(&previous) => previous + self.alpha * (*value as f64 - previous),
+                    None => *value as f64,
+                };
+                self.state.insert((update.slot, code), smoothed);
+                *value = smoothed.round() as i32;
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.state.clear();
+    }
+}
+
+#[derive(Debug)]
+pub struct RateLimit {
+    interval_us: u64,
+    last_emit: HashMap<i32, u64>,
+    pending: HashMap<i32, BTreeMap<u16, i32>>,
+}
+
+impl RateLimit {
+    pub fn new(max_hz: u32) -> Self {
+        Self {
+            interval_us: 1_000_000 / u64::from(max_hz.max(1)),
+            last_emit: HashMap::new(),
+            pending: HashMap::new(),
+        }
+    }
+}
+
+impl InputFilter for RateLimit {
+    fn apply(&mut self, frame: &mut FilterFrame) {
+        let time_us = frame.time_us;
+        let mut kept = Vec::with_capacity(frame.updates.len());
+
+        for mut update in frame.updates.drain(..) {
+            let pending = self.pending.remove(&update.slot);
+            if update.is_up() {
+                // 抬起前先补发被合并掉的最后位置
+                if let Some(axes) = pending {
+                    kept.push(SlotUpdate {
+                        slot: update.slot,
+                        tracking_id: None,
+                        axes,
+                    });
+                }
+                self.last_emit.remove(&update.slot);
+                kept.push(update);
+                continue;
+            }
+
+            let too_soon = self
+                .last_emit
+                .get(&update.slot)
+                .is_some_and(|&last| time_us.saturating_sub(last) < self.interval_us);
+            if update.tracking_id.is_none() && too_soon {
+                let mut axes = pending.unwrap_or_default();
+                axes.extend(update.axes);
+                self.pending.insert(update.slot, axes);
+                continue;
+            }
+
+            if let (Some(mut axes), None) = (pending, update.tracking_id) {
+                axes.append(&mut update.axes);
+                update.axes = axes;
+            }
+            self.last_emit.insert(update.slot, time_us);
+            kept.push(update);
+        }
+        frame.updates = kept;
+    }
+
+    fn reset(&mut self) {
+        self.last_emit.clear();
+        self.pending.clear();
+    }
+
+    // 手指移动后静止时不会再有新帧，合并掉的位置到间隔结束时输出
+    fn deadline(&self) -> Option<u64> {
+        self.pending
+            .keys()
+            .filter_map(|slot| self.last_emit.get(slot))
+            .map(|&last| last + self.interval_us)
+            .min()
+    }
+
+    fn flush(&mut self, frame: &mut FilterFrame) {
+        let time_us = frame.time_us;
+        let mut due: Vec<i32> = self
+            .pending
+            .keys()
+            .copied()
+            .filter(|slot| self.last_emit.get(slot).is_some_and(|&last| time_us.saturating_sub(last) >= self.interval_us))
+            .collect();
+        due.sort_unstable();
+        for slot in due {
+            let axes = self.pending.remove(&slot).unwrap_or_default();
+            frame.updates.push(SlotUpdate {
+                slot,
+                tracking_id: None,
+                axes,
+            });
+            self.last_emit.insert(slot, time_us);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RANGE: AxisRange = AxisRange {
+        x_min: 0,
+        x_max: 1000,
+        y_min: 0,
+        y_max: 2000,
+    };
+
+    type RecordedFrame = (u64, Vec<(u16, u16, i32)>);
+
+    // 解析`getevent -lt`录制的事件流，按SYN_REPORT分帧
+    fn parse_recording(text: &str) -> Vec<RecordedFrame> {
+        let mut frames = Vec::new();
+        let mut events = Vec::new();
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let (time, rest) = line.trim_start_matches('[').split_once(']').unwrap();
+            let time_us = (time.trim().parse::<f64>().unwrap() * 1_000_000.0).round() as u64;
+            let fields: Vec<&str> = rest.split_whitespace().collect();
+            let event_type = match fields[0] {
+                "EV_SYN" => EV_SYN,
+                "EV_KEY" => EV_KEY,
+                "EV_ABS" => EV_ABS,
+                other => panic!("unknown type {}", other),
+            };
+            let code = match fields[1] {
+                "SYN_REPORT" => SYN_REPORT,
+                "BTN_TOUCH" => BTN_TOUCH,
+                "ABS_MT_SLOT" => ABS_MT_SLOT,
+                "ABS_MT_TRACKING_ID" => ABS_MT_TRACKING_ID,
+                "ABS_MT_POSITION_X" => ABS_MT_POSITION_X,
+                "ABS_MT_POSITION_Y" => ABS_MT_POSITION_Y,
+                "ABS_MT_TOUCH_MAJOR" => ABS_MT_TOUCH_MAJOR,
+                other => panic!("unknown code {}", other),
+            };
+            let value = match fields[2] {
+                "DOWN" => 1,
+                "UP" => 0,
+                hex => u32::from_str_radix(hex, 16).unwrap() as i32,
+            };
+            if event_type == EV_SYN {
+                frames.push((time_us, std::mem::take(&mut events)));
+            } else {
+                events.push((event_type, code, value));
+            }
+        }
+        frames
+    }
+
+    fn run(chain: &mut FilterChain, recording: &str) -> Vec<Vec<(u16, u16, i32)>> {
+        parse_recording(recording)
+            .into_iter()
+            .map(|(time_us, events)| chain.apply(&events, time_us))
+            .collect()
+    }
+
+    fn chain(filters: Vec<FilterSpec>) -> FilterChain {
+        FilterChain::from_config(&FilterConfig { filters }, RANGE, 0)
+    }
+
+    const TAP: &str = "
+        [   1.000000] EV_ABS       ABS_MT_TRACKING_ID   00000010
+        [   1.000000] EV_ABS       ABS_MT_POSITION_X    00000032
+        [   1.000000] EV_ABS       ABS_MT_POSITION_Y    00000064
+        [   1.000000] EV_KEY       BTN_TOUCH            DOWN
+        [   1.000000] EV_SYN       SYN_REPORT           00000000
+        [   1.010000] EV_ABS       ABS_MT_POSITION_X    00000034
+        [   1.010000] EV_SYN       SYN_REPORT           00000000
+        [   1.020000] EV_ABS       ABS_MT_TRACKING_ID   ffffffff
+        [   1.020000] EV_KEY       BTN_TOUCH            UP
+        [   1.020000] EV_SYN       SYN_REPORT           00000000
+    ";
+
+    #[test]
+    fn empty_chain_passes_contacts_through() {
+        let frames = run(&mut chain(vec![]), TAP);
+        assert_eq!(
+            frames,
+            vec![
+                vec![
+                    (EV_ABS, ABS_MT_SLOT, 0),
+                    (EV_ABS, ABS_MT_TRACKING_ID, 0x10),
+                    (EV_ABS, ABS_MT_POSITION_X, 50),
+                    (EV_ABS, ABS_MT_POSITION_Y, 100),
+                    (EV_KEY, BTN_TOUCH, 1),
+                ],
+                vec![(EV_ABS, ABS_MT_SLOT, 0), (EV_ABS, ABS_MT_POSITION_X, 52)],
+                vec![(EV_ABS, ABS_MT_SLOT, 0), (EV_ABS, ABS_MT_TRACKING_ID, -1), (EV_KEY, BTN_TOUCH, 0)],
+            ]
+        );
+    }
+
+    #[test]
+    fn dead_zone_drops_whole_contact() {
+        let regions = vec![Region { x_min: 0, y_min: 0, x_max: 60, y_max: 2000 }];
+        let frames = run(&mut chain(vec![FilterSpec::DeadZone { regions }]), TAP);
+        assert!(frames.iter().all(Vec::is_empty));
+    }
+
+    #[test]
+    fn palm_rejection_lifts_contact_that_grows() {
+        let recording = "
+            [   1.000000] EV_ABS       ABS_MT_SLOT          00000001
+            [   1.000000] EV_ABS       ABS_MT_TRACKING_ID   00000020
+            [   1.000000] EV_ABS       ABS_MT_POSITION_X    00000100
+            [   1.000000] EV_ABS       ABS_MT_TOUCH_MAJOR   00000010
+            [   1.000000] EV_SYN       SYN_REPORT           00000000
+            [   1.010000] EV_ABS       ABS_MT_TOUCH_MAJOR   00000400
+            [   1.010000] EV_SYN       SYN_REPORT           00000000
+            [   1.020000] EV_ABS       ABS_MT_POSITION_X    00000110
+            [   1.020000] EV_SYN       SYN_REPORT           00000000
+            [   1.030000] EV_ABS       ABS_MT_TRACKING_ID   ffffffff
+            [   1.030000] EV_SYN       SYN_REPORT           00000000
+        ";
+        let frames = run(&mut chain(vec![FilterSpec::PalmRejection { max_touch_major: 0x100 }]), recording);
+        assert_eq!(frames[0].len(), 5);
+        assert_eq!(frames[1], vec![(EV_ABS, ABS_MT_SLOT, 1), (EV_ABS, ABS_MT_TRACKING_ID, -1), (EV_KEY, BTN_TOUCH, 0)]);
+        assert!(frames[2].is_empty());
+        assert!(frames[3].is_empty());
+    }
+
+    #[test]
+    fn debounce_drops_quick_retouch() {
+        let recording = format!(
+            "{}
+            [   1.030000] EV_ABS       ABS_MT_TRACKING_ID   00000011
+            [   1.030000] EV_SYN       SYN_REPORT           00000000
+            [   1.040000] EV_ABS       ABS_MT_TRACKING_ID   ffffffff
+            [   1.040000] EV_SYN       SYN_REPORT           00000000
+            [   1.200000] EV_ABS       ABS_MT_TRACKING_ID   00000012
+            [   1.200000] EV_SYN       SYN_REPORT           00000000",
+            TAP
+        );
+        let frames = run(&mut chain(vec![FilterSpec::Debounce { window_ms: 50 }]), &recording);
+        assert_eq!(frames.len(), 6);
+        assert!(frames[3].is_empty());
+        assert!(frames[4].is_empty());
+        assert_eq!(frames[5][1], (EV_ABS, ABS_MT_TRACKING_ID, 0x12));
+    }
+
+    #[test]
+    fn remap_and_scale_transform_positions() {
+        let remap = FilterSpec::Remap { swap_xy: true, invert_x: false, invert_y: true };
+        let frames = run(&mut chain(vec![remap]), TAP);
+        // (50, 100) -> 归一化(0.05, 0.05) -> 交换 -> 翻转Y -> (50, 1900)
+        assert_eq!(frames[0][2..4], [(EV_ABS, ABS_MT_POSITION_X, 50), (EV_ABS, ABS_MT_POSITION_Y, 1900)]);
+        assert_eq!(frames[1][1..], [(EV_ABS, ABS_MT_POSITION_X, 50), (EV_ABS, ABS_MT_POSITION_Y, 1896)]);
+
+        let frames = run(&mut chain(vec![FilterSpec::Scale { x: 2.0, y: 0.5 }]), TAP);
+        assert_eq!(frames[0][2..4], [(EV_ABS, ABS_MT_POSITION_X, 100), (EV_ABS, ABS_MT_POSITION_Y, 50)]);
+    }
+
+    #[test]
+    fn smoothing_and_rate_limit() {
+        let moves = "
+            [   1.000000] EV_ABS       ABS_MT_TRACKING_ID   00000001
+            [   1.000000] EV_ABS       ABS_MT_POSITION_X    00000000
+            [   1.000000] EV_SYN       SYN_REPORT           00000000
+            [   1.004000] EV_ABS       ABS_MT_POSITION_X    00000064
+            [   1.004000] EV_SYN       SYN_REPORT           00000000
+            [   1.008000] EV_ABS       ABS_MT_POSITION_X    000000c8
+            [   1.008000] EV_SYN       SYN_REPORT           00000000
+            [   1.009000] EV_ABS       ABS_MT_TRACKING_ID   ffffffff
+            [   1.009000] EV_SYN       SYN_REPORT           00000000
+        ";
+        let frames = run(&mut chain(vec![FilterSpec::Smoothing { alpha: 0.5 }]), moves);
+        assert_eq!(frames[1], vec![(EV_ABS, ABS_MT_SLOT, 0), (EV_ABS, ABS_MT_POSITION_X, 50)]);
+        assert_eq!(frames[2], vec![(EV_ABS, ABS_MT_SLOT, 0), (EV_ABS, ABS_MT_POSITION_X, 125)]);
+
+        // 100Hz：1.004被合并，1.008不到10ms也被合并，抬起前补发最后的位置
+        let frames = run(&mut chain(vec![FilterSpec::RateLimit { max_hz: 100 }]), moves);
+        assert!(frames[1].is_empty());
+        assert!(frames[2].is_empty());
+        assert_eq!(
+            frames[3],
+            vec![
+                (EV_ABS, ABS_MT_SLOT, 0),
+                (EV_ABS, ABS_MT_POSITION_X, 200),
+                (EV_ABS, ABS_MT_SLOT, 0),
+                (EV_ABS, ABS_MT_TRACKING_ID, -1),
+                (EV_KEY, BTN_TOUCH, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn rate_limit_flushes_held_position_at_deadline() {
+        let moves = "
+            [   1.000000] EV_ABS       ABS_MT_TRACKING_ID   00000001
+            [   1.000000] EV_ABS       ABS_MT_POSITION_X    00000000
+            [   1.000000] EV_SYN       SYN_REPORT           00000000
+            [   1.004000] EV_ABS       ABS_MT_POSITION_X    00000064
+            [   1.004000] EV_SYN       SYN_REPORT           00000000
+        ";
+        let mut chain = chain(vec![FilterSpec::Smoothing { alpha: 1.0 }, FilterSpec::RateLimit { max_hz: 100 }]);
+        let frames = run(&mut chain, moves);
+        assert!(frames[1].is_empty());
+        assert_eq!(chain.deadline(), Some(1_010_000));
+
+        // 到期前不输出，到期后补发合并掉的位置
+        assert!(chain.flush(1_009_000).is_empty());
+        assert_eq!(chain.flush(1_010_000), vec![(EV_ABS, ABS_MT_SLOT, 0), (EV_ABS, ABS_MT_POSITION_X, 100)]);
+        assert_eq!(chain.deadline(), None);
+        assert!(chain.flush(1_050_000).is_empty());
+    }
+
+    #[test]
+    fn reset_forgets_contacts_and_pending_updates() {
+        // 50Hz：TAP中10ms后的移动被合并
+        let mut chain = chain(vec![FilterSpec::RateLimit { max_hz: 50 }]);
+        let frames = parse_recording(TAP);
+        chain.apply(&frames[0].1, frames[0].0);
+        chain.apply(&frames[1].1, frames[1].0);
+        assert!(chain.deadline().is_some());
+
+        chain.reset(0);
+        assert_eq!(chain.deadline(), None);
+        // 重新按下时BTN_TOUCH再次输出，位置不与之前的触点合并
+        let down = chain.apply(&frames[0].1, 2_000_000);
+        assert_eq!(down.last(), Some(&(EV_KEY, BTN_TOUCH, 1)));
+        assert!(chain.flush(3_000_000).is_empty());
+    }
+
+    #[test]
+    fn config_parses_all_filter_types() {
+        let config: FilterConfig = serde_json::from_str(
+            r#"{"filters": [
+                {"type": "dead_zone", "regions": [{"x_min": 0, "y_min": 0, "x_max": 40, "y_max": 2400}]},
+                {"type": "remap", "invert_y": true},
+                {"type": "palm_rejection", "max_touch_major": 300},
+                {"type": "debounce", "window_ms": 30},
+                {"type": "scale", "x": 1.0, "y": 0.5},
+                {"type": "smoothing", "alpha": 0.6},
+                {"type": "rate_limit", "max_hz": 120}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(config.filters.len(), 7);
+        assert!(matches!(config.filters[1], FilterSpec::Remap { swap_xy: false, invert_x: false, invert_y: true }));
+    }
+}
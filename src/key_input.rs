@@ -0,0 +1,354 @@
+use crate::event_sink::EventSink;
+use crate::uinput::{destroy_uinput_device, new_key_dev, InputDevice};
+use crate::uinput_defs::*;
+use log::{debug, error, info};
+use std::{
+    io,
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+// Android Generic.kl中常用的硬件按键
+pub const HARDWARE_KEYS: &[u16] = &[
+    KEY_BACK,
+    KEY_HOMEPAGE,
+    KEY_APPSELECT,
+    KEY_MENU,
+    KEY_POWER,
+    KEY_VOLUMEUP,
+    KEY_VOLUMEDOWN,
+    KEY_MUTE,
+    KEY_CAMERA,
+    KEY_SEARCH,
+];
+
+// 文本输入需要的按键（US布局）
+pub const TEXT_KEYS: &[u16] = &[
+    KEY_ESC, KEY_1, KEY_2, KEY_3, KEY_4, KEY_5, KEY_6, KEY_7, KEY_8, KEY_9, KEY_0,
+    KEY_MINUS, KEY_EQUAL, KEY_BACKSPACE, KEY_TAB,
+    KEY_Q, KEY_W, KEY_E, KEY_R, KEY_T, KEY_Y, KEY_U, KEY_I, KEY_O, KEY_P,
+    KEY_LEFTBRACE, KEY_RIGHTBRACE, KEY_ENTER,
+    KEY_A, KEY_S, KEY_D, KEY_F, KEY_G, KEY_H, KEY_J, KEY_K, KEY_L,
+    KEY_SEMICOLON, KEY_APOSTROPHE, KEY_GRAVE, KEY_LEFTSHIFT, KEY_BACKSLASH,
+    KEY_Z, KEY_X, KEY_C, KEY_V, KEY_B, KEY_N, KEY_M,
+    KEY_COMMA, KEY_DOT, KEY_SLASH, KEY_SPACE,
+];
+
+// 按键名称表，名称与Android KeyEvent保持一致
+const KEY_NAMES: &[(&str, u16)] = &[
+    ("BACK", KEY_BACK),
+    ("HOME", KEY_HOMEPAGE),
+    ("APP_SWITCH", KEY_APPSELECT),
+    ("MENU", KEY_MENU),
+    ("POWER", KEY_POWER),
+    ("VOLUME_UP", KEY_VOLUMEUP),
+    ("VOLUME_DOWN", KEY_VOLUMEDOWN),
+    ("VOLUME_MUTE", KEY_MUTE),
+    ("CAMERA", KEY_CAMERA),
+    ("SEARCH", KEY_SEARCH),
+    ("ENTER", KEY_ENTER),
+    ("DEL", KEY_BACKSPACE),
+    ("TAB", KEY_TAB),
+    ("SPACE", KEY_SPACE),
+    ("ESCAPE", KEY_ESC),
+];
+
+const KEY_TAP_DELAY_MS: u64 = 15;
+const DEFAULT_LONG_PRESS_MS: u64 = 800;
+
+// 默认按键集合：硬件按键 + 文本输入按键
+pub fn default_key_set() -> Vec<u16> {
+    let mut keys = HARDWARE_KEYS.to_vec();
+    keys.extend_from_slice(TEXT_KEYS);
+    keys
+}
+
+// 根据名称查找按键码，支持Android名称（BACK、HOME等）或十进制按键码
+pub fn key_code_from_name(name: &str) -> Option<u16> {
+    let upper = name.trim().to_uppercase();
+    let upper = upper.strip_prefix("KEYCODE_").unwrap_or(&upper);
+    KEY_NAMES
+        .iter()
+        .find(|(key_name, _)| *key_name == upper)
+        .map(|(_, code)| *code)
+        .or_else(|| upper.parse::<u16>().ok().filter(|code| *code <= KEY_MAX))
+}
+
+// 把字符映射为(按键码, 是否需要Shift)，只支持US布局的可打印ASCII
+pub fn char_to_key(c: char) -> Option<(u16, bool)> {
+    const LETTERS: [u16; 26] = [
+        KEY_A, KEY_B, KEY_C, KEY_D, KEY_E, KEY_F, KEY_G, KEY_H, KEY_I, KEY_J, KEY_K, KEY_L, KEY_M,
+        KEY_N, KEY_O, KEY_P, KEY_Q, KEY_R, KEY_S, KEY_T, KEY_U, KEY_V, KEY_W, KEY_X, KEY_Y, KEY_Z,
+    ];
+    const DIGITS: [u16; 10] = [KEY_0, KEY_1, KEY_2, KEY_3, KEY_4, KEY_5, KEY_6, KEY_7, KEY_8, KEY_9];
+
+    let key = match c {
+        'a'..='z' => (LETTERS[(c as u8 - b'a') as usize], false),
+        'A'..='Z' => (LETTERS[(c as u8 - b'A') as usize], true),
+        '0'..='9' => (DIGITS[(c as u8 - b'0') as usize], false),
+        ')' => (KEY_0, true),
+        '!' => (KEY_1, true),
+        '@' => (KEY_2, true),
+        '#' => (KEY_3, true),
+        '$' => (KEY_4, true),
+        '%' => (KEY_5, true),
+        '^' => (KEY_6, true),
+        '&' => (KEY_7, true),
+        '*' => (KEY_8, true),
+        '(' => (KEY_9, true),
+        ' ' => (KEY_SPACE, false),
+        '\n' => (KEY_ENTER, false),
+        '\t' => (KEY_TAB, false),
+        '-' => (KEY_MINUS, false),
+        '_' => (KEY_MINUS, true),
+        '=' => (KEY_EQUAL, false),
+        '+' => (KEY_EQUAL, true),
+        '[' => (KEY_LEFTBRACE, false),
+        '{' => (KEY_LEFTBRACE, true),
+        ']' => (KEY_RIGHTBRACE, false),
+        '}' => (KEY_RIGHTBRACE, true),
+        '\\' => (KEY_BACKSLASH, false),
+        '|' => (KEY_BACKSLASH, true),
+        ';' => (KEY_SEMICOLON, false),
+        ':' => (KEY_SEMICOLON, true),
+        '\'' => (KEY_APOSTROPHE, false),
+        '"' => (KEY_APOSTROPHE, true),
+        '`' => (KEY_GRAVE, false),
+        '~' => (KEY_GRAVE, true),
+        ',' => (KEY_COMMA, false),
+        '<' => (KEY_COMMA, true),
+        '.' => (KEY_DOT, false),
+        '>' => (KEY_DOT, true),
+        '/' => (KEY_SLASH, false),
+        '?' => (KEY_SLASH, true),
+        _ => return None,
+    };
+    Some(key)
+}
+
+pub struct KeySimulation {
+    key_set: Vec<u16>,
+    key_device: Option<Arc<Mutex<InputDevice>>>,
+    // 事件的写入目标，通常就是key_device
+    sink: Option<Arc<Mutex<dyn EventSink>>>,
+}
+
+// sink不一定实现Debug
+impl std::fmt::Debug for KeySimulation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KeySimulation")
+            .field("key_set", &self.key_set)
+            .field("key_device", &self.key_device)
+            .finish_non_exhaustive()
+    }
+}
+
+impl KeySimulation {
+    pub fn new() -> Self {
+        Self::new_with_keys(default_key_set())
+    }
+
+    pub fn new_with_keys(key_set: Vec<u16>) -> Self {
+        Self {
+            key_set,
+            key_device: None,
+            sink: None,
+        }
+    }
+
+    // 不创建uinput设备，事件写到给定的sink，用于测试
+    pub fn new_with_sink(key_set: Vec<u16>, sink: Arc<Mutex<dyn EventSink>>) -> Self {
+        Self {
+            sink: Some(sink),
+            ..Self::new_with_keys(key_set)
+        }
+    }
+
+    pub fn key_input_setup(&mut self, name: &str) -> bool {
+        info!("key_input_setup: name={}, keys={}", name, self.key_set.len());
+        if self.sink.is_some() {
+            return true;
+        }
+
+        match new_key_dev(name, &self.key_set) {
+            Ok(dev) => {
+                let device = Arc::new(Mutex::new(dev));
+                self.sink = Some(device.clone());
+                self.key_device = Some(device);
+                true
+            }
+            Err(e) => {
+                error!("key_input_setup: failed to create key device: {}", e);
+                false
+            }
+        }
+    }
+
+    pub fn key_input_stop(&mut self) {
+        self.sink = None;
+        if let Some(key_device) = self.key_device.take() {
+            let device = key_device.lock().unwrap();
+            let _ = destroy_uinput_device(&device);
+        }
+    }
+
+    pub fn press_key(&mut self, code: u16) -> io::Result<()> {
+        self.send_key(code, 1)
+    }
+
+    pub fn release_key(&mut self, code: u16) -> io::Result<()> {
+        self.send_key(code, 0)
+    }
+
+    pub fn tap_key(&mut self, code: u16) -> io::Result<()> {
+        self.hold_key(code, Duration::from_millis(KEY_TAP_DELAY_MS))
+    }
+
+    pub fn long_press_key(&mut self, code: u16) -> io::Result<()> {
+        self.hold_key(code, Duration::from_millis(DEFAULT_LONG_PRESS_MS))
+    }
+
+    pub fn hold_key(&mut self, code: u16, duration: Duration) -> io::Result<()> {
+        self.press_key(code)?;
+        thread::sleep(duration);
+        self.release_key(code)?;
+        thread::sleep(Duration::from_millis(KEY_TAP_DELAY_MS));
+        Ok(())
+    }
+
+    // 逐字符输入文本，大写字母和符号自动加Shift
+    pub fn type_text(&mut self, text: &str) -> io::Result<()> {
+        debug!("type_text: typing {} chars", text.chars().count());
+        for c in text.chars() {
+            let (code, shift) = char_to_key(c).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidInput, format!("unsupported character {:?}", c))
+            })?;
+            if shift {
+                self.press_key(KEY_LEFTSHIFT)?;
+            }
+            let result = self.tap_key(code);
+            if shift {
+                self.release_key(KEY_LEFTSHIFT)?;
+            }
+            result?;
+        }
+        Ok(())
+    }
+
+    fn send_key(&mut self, code: u16, value: i32) -> io::Result<()> {
+        if !self.key_set.contains(&code) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("key {} is not in the configured key set", code),
+            ));
+        }
+        let sink = self.sink.as_ref().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotConnected, "key device is not set up")
+        })?;
+
+        let mut device = sink.lock().unwrap();
+        device.write_event(EV_KEY, code, value)?;
+        device.write_event(EV_SYN, SYN_REPORT, 0)
+    }
+}
+
+impl Default for KeySimulation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event_sink::MemorySink;
+
+    #[test]
+    fn char_to_key_adds_shift_for_upper_case_and_symbols() {
+        assert_eq!(char_to_key('a'), Some((KEY_A, false)));
+        assert_eq!(char_to_key('Z'), Some((KEY_Z, true)));
+        assert_eq!(char_to_key('5'), Some((KEY_5, false)));
+        assert_eq!(char_to_key('%'), Some((KEY_5, true)));
+        assert_eq!(char_to_key('?'), Some((KEY_SLASH, true)));
+        assert_eq!(char_to_key('\n'), Some((KEY_ENTER, false)));
+        // 所有可打印ASCII都能映射，且按键都在TEXT_KEYS中
+        for c in ' '..='~' {
+            let (code, _) = char_to_key(c).unwrap_or_else(|| panic!("{:?} is not mapped", c));
+            assert!(TEXT_KEYS.contains(&code), "{:?}", c);
+        }
+    }
+
+    #[test]
+    fn char_to_key_rejects_unmapped_characters() {
+        for c in ['é', '中', '€', '\r', '\u{7f}', '\0'] {
+            assert_eq!(char_to_key(c), None, "{:?}", c);
+        }
+    }
+
+    #[test]
+    fn key_code_from_name_accepts_android_names_and_codes() {
+        assert_eq!(key_code_from_name("back"), Some(KEY_BACK));
+        assert_eq!(key_code_from_name("KEYCODE_HOME"), Some(KEY_HOMEPAGE));
+        assert_eq!(key_code_from_name(" volume_up "), Some(KEY_VOLUMEUP));
+        assert_eq!(key_code_from_name("116"), Some(KEY_POWER));
+        assert_eq!(key_code_from_name("9999"), None);
+        assert_eq!(key_code_from_name("KEYCODE_FOO"), None);
+        assert_eq!(key_code_from_name(""), None);
+    }
+
+    fn keys() -> (KeySimulation, Arc<Mutex<MemorySink>>) {
+        let sink = Arc::new(Mutex::new(MemorySink::new()));
+        (KeySimulation::new_with_sink(default_key_set(), sink.clone()), sink)
+    }
+
+    // 去掉SYN_REPORT后的(按键码, 值)，每个按键事件后都必须跟一个SYN_REPORT
+    fn key_events(sink: &Mutex<MemorySink>) -> Vec<(u16, i32)> {
+        let events = std::mem::take(&mut sink.lock().unwrap().events);
+        events
+            .chunks(2)
+            .map(|pair| match pair {
+                [(EV_KEY, code, value), (EV_SYN, SYN_REPORT, 0)] => (*code, *value),
+                other => panic!("unexpected events {:?}", other),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn type_text_wraps_shifted_keys_in_shift_press() {
+        let (mut keys, sink) = keys();
+        keys.type_text("aB!").unwrap();
+        assert_eq!(
+            key_events(&sink),
+            vec![
+                (KEY_A, 1),
+                (KEY_A, 0),
+                (KEY_LEFTSHIFT, 1),
+                (KEY_B, 1),
+                (KEY_B, 0),
+                (KEY_LEFTSHIFT, 0),
+                (KEY_LEFTSHIFT, 1),
+                (KEY_1, 1),
+                (KEY_1, 0),
+                (KEY_LEFTSHIFT, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn type_text_stops_at_unsupported_character() {
+        let (mut keys, sink) = keys();
+        let error = keys.type_text("a€b").unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(key_events(&sink), vec![(KEY_A, 1), (KEY_A, 0)]);
+    }
+
+    #[test]
+    fn keys_outside_the_key_set_are_rejected() {
+        let sink = Arc::new(Mutex::new(MemorySink::new()));
+        let mut keys = KeySimulation::new_with_sink(HARDWARE_KEYS.to_vec(), sink.clone());
+        assert_eq!(keys.press_key(KEY_A).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        assert!(sink.lock().unwrap().events.is_empty());
+        assert!(KeySimulation::new().press_key(KEY_BACK).is_err());
+    }
+}
@@ -0,0 +1,29 @@
+pub mod logger;
+pub mod uinput_defs;
+pub mod uinput;
+pub mod touch_input;
+pub mod key_input;
+pub mod mouse_input;
+pub mod utils;
+pub mod device_scanner;
+pub mod device_classifier;
+pub mod device_watcher;
+pub mod device_report;
+pub mod mock_device;
+pub mod passthrough;
+pub mod input_filter;
+pub mod event_decoder;
+pub mod sysfs_scanner;
+pub mod event_sink;
+pub mod mt_validator;
+pub mod tracking_id;
+pub mod slot_emitter;
+pub mod touch_engine;
+pub mod gesture;
+pub mod signals;
+pub mod config;
+pub mod device_profile;
+pub mod display_size;
+pub mod metrics;
+#[cfg(feature = "tokio")]
+pub mod async_touch;
@@ -0,0 +1,136 @@
+use log::{LevelFilter, Log, Metadata, Record};
+use serde_json::json;
+use std::{
+    io::{self, Write},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+pub const LOG_ENV: &str = "TOUCHSIM_LOG";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LogFormat {
+    Text,
+    // 每行一个JSON对象：ts、level、target、msg
+    Json,
+}
+
+// 日志级别规则：default作用于所有target，directives按target前缀覆盖，最长的前缀生效；
+// 语法与RUST_LOG相同，例如"info,touch_simulation::uinput=trace"
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogFilter {
+    default: LevelFilter,
+    directives: Vec<(String, LevelFilter)>,
+}
+
+impl LogFilter {
+    pub fn new(default: LevelFilter) -> Self {
+        Self {
+            default,
+            directives: Vec::new(),
+        }
+    }
+
+    pub fn parse(default: LevelFilter, spec: &str) -> Result<Self, String> {
+        let mut filter = Self::new(default);
+        for directive in spec.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            let parse_level = |level: &str| level.parse().map_err(|_| format!("invalid log level '{}'", level));
+            match directive.split_once('=') {
+                Some((target, level)) => filter.directives.push((target.to_string(), parse_level(level)?)),
+                None => filter.default = parse_level(directive)?,
+            }
+        }
+        // 最长的前缀排在前面
+        filter.directives.sort_by_key(|(target, _)| std::cmp::Reverse(target.len()));
+        Ok(filter)
+    }
+
+    pub fn level_for(&self, target: &str) -> LevelFilter {
+        self.directives
+            .iter()
+            .find(|(prefix, _)| target.starts_with(prefix.as_str()))
+            .map_or(self.default, |(_, level)| *level)
+    }
+
+    // 设置给log::set_max_level，关闭的级别在宏里直接跳过，不会格式化参数
+    pub fn max_level(&self) -> LevelFilter {
+        self.directives.iter().map(|(_, level)| *level).fold(self.default, Ord::max)
+    }
+}
+
+// 写到stderr，stdout留给命令输出（decode、device info --json等）
+struct StderrLogger {
+    filter: LogFilter,
+    format: LogFormat,
+}
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.filter.level_for(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let line = match self.format {
+            LogFormat::Text => format!("{:<5} {}: {}", record.level(), record.target(), record.args()),
+            LogFormat::Json => {
+                let ts = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs_f64();
+                json!({
+                    "ts": ts,
+                    "level": record.level().as_str(),
+                    "target": record.target(),
+                    "msg": record.args().to_string(),
+                })
+                .to_string()
+            }
+        };
+        let _ = writeln!(io::stderr().lock(), "{}", line);
+    }
+
+    fn flush(&self) {
+        let _ = io::stderr().flush();
+    }
+}
+
+// 安装全局logger，只能调用一次；作为库使用时也可以换成其他log实现
+pub fn init(filter: LogFilter, format: LogFormat) -> Result<(), log::SetLoggerError> {
+    let max_level = filter.max_level();
+    log::set_boxed_logger(Box::new(StderrLogger { filter, format }))?;
+    log::set_max_level(max_level);
+    Ok(())
+}
+
+// -v/-q的净次数换算成默认级别：默认info，-v为debug，-vv为trace，-q为warn，-qq为error
+pub fn level_from_verbosity(verbosity: i32) -> LevelFilter {
+    match verbosity {
+        i32::MIN..=-3 => LevelFilter::Off,
+        -2 => LevelFilter::Error,
+        -1 => LevelFilter::Warn,
+        0 => LevelFilter::Info,
+        1 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn longest_matching_directive_wins() {
+        let filter = LogFilter::parse(
+            LevelFilter::Info,
+            "warn,touch_simulation=debug,touch_simulation::uinput=trace",
+        )
+        .unwrap();
+        assert_eq!(filter.level_for("touch_simulation::uinput"), LevelFilter::Trace);
+        assert_eq!(filter.level_for("touch_simulation::touch_engine"), LevelFilter::Debug);
+        assert_eq!(filter.level_for("other_crate"), LevelFilter::Warn);
+        assert_eq!(filter.max_level(), LevelFilter::Trace);
+
+        assert!(LogFilter::parse(LevelFilter::Info, "uinput=loud").is_err());
+        assert_eq!(level_from_verbosity(-1), LevelFilter::Warn);
+        assert_eq!(level_from_verbosity(5), LevelFilter::Trace);
+    }
+}
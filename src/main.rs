@@ -1,12 +1,27 @@
-mod uinput_defs;
-mod uinput;
-mod touch_input;
-mod utils;
-mod device_scanner;
-
-use touch_input::{TouchSimulation, TypeMode};
+use touch_simulation::{device_report, device_scanner, sysfs_scanner, uinput};
+use touch_simulation::config::{Config, DeviceRule, ModeSetting};
+use touch_simulation::device_classifier::classify;
+use touch_simulation::device_profile::ProfileDb;
+use touch_simulation::display_size::{resolve_display_size, DEFAULT_SOURCES};
+use touch_simulation::mock_device::{MockProfile, MOCK_PROFILE_ENV};
+use touch_simulation::device_watcher::{DeviceWatcher, WatchSource};
+use touch_simulation::event_decoder::{decode_stream, Frame, RecordFormat};
+use touch_simulation::input_filter::FilterConfig;
+use touch_simulation::logger::{self, LogFilter, LogFormat, LOG_ENV};
+use touch_simulation::key_input::{key_code_from_name, KeySimulation};
+use touch_simulation::mouse_input::{MouseButton, MouseSimulation};
+use touch_simulation::mt_validator::{Validator, ValidatorSpec};
+use touch_simulation::signals::{DaemonSignal, SignalListener};
+use touch_simulation::gesture::Rotation;
+use touch_simulation::touch_input::TouchSimulation;
+use log::{error, info, warn};
 use std::{
-    io::{self, Write},
+    env,
+    fs::File,
+    io::{self, BufRead, Write},
+    path::Path,
+    process,
+    sync::mpsc,
     thread,
     time::Duration,
 };
@@ -16,40 +31,114 @@ const Y: i32 = 1064;
 const NX: i32 = 400;
 const NY: i32 = 1408;
 
-fn gen_move_points(sim: &mut TouchSimulation, start_x: i32, start_y: i32, end_x: i32, end_y: i32) {
-    let min_point_count = 2;
-    let max_move_distance = 10;
+fn swipe(sim: &mut TouchSimulation, start_x: i32, start_y: i32, end_x: i32, end_y: i32) {
+    if let Err(e) = sim.swipe(start_x, start_y, end_x, end_y) {
+        error!("Swipe failed: {}", e);
+    }
+}
 
-    let d_x = (end_x - start_x) as f32;
-    let d_y = (end_y - start_y) as f32;
+// 解析并执行mouse子命令，鼠标设备在第一次使用时创建
+fn mouse_command(mouse: &mut MouseSimulation, width: i32, height: i32, arg: &str) -> Result<(), String> {
+    let args: Vec<&str> = arg.split_whitespace().collect();
+    let nums: Vec<i32> = args.iter().skip(1).filter_map(|a| a.parse().ok()).collect();
 
-    let x_count = ((d_x.abs() / max_move_distance as f32) as i32).abs();
-    let y_count = ((d_y.abs() / max_move_distance as f32) as i32).abs();
-    let mut count = x_count.max(y_count);
-    count = count.max(min_point_count);
+    if !mouse.mouse_input_setup("TouchSimulation_Mouse", width, height) {
+        return Err("failed to setup mouse device".to_string());
+    }
+
+    let result = match (args.first().copied(), nums.as_slice()) {
+        (Some("move"), [x, y]) => mouse.move_to(*x, *y),
+        (Some("rel"), [dx, dy]) => mouse.move_relative(*dx, *dy),
+        (Some("click"), rest) => {
+            let button = args.get(1).and_then(|b| MouseButton::from_name(b)).unwrap_or(MouseButton::Left);
+            match rest {
+                [x, y] => mouse.click_at(*x, *y, button),
+                _ => mouse.click(button),
+            }
+        }
+        (Some("drag"), [x1, y1, x2, y2]) => mouse.drag(*x1, *y1, *x2, *y2, MouseButton::Left),
+        (Some("scroll"), [v]) => mouse.scroll(*v, 0),
+        (Some("scroll"), [v, h]) => mouse.scroll(*v, *h),
+        _ => return Err("usage: mouse move X Y | rel DX DY | click [left|right|middle] [X Y] | drag X1 Y1 X2 Y2 | scroll V [H]".to_string()),
+    };
+    result.map_err(|e| e.to_string())
+}
 
-    let act_delta_x = d_x / count as f32;
-    let act_delta_y = d_y / count as f32;
+// device info [--json] [PATH]：打印扫描到的设备元数据
+fn device_info_command(devices: &[uinput::InputDevice], args: &[&str]) {
+    let json = args.contains(&"--json");
+    let path = args.iter().find(|a| !a.starts_with("--"));
+    let selected: Vec<uinput::InputDevice> = devices
+        .iter()
+        .filter(|d| path.is_none_or(|p| d.path == *p))
+        .cloned()
+        .collect();
 
-    for i in 0..count {
-        let x = (start_x as f32 + act_delta_x * i as f32) as i32;
-        let y = (start_y as f32 + act_delta_y * i as f32) as i32;
-        sim.send_touch_move(x, y);
+    if json {
+        device_report::print_device_json(&selected);
+    } else {
+        for device in &selected {
+            device_report::print_device_table(device);
+        }
     }
 }
 
-fn swipe(sim: &mut TouchSimulation, start_x: i32, start_y: i32, end_x: i32, end_y: i32) {
-    sim.send_touch_move(start_x, start_y);
-    gen_move_points(sim, start_x, start_y, end_x, end_y);
-    sim.send_touch_move(end_x, end_y);
-    sim.send_touch_up();
+// decode [--json] [--state] [--validate] [--format 24|16] PATH：解码录制文件或实时读取设备节点，PATH为-时读标准输入
+fn decode_command(args: &[&str]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut args = args.to_vec();
+    let json = take_flag(&mut args, "--json");
+    let state = take_flag(&mut args, "--state");
+    let validate = take_flag(&mut args, "--validate");
+    let format = match take_option(&mut args, "--format") {
+        Some(size) => size
+            .parse()
+            .ok()
+            .and_then(RecordFormat::from_size)
+            .ok_or_else(|| format!("unsupported record size {}, expected 24 or 16", size))?,
+        None => RecordFormat::NATIVE,
+    };
+    let [path] = args.as_slice() else {
+        return Err("usage: decode [--json] [--state] [--validate] [--format 24|16] PATH".into());
+    };
+
+    // 设备节点按设备声明的能力校验，录制文件只检查协议本身
+    let mut validator = validate.then(|| {
+        let spec = path
+            .starts_with("/dev/input/")
+            .then(|| device_scanner::probe_device(Path::new(path)))
+            .flatten()
+            .map_or_else(ValidatorSpec::permissive, |device| ValidatorSpec::from_device(&device));
+        Validator::new(spec)
+    });
+    let print = |frame: &Frame| {
+        frame.print(json, state);
+        if let Some(validator) = &mut validator {
+            for event in &frame.events {
+                validator.push(event.event_type, event.code, event.value);
+            }
+        }
+    };
+    if *path == "-" {
+        decode_stream(io::stdin().lock(), format, print)?;
+    } else {
+        let file = File::open(path).map_err(|e| format!("failed to open {}: {}", path, e))?;
+        decode_stream(file, format, print)?;
+    }
+
+    if let Some(validator) = validator {
+        println!("validate: {}", validator.summary());
+        if !validator.violations().is_empty() {
+            return Err(format!("{} protocol violations", validator.violations().len()).into());
+        }
+    }
+    Ok(())
 }
 
-fn select_device(devices: &Vec<uinput::InputDevice>) -> Option<usize> {
+fn select_device(devices: &[uinput::InputDevice]) -> Option<usize> {
     println!("Found {} input devices:", devices.len());
     for (i, device) in devices.iter().enumerate() {
-        println!("{}: {} (path: {}, slots: {}, resolution: {}x{})", 
-                 i, device.name, device.path, device.slots, 
+        println!("{}: {}{} (path: {}, slots: {}, resolution: {}x{})", 
+                 i, if device.info.mock { "[MOCK] " } else { "" }, device.name, device.path, device.slots, 
                  device.touch_x_max - device.touch_x_min, 
                  device.touch_y_max - device.touch_y_min);
     }
@@ -74,82 +163,572 @@ fn select_device(devices: &Vec<uinput::InputDevice>) -> Option<usize> {
     }
 }
 
+// 取出开关参数，返回是否出现过
+fn take_flag(args: &mut Vec<&str>, flag: &str) -> bool {
+    let before = args.len();
+    args.retain(|a| *a != flag);
+    args.len() != before
+}
+
+// 取出可以重复的开关参数，返回出现的次数
+fn take_count(args: &mut Vec<&str>, flag: &str) -> usize {
+    let before = args.len();
+    args.retain(|a| *a != flag);
+    before - args.len()
+}
+
+// -v/-vv/-q/-qq调整默认级别，--log或TOUCHSIM_LOG按模块设置级别，--log-json输出JSON
+fn init_logging(args: &mut Vec<&str>) -> Result<(), Box<dyn std::error::Error>> {
+    let verbosity = take_count(args, "-v") + 2 * take_count(args, "-vv");
+    let quiet = take_count(args, "-q") + 2 * take_count(args, "-qq");
+    let format = if take_flag(args, "--log-json") { LogFormat::Json } else { LogFormat::Text };
+    let default = logger::level_from_verbosity(verbosity as i32 - quiet as i32);
+    let spec = take_option(args, "--log").map(str::to_string).or_else(|| env::var(LOG_ENV).ok());
+    let filter = match spec {
+        Some(spec) => LogFilter::parse(default, &spec)?,
+        None => LogFilter::new(default),
+    };
+    logger::init(filter, format)?;
+    Ok(())
+}
+
+// 取出带值的参数：--name VALUE
+fn take_option<'a>(args: &mut Vec<&'a str>, name: &str) -> Option<&'a str> {
+    let index = args.iter().position(|a| *a == name)?;
+    args.remove(index);
+    if index < args.len() {
+        Some(args.remove(index))
+    } else {
+        None
+    }
+}
+
+// mock模式必须显式开启：--mock、--mock-profile PATH或TOUCHSIM_MOCK_PROFILE
+fn load_mock_device(enabled: bool, profile_path: Option<&str>) -> Result<Option<uinput::InputDevice>, Box<dyn std::error::Error>> {
+    let env_path = env::var(MOCK_PROFILE_ENV).ok();
+    let profile_path = profile_path.or(env_path.as_deref());
+    if !enabled && profile_path.is_none() {
+        return Ok(None);
+    }
+
+    let profile = match profile_path {
+        Some(path) => MockProfile::load(Path::new(path))?,
+        None => MockProfile::default(),
+    };
+    warn!("*** MOCK MODE: using simulated reference device '{}' ({}), no real touchscreen is read ***",
+             profile.name, profile_path.unwrap_or("built-in profile"));
+    Ok(Some(profile.to_input_device()?))
+}
+
+// 加载配置文件，没有指定时使用默认配置
+fn load_config(cli_path: Option<&str>) -> Result<Config, Box<dyn std::error::Error>> {
+    match Config::locate(cli_path) {
+        Some(path) => {
+            info!("Loading config from {}", path.display());
+            Config::load(&path)
+        }
+        None => Ok(Config::default()),
+    }
+}
+
+// display：逐个尝试显示尺寸的来源，便于排查自动检测的结果
+fn display_command() {
+    for source in DEFAULT_SOURCES {
+        match source.detect() {
+            Some((width, height)) => println!("{:<40} {}x{}", source.to_string(), width, height),
+            None => println!("{:<40} unavailable", source.to_string()),
+        }
+    }
+}
+
+// 内置设备档案加上--profiles或TOUCHSIM_PROFILES指定的用户档案
+fn load_profiles(cli_path: Option<&str>) -> Result<ProfileDb, Box<dyn std::error::Error>> {
+    let mut profiles = ProfileDb::builtin();
+    if let Some(path) = ProfileDb::locate(cli_path) {
+        let count = profiles.load_user(&path)?;
+        info!("Loaded {} device profiles from {}", count, path.display());
+    }
+    Ok(profiles)
+}
+
+// 命令行参数覆盖配置文件中的值
+fn apply_cli_overrides(config: &mut Config, args: &mut Vec<&str>) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(mode) = take_option(args, "--mode") {
+        config.device.mode = mode.parse()?;
+    }
+    if let Some(pattern) = take_option(args, "--device") {
+        config.device.rules = vec![DeviceRule {
+            name: Some(pattern.to_string()),
+            ..DeviceRule::default()
+        }];
+    }
+    if let Some(size) = take_option(args, "--display") {
+        let (width, height) = size
+            .split_once('x')
+            .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))
+            .ok_or_else(|| format!("invalid display size {}, expected WIDTHxHEIGHT", size))?;
+        config.display.width = Some(width);
+        config.display.height = Some(height);
+    }
+    if let Some(rotation) = take_option(args, "--rotation") {
+        let degrees: u16 = rotation.parse().map_err(|_| format!("invalid rotation {}", rotation))?;
+        config.display.rotation = Rotation::try_from(degrees)?;
+    }
+    if let Some(rate) = take_option(args, "--report-rate") {
+        config.engine.report_rate_hz = Some(rate.parse().map_err(|_| format!("invalid report rate {}", rate))?);
+    }
+    if let Some(path) = take_option(args, "--filters") {
+        config.engine.filters = Some(path.into());
+    }
+    config.engine.passthrough |= take_flag(args, "--passthrough");
+    config.engine.validate |= take_flag(args, "--validate");
+    Ok(())
+}
+
+// config check：打印合并命令行参数后的配置，有问题时退出码为1
+fn config_check_command(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    println!("{}", serde_json::to_string_pretty(config)?);
+    let problems = config.check();
+    for problem in &problems {
+        eprintln!("config: {}", problem);
+    }
+    if !problems.is_empty() {
+        return Err(format!("{} config problems", problems.len()).into());
+    }
+    println!("config: ok");
+    Ok(())
+}
+
+// --sysfs：从sysfs（或/proc/bus/input/devices）读取设备信息，不逐个打开event节点
+fn scan_all_devices(use_sysfs: bool) -> Result<Vec<uinput::InputDevice>, Box<dyn std::error::Error>> {
+    if use_sysfs {
+        sysfs_scanner::scan_sysfs_devices()
+    } else {
+        device_scanner::scan_all_input_devices()
+    }
+}
+
+// 监听设备热插拔，触摸屏驱动重新加载后自动重建虚拟设备
+fn start_watcher() -> Option<DeviceWatcher> {
+    DeviceWatcher::start(WatchSource::Inotify)
+        .or_else(|_| DeviceWatcher::start(WatchSource::Netlink))
+        .map_err(|e| warn!("Failed to start device watcher: {}", e))
+        .ok()
+}
+
+fn poll_watcher(sim: &mut TouchSimulation, watcher: Option<&DeviceWatcher>) {
+    if let Some(watcher) = watcher {
+        while let Some(event) = watcher.try_recv() {
+            if sim.handle_device_event(&event) {
+                info!("Touch device rebuilt after {:?}", event);
+            }
+        }
+    }
+}
+
+fn stats_command(sim: &TouchSimulation, arg: &str) {
+    let metrics = sim.metrics();
+    if arg == "--json" {
+        println!("{}", serde_json::to_string(&metrics).unwrap());
+    } else {
+        println!("{}", metrics);
+    }
+}
+
+// 在后台线程逐行读取标准输入，主循环可以同时处理热插拔和信号
+fn spawn_stdin_reader() -> mpsc::Receiver<String> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines().map_while(Result::ok) {
+            if sender.send(line).is_err() {
+                return;
+            }
+        }
+    });
+    receiver
+}
+
+fn daemon_lift_all(sim: &mut TouchSimulation) {
+    if sim.lift_all() {
+        info!("All contacts lifted");
+    } else {
+        error!("Failed to lift contacts, touch device is not running");
+    }
+}
+
+// 守护模式：SIGUSR1紧急抬起所有触点，SIGUSR2输出统计，SIGTERM/SIGINT退出；
+// 标准输入可以发送stats [--json]、lift和exit
+fn run_daemon(sim: &mut TouchSimulation, signals: &SignalListener) {
+    info!("Running as daemon (pid {}), send SIGUSR1 to lift all contacts, SIGUSR2 for stats", process::id());
+    let watcher = start_watcher();
+    let commands = spawn_stdin_reader();
+    loop {
+        poll_watcher(sim, watcher.as_ref());
+        while let Ok(line) = commands.try_recv() {
+            let line = line.trim();
+            let (command, arg) = line.split_once(' ').unwrap_or((line, ""));
+            match command {
+                "stats" => stats_command(sim, arg.trim()),
+                "lift" => daemon_lift_all(sim),
+                "exit" => return,
+                "" => {}
+                _ => warn!("Unknown daemon command: {}", command),
+            }
+        }
+        match signals.recv_timeout(Duration::from_millis(100)) {
+            Some(DaemonSignal::LiftAll) => daemon_lift_all(sim),
+            Some(DaemonSignal::Stats) => info!("Injection stats:\n{}", sim.metrics()),
+            Some(DaemonSignal::Terminate) => break,
+            None => {}
+        }
+    }
+}
+
 fn main() {
-    println!("Touch Simulation Rust Version - Starting...");
+    let args: Vec<String> = env::args().skip(1).collect();
+    let mut args: Vec<&str> = args.iter().map(String::as_str).collect();
+    if let Err(e) = init_logging(&mut args) {
+        eprintln!("Invalid logging options: {}", e);
+        process::exit(1);
+    }
+    let mock_enabled = take_flag(&mut args, "--mock");
+    let mock_profile = take_option(&mut args, "--mock-profile");
+    let use_sysfs = take_flag(&mut args, "--sysfs");
+    let config_path = take_option(&mut args, "--config");
+    let profiles = match load_profiles(take_option(&mut args, "--profiles")) {
+        Ok(profiles) => profiles,
+        Err(e) => {
+            error!("Failed to load device profiles: {}", e);
+            process::exit(1);
+        }
+    };
+    let daemon = take_flag(&mut args, "--daemon");
+    if let ["device", "info", rest @ ..] = args.as_slice() {
+        match scan_all_devices(use_sysfs).and_then(device_scanner::select_touchscreens) {
+            Ok(mut devices) => {
+                profiles.annotate(&mut devices);
+                device_info_command(&devices, rest)
+            }
+            Err(e) => error!("Failed to scan input devices: {}", e),
+        }
+        return;
+    }
+    if let ["decode", rest @ ..] = args.as_slice() {
+        if let Err(e) = decode_command(rest) {
+            error!("decode failed: {}", e);
+            process::exit(1);
+        }
+        return;
+    }
+    if let ["display"] = args.as_slice() {
+        display_command();
+        return;
+    }
+    if let ["device", "list", rest @ ..] = args.as_slice() {
+        match scan_all_devices(use_sysfs) {
+            Ok(mut devices) => {
+                profiles.annotate(&mut devices);
+                device_report::print_device_list(&devices, rest.contains(&"--json"))
+            }
+            Err(e) => error!("Failed to scan input devices: {}", e),
+        }
+        return;
+    }
+
+    // 子命令自己解析--validate等参数，配置文件只用于交互和守护模式
+    let mut config = match load_config(config_path) {
+        Ok(config) => config,
+        Err(e) => {
+            error!("Failed to load config: {}", e);
+            process::exit(1);
+        }
+    };
+    if let Err(e) = apply_cli_overrides(&mut config, &mut args) {
+        error!("Invalid arguments: {}", e);
+        process::exit(1);
+    }
+    if let ["config", "check"] = args.as_slice() {
+        if let Err(e) = config_check_command(&config) {
+            error!("config check failed: {}", e);
+            process::exit(1);
+        }
+        return;
+    }
+    let problems = config.check();
+    if !problems.is_empty() {
+        for problem in &problems {
+            error!("config: {}", problem);
+        }
+        process::exit(1);
+    }
+
+    info!("Touch Simulation Rust Version - Starting...");
     
-    // 扫描输入设备
-    println!("Scanning for input devices...");
-    let devices = match device_scanner::scan_input_devices() {
-        Ok(devices) => devices,
+    // 扫描输入设备，只有显式开启mock模式时才使用模拟设备
+    let mut devices = match load_mock_device(mock_enabled, mock_profile) {
+        Ok(Some(mock_device)) => vec![mock_device],
+        Ok(None) => {
+            info!("Scanning for input devices...");
+            match scan_all_devices(use_sysfs).and_then(device_scanner::select_touchscreens) {
+                Ok(devices) => devices,
+                Err(e) => {
+                    error!("Failed to scan input devices: {}", e);
+                    process::exit(1);
+                }
+            }
+        }
         Err(e) => {
-            eprintln!("Failed to scan input devices: {}", e);
-            return;
+            error!("Failed to load mock device: {}", e);
+            process::exit(1);
         }
     };
     
     if devices.is_empty() {
-        eprintln!("No input devices found!");
+        error!("No input devices found!");
         return;
     }
+    profiles.annotate(&mut devices);
     
-    // 选择设备
-    let selected_index = match select_device(&devices) {
+    // 配置了匹配规则时按规则选择，否则守护模式直接使用第一个
+    let selected = if !config.device.rules.is_empty() {
+        let Some(index) = config.device.select(&devices) else {
+            error!("No input device matches the configured rules!");
+            process::exit(1);
+        };
+        Some(index)
+    } else if daemon {
+        Some(0)
+    } else {
+        select_device(&devices)
+    };
+    let selected_index = match selected {
         Some(index) => index,
         None => {
-            println!("No device selected. Exiting.");
+            info!("No device selected. Exiting.");
             return;
         }
     };
     
     let selected_device = &devices[selected_index];
-    println!("Selected device: {} at {}", selected_device.name, selected_device.path);
+    info!("Selected device: {} at {}", selected_device.name, selected_device.path);
     
-    // 使用设备的真实分辨率
-    let device_width = selected_device.touch_x_max - selected_device.touch_x_min;
-    let device_height = selected_device.touch_y_max - selected_device.touch_y_min;
-    println!("Device resolution: {}x{}", device_width, device_height);
+    // 显示尺寸：显式指定 > 自动检测 > 设备的坐标范围
+    let explicit = config.display.width.zip(config.display.height);
+    let digitizer = (
+        selected_device.touch_x_max - selected_device.touch_x_min,
+        selected_device.touch_y_max - selected_device.touch_y_min,
+    );
+    let display = resolve_display_size(explicit, &config.display.detect, config.display.rotation, digitizer);
+    let (device_width, device_height) = (display.width, display.height);
+    info!("Display size: {}x{} (source: {})", device_width, device_height, display.source);
+    
+    let profile = profiles.find(selected_device).cloned();
+    if let Some(profile) = &profile {
+        info!("Using device profile {}", profile.id);
+    }
+
+    // 配置和命令行没有指定协议时使用设备档案的协议；
+    // auto：Type A面板（没有ABS_MT_SLOT）使用Type A协议，其余使用Type B
+    let mode_setting = match (config.device.mode, profile.as_ref().and_then(|p| p.mode)) {
+        (ModeSetting::Auto, Some(mode)) => mode,
+        (mode, _) => mode,
+    };
+    let mode = mode_setting.resolve(classify(&selected_device.info).touchscreen_type_b());
     
     let mut sim = TouchSimulation::new_with_device(selected_device.clone());
+    sim.set_profile(profile);
+    sim.set_validation(config.engine.validate);
+    sim.set_rotation(config.display.rotation);
+    sim.set_gesture_params(config.gesture_params());
+    sim.set_contact_size(config.touch);
+
+    // 信号屏蔽字要在分发线程等其他线程创建之前设置
+    let signals = if daemon {
+        match SignalListener::start(sim.cancel_token()) {
+            Ok(signals) => Some(signals),
+            Err(e) => {
+                error!("Failed to install signal listener: {}", e);
+                process::exit(1);
+            }
+        }
+    } else {
+        None
+    };
     
-    println!("Setting up touch input device...");
-    if !sim.touch_input_setup(TypeMode::TypeB, device_width, device_height) {
-        eprintln!("Failed to setup touch device!");
+    info!("Setting up touch input device ({:?})...", mode);
+    if !sim.touch_input_setup(mode, device_width, device_height) {
+        error!("Failed to setup touch device!");
         return;
     }
-    println!("Touch input device setup successful!");
+    info!("Touch input device setup successful!");
 
-    println!("Starting touch simulation in 3 seconds...");
+    if let Some(path) = &config.engine.filters {
+        match FilterConfig::load(path) {
+            Ok(filters) => {
+                info!("Loaded {} passthrough filters from {}", filters.filters.len(), path.display());
+                sim.set_passthrough_filters(Some(filters));
+            }
+            Err(e) => {
+                error!("Failed to load filters: {}", e);
+                process::exit(1);
+            }
+        }
+    }
+
+    // 转发模式：独占真实触摸屏，人手操作与自动化注入同时生效
+    if config.engine.passthrough && !sim.passthrough_start() {
+        warn!("Failed to start passthrough, continuing without it");
+    }
+
+    // 按键设备创建失败不影响触摸模拟
+    let mut keys = KeySimulation::new();
+    if !keys.key_input_setup("TouchSimulation_Keys") {
+        warn!("Failed to setup key device, key commands are disabled");
+    }
+
+    if let Some(signals) = &signals {
+        run_daemon(&mut sim, signals);
+        info!("Injection stats:\n{}", sim.metrics());
+        info!("Stopping touch simulation...");
+        sim.touch_input_stop();
+        keys.key_input_stop();
+        info!("Touch simulation stopped.");
+        return;
+    }
+
+    // 演示手势期间的热插拔事件在每次滑动之间处理
+    let watcher = start_watcher();
+
+    info!("Starting touch simulation in 3 seconds...");
     thread::sleep(Duration::from_secs(3));
 
-    println!("Executing swipe 1: ({}, {}) -> ({}, {})", X, Y, X, NY);
+    info!("Executing swipe 1: ({}, {}) -> ({}, {})", X, Y, X, NY);
+    poll_watcher(&mut sim, watcher.as_ref());
     swipe(&mut sim, X, Y, X, NY);
 
-    println!("Executing swipe 2: ({}, {}) -> ({}, {})", NX, Y, X, NY);
+    info!("Executing swipe 2: ({}, {}) -> ({}, {})", NX, Y, X, NY);
     thread::sleep(Duration::from_secs(3));
+    poll_watcher(&mut sim, watcher.as_ref());
     swipe(&mut sim, NX, Y, X, NY);
 
-    println!("Executing swipe 3: ({}, {}) -> ({}, {})", X, NY, X, Y);
+    info!("Executing swipe 3: ({}, {}) -> ({}, {})", X, NY, X, Y);
     thread::sleep(Duration::from_secs(3));
+    poll_watcher(&mut sim, watcher.as_ref());
     swipe(&mut sim, X, NY, X, Y);
 
-    println!("Executing swipe 4: ({}, {}) -> ({}, {})", X, NY, NX, Y);
+    info!("Executing swipe 4: ({}, {}) -> ({}, {})", X, NY, NX, Y);
     thread::sleep(Duration::from_secs(3));
+    poll_watcher(&mut sim, watcher.as_ref());
     swipe(&mut sim, X, NY, NX, Y);
 
-    println!("All swipes completed. Enter 'exit' to quit.");
+    println!("All swipes completed.");
+    println!("{}", sim.metrics());
+    println!("Enter 'exit' to quit.");
+    println!("Other commands: key <NAME>, longpress <NAME>, text <TEXT>, mouse <ACTION>, device info|virtual [--json], passthrough on|off, validate, stats [--json], lift, rebuild");
+    let mut mouse = MouseSimulation::new();
+
+    // 等待输入时也处理热插拔，标准输入关闭时按exit处理
+    let commands = spawn_stdin_reader();
     loop {
         print!("Enter 'exit' to quit: ");
         io::stdout().flush().unwrap();
-        
-        let mut input = String::new();
-        io::stdin().read_line(&mut input).unwrap();
-        
-        let input = input.trim().to_lowercase();
-        if input == "exit" {
-            println!("Stopping touch simulation...");
-            sim.touch_input_stop();
-            println!("Touch simulation stopped.");
-            break;
-        }
-    }
-}
\ No newline at end of file
+
+        let input = loop {
+            poll_watcher(&mut sim, watcher.as_ref());
+            match commands.recv_timeout(Duration::from_millis(100)) {
+                Ok(line) => break line,
+                Err(mpsc::RecvTimeoutError::Timeout) => {}
+                Err(mpsc::RecvTimeoutError::Disconnected) => break "exit".to_string(),
+            }
+        };
+
+        let input = input.trim();
+        let (command, arg) = input.split_once(' ').unwrap_or((input, ""));
+        match command.to_lowercase().as_str() {
+            "exit" => {
+                info!("Injection stats:\n{}", sim.metrics());
+                info!("Stopping touch simulation...");
+                sim.touch_input_stop();
+                keys.key_input_stop();
+                mouse.mouse_input_stop();
+                info!("Touch simulation stopped.");
+                break;
+            }
+            "key" | "longpress" => {
+                let Some(code) = key_code_from_name(arg) else {
+                    println!("Unknown key: {}", arg);
+                    continue;
+                };
+                let result = if command.eq_ignore_ascii_case("key") {
+                    keys.tap_key(code)
+                } else {
+                    keys.long_press_key(code)
+                };
+                if let Err(e) = result {
+                    println!("Failed to send key {}: {}", arg, e);
+                }
+            }
+            "text" => {
+                if let Err(e) = keys.type_text(arg) {
+                    println!("Failed to type text: {}", e);
+                }
+            }
+            "device" => {
+                let args: Vec<&str> = arg.split_whitespace().collect();
+                match args.split_first() {
+                    Some((&"info", rest)) => device_info_command(std::slice::from_ref(selected_device), rest),
+                    Some((&"virtual", rest)) => match sim.virtual_device() {
+                        Some(device) => device_info_command(std::slice::from_ref(&device), rest),
+                        None => println!("Touch device is not running"),
+                    },
+                    _ => println!("usage: device info|virtual [--json]"),
+                }
+            }
+            "passthrough" => match arg {
+                "on" => {
+                    if sim.passthrough_start() {
+                        println!("Passthrough enabled");
+                    } else {
+                        println!("Failed to start passthrough");
+                    }
+                }
+                "off" => {
+                    sim.passthrough_stop();
+                    println!("Passthrough disabled");
+                }
+                _ => println!("usage: passthrough on|off (currently {})", if sim.is_passthrough() { "on" } else { "off" }),
+            },
+            "validate" => match sim.validator() {
+                Some(validator) => {
+                    let validator = validator.lock().unwrap();
+                    println!("{}", validator.summary());
+                    for violation in validator.violations().iter().rev().take(10).rev() {
+                        println!("  frame {}: {:?}: {}", violation.frame, violation.rule, violation.message);
+                    }
+                }
+                None => println!("Validation is disabled, restart with --validate"),
+            },
+            "stats" => stats_command(&sim, arg),
+            "lift" => {
+                if sim.lift_all() {
+                    println!("All contacts lifted");
+                } else {
+                    println!("Touch device is not running");
+                }
+            }
+            "rebuild" => {
+                if sim.rebuild_device() {
+                    println!("Touch device rebuilt");
+                } else {
+                    println!("Failed to rebuild touch device");
+                }
+            }
+            "mouse" => {
+                if let Err(e) = mouse_command(&mut mouse, device_width, device_height, arg) {
+                    println!("Mouse command failed: {}", e);
+                }
+            }
+            _ => {}
+        }
+    }
+}
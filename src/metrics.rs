@@ -0,0 +1,168 @@
+use serde::Serialize;
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+const BUCKETS: usize = 32;
+
+// 按微秒取log2分桶的直方图，第i个桶覆盖[2^(i-1), 2^i)微秒，记录是常数时间且不分配内存
+#[derive(Debug, Clone, Default)]
+pub struct Histogram {
+    buckets: [u64; BUCKETS],
+    count: u64,
+    sum_us: u64,
+    max_us: u64,
+}
+
+impl Histogram {
+    pub fn record(&mut self, value: Duration) {
+        let us = value.as_micros().min(u64::MAX as u128) as u64;
+        let bucket = ((u64::BITS - us.leading_zeros()) as usize).min(BUCKETS - 1);
+        self.buckets[bucket] += 1;
+        self.count += 1;
+        self.sum_us = self.sum_us.saturating_add(us);
+        self.max_us = self.max_us.max(us);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    // 返回所在桶的上界，不超过记录到的最大值
+    pub fn percentile_us(&self, q: f64) -> u64 {
+        if self.count == 0 {
+            return 0;
+        }
+        let rank = ((self.count as f64 * q).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (i, &n) in self.buckets.iter().enumerate() {
+            seen += n;
+            if seen >= rank {
+                return ((1u64 << i) - 1).min(self.max_us);
+            }
+        }
+        self.max_us
+    }
+
+    pub fn summary(&self) -> HistogramSummary {
+        HistogramSummary {
+            count: self.count,
+            mean_us: self.sum_us.checked_div(self.count).unwrap_or(0),
+            p50_us: self.percentile_us(0.5),
+            p99_us: self.percentile_us(0.99),
+            max_us: self.max_us,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct HistogramSummary {
+    pub count: u64,
+    pub mean_us: u64,
+    pub p50_us: u64,
+    pub p99_us: u64,
+    pub max_us: u64,
+}
+
+impl fmt::Display for HistogramSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "n={} mean={}us p50<={}us p99<={}us max={}us",
+            self.count, self.mean_us, self.p50_us, self.p99_us, self.max_us
+        )
+    }
+}
+
+// 注入路径的统计，由分发线程和手势播放共同更新；虚拟设备重建后继续累计
+#[derive(Debug, Clone, Default)]
+pub struct Metrics {
+    pub frames: u64,
+    pub events: u64,
+    pub write_errors: u64,
+    // 写uinput返回EAGAIN后重试的次数
+    pub eagain_retries: u64,
+    // 从调用方发出命令到这一帧写完
+    pub latency: Histogram,
+    // 手势步骤实际执行时间比计划晚了多少
+    pub lateness: Histogram,
+    first_frame: Option<Instant>,
+    last_frame: Option<Instant>,
+}
+
+pub type SharedMetrics = Arc<Mutex<Metrics>>;
+
+impl Metrics {
+    pub fn record_frame(&mut self, events: usize, latency: Option<Duration>) {
+        let now = Instant::now();
+        self.first_frame.get_or_insert(now);
+        self.last_frame = Some(now);
+        self.frames += 1;
+        self.events += events as u64;
+        if let Some(latency) = latency {
+            self.latency.record(latency);
+        }
+    }
+
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        let elapsed = match (self.first_frame, self.last_frame) {
+            (Some(first), Some(last)) => last.duration_since(first).as_secs_f64(),
+            _ => 0.0,
+        };
+        MetricsSnapshot {
+            frames: self.frames,
+            events: self.events,
+            write_errors: self.write_errors,
+            eagain_retries: self.eagain_retries,
+            frames_per_second: if elapsed > 0.0 { (self.frames - 1) as f64 / elapsed } else { 0.0 },
+            latency: self.latency.summary(),
+            lateness: self.lateness.summary(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct MetricsSnapshot {
+    pub frames: u64,
+    pub events: u64,
+    pub write_errors: u64,
+    pub eagain_retries: u64,
+    // 第一帧到最后一帧之间的平均帧率
+    pub frames_per_second: f64,
+    pub latency: HistogramSummary,
+    pub lateness: HistogramSummary,
+}
+
+impl fmt::Display for MetricsSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "frames={} events={} write_errors={} eagain_retries={} fps={:.1}",
+            self.frames, self.events, self.write_errors, self.eagain_retries, self.frames_per_second
+        )?;
+        writeln!(f, "command-to-write latency: {}", self.latency)?;
+        write!(f, "scheduling lateness: {}", self.lateness)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn histogram_percentiles_use_bucket_upper_bounds() {
+        let mut histogram = Histogram::default();
+        for us in [0, 3, 90, 100, 120, 5000] {
+            histogram.record(Duration::from_micros(us));
+        }
+        let summary = histogram.summary();
+        assert_eq!(summary.count, 6);
+        assert_eq!(summary.mean_us, 885);
+        // 90、100、120都在[64, 128)这个桶里
+        assert_eq!(summary.p50_us, 127);
+        assert_eq!(summary.p99_us, 5000);
+        assert_eq!(Histogram::default().summary(), HistogramSummary::default());
+    }
+}
@@ -0,0 +1,121 @@
+use crate::uinput::{DeviceInfo, InputDevice};
+use crate::uinput_defs::InputId;
+use log::{info};
+use serde::Deserialize;
+use std::{
+    fs::File,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+pub const MOCK_PROFILE_ENV: &str = "TOUCHSIM_MOCK_PROFILE";
+
+// 模拟参考设备的描述，只在显式开启mock模式时使用
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct MockProfile {
+    pub name: String,
+    pub slots: i32,
+    pub x_min: i32,
+    pub x_max: i32,
+    pub y_min: i32,
+    pub y_max: i32,
+    pub vendor: u16,
+    pub product: u16,
+    pub has_touch_major: bool,
+    pub has_touch_minor: bool,
+    pub has_width_major: bool,
+    pub has_width_minor: bool,
+    pub has_orientation: bool,
+    pub has_pressure: bool,
+}
+
+impl Default for MockProfile {
+    // 与Go实现找不到设备时使用的模拟设备一致
+    fn default() -> Self {
+        Self {
+            name: "MockTouchDevice".to_string(),
+            slots: 10,
+            x_min: 0,
+            x_max: 1080,
+            y_min: 0,
+            y_max: 2340,
+            vendor: 0,
+            product: 0,
+            has_touch_major: true,
+            has_touch_minor: true,
+            has_width_major: true,
+            has_width_minor: true,
+            has_orientation: true,
+            has_pressure: true,
+        }
+    }
+}
+
+impl MockProfile {
+    // 从JSON文件加载，缺省字段使用默认值
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let file = File::open(path).map_err(|e| format!("failed to open mock profile {}: {}", path.display(), e))?;
+        let profile = serde_json::from_reader(file)
+            .map_err(|e| format!("invalid mock profile {}: {}", path.display(), e))?;
+        Ok(profile)
+    }
+
+    // 按模拟描述构造参考设备，path以mock:开头，没有真实的设备节点
+    pub fn to_input_device(&self) -> Result<InputDevice, Box<dyn std::error::Error>> {
+        info!("MockProfile::to_input_device: creating mock device {}", self.name);
+        Ok(InputDevice {
+            name: self.name.clone(),
+            path: format!("mock:{}", self.name),
+            input_id: InputId {
+                vendor: self.vendor,
+                product: self.product,
+                ..InputId::default()
+            },
+            slots: self.slots,
+            touch_x_min: self.x_min,
+            touch_x_max: self.x_max - self.x_min + 1,
+            touch_y_min: self.y_min,
+            touch_y_max: self.y_max - self.y_min + 1,
+            has_touch_major: self.has_touch_major,
+            has_touch_minor: self.has_touch_minor,
+            has_width_major: self.has_width_major,
+            has_width_minor: self.has_width_minor,
+            has_orientation: self.has_orientation,
+            has_pressure: self.has_pressure,
+            info: DeviceInfo {
+                mock: true,
+                ..DeviceInfo::default()
+            },
+            file: Arc::new(Mutex::new(File::open("/dev/null")?)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device_scanner::build_input_device;
+    use crate::uinput_defs::*;
+
+    #[test]
+    fn mock_ranges_match_scanned_device() {
+        let profile = MockProfile {
+            x_min: 10,
+            x_max: 1090,
+            y_min: 0,
+            y_max: 2339,
+            ..MockProfile::default()
+        };
+        let axis = |minimum, maximum| AbsInfo { minimum, maximum, ..AbsInfo::default() };
+        let info = DeviceInfo {
+            abs_infos: [(ABS_MT_POSITION_X, axis(10, 1090)), (ABS_MT_POSITION_Y, axis(0, 2339))].into_iter().collect(),
+            ..DeviceInfo::default()
+        };
+        let scanned = build_input_device(String::new(), String::new(), InputId::default(), info, File::open("/dev/null").unwrap());
+        let mock = profile.to_input_device().unwrap();
+        assert_eq!((mock.touch_x_min, mock.touch_x_max), (scanned.touch_x_min, scanned.touch_x_max));
+        assert_eq!((mock.touch_y_min, mock.touch_y_max), (scanned.touch_y_min, scanned.touch_y_max));
+        assert_eq!((mock.touch_x_max, mock.touch_y_max), (1081, 2340));
+    }
+}
@@ -0,0 +1,354 @@
+use crate::event_sink::{EventSink, RetryingSink};
+use crate::uinput::{destroy_uinput_device, new_mouse_dev, InputDevice};
+use crate::uinput_defs::*;
+use log::{debug, error, info};
+use std::{
+    io,
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+// 高精度滚轮每个刻度的值，参考input-event-codes.h中REL_WHEEL_HI_RES的说明
+const WHEEL_HI_RES_PER_DETENT: i32 = 120;
+// 每次上报的最大位移计数，低速移动时系统指针加速度基本恒定
+const DEFAULT_MAX_STEP: i32 = 8;
+const DEFAULT_STEP_INTERVAL_MS: u64 = 8;
+// 归零时使用的大步长，指针会被屏幕边缘截停，所以不需要考虑加速度
+const HOMING_STEP: i32 = 127;
+// 归零时每帧之间的间隔，避免连续写满uinput缓冲
+const HOMING_INTERVAL: Duration = Duration::from_millis(1);
+const CLICK_DELAY_MS: u64 = 15;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+}
+
+impl MouseButton {
+    pub fn code(self) -> u16 {
+        match self {
+            MouseButton::Left => BTN_LEFT,
+            MouseButton::Right => BTN_RIGHT,
+            MouseButton::Middle => BTN_MIDDLE,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "" | "left" => Some(MouseButton::Left),
+            "right" => Some(MouseButton::Right),
+            "middle" => Some(MouseButton::Middle),
+            _ => None,
+        }
+    }
+}
+
+pub struct MouseSimulation {
+    mouse_device: Option<Arc<Mutex<InputDevice>>>,
+    // 事件的写入目标，通常就是mouse_device
+    sink: Option<Arc<Mutex<dyn EventSink>>>,
+    screen_width: i32,
+    screen_height: i32,
+    // 估计的指针位置，未知时移动到绝对坐标前需要先归零
+    position: Option<(i32, i32)>,
+    max_step: i32,
+    // 低速时每个位移计数对应的屏幕像素数
+    pointer_gain: f32,
+    step_interval: Duration,
+}
+
+// sink不一定实现Debug，只输出设备和位置状态
+impl std::fmt::Debug for MouseSimulation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MouseSimulation")
+            .field("mouse_device", &self.mouse_device)
+            .field("screen_width", &self.screen_width)
+            .field("screen_height", &self.screen_height)
+            .field("position", &self.position)
+            .field("max_step", &self.max_step)
+            .field("pointer_gain", &self.pointer_gain)
+            .field("step_interval", &self.step_interval)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for MouseSimulation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MouseSimulation {
+    pub fn new() -> Self {
+        Self {
+            mouse_device: None,
+            sink: None,
+            screen_width: 0,
+            screen_height: 0,
+            position: None,
+            max_step: DEFAULT_MAX_STEP,
+            pointer_gain: 1.0,
+            step_interval: Duration::from_millis(DEFAULT_STEP_INTERVAL_MS),
+        }
+    }
+
+    // 不创建uinput设备，事件写到给定的sink，用于测试
+    pub fn new_with_sink(sink: Arc<Mutex<dyn EventSink>>, width: i32, height: i32) -> Self {
+        Self {
+            sink: Some(sink),
+            screen_width: width,
+            screen_height: height,
+            ..Self::new()
+        }
+    }
+
+    pub fn mouse_input_setup(&mut self, name: &str, width: i32, height: i32) -> bool {
+        info!("mouse_input_setup: name={}, width={}, height={}", name, width, height);
+        self.screen_width = width;
+        self.screen_height = height;
+        if self.sink.is_some() {
+            return true;
+        }
+
+        match new_mouse_dev(name) {
+            Ok(dev) => {
+                let device = Arc::new(Mutex::new(dev));
+                self.sink = Some(device.clone());
+                self.mouse_device = Some(device);
+                self.position = None;
+                true
+            }
+            Err(e) => {
+                error!("mouse_input_setup: failed to create mouse device: {}", e);
+                false
+            }
+        }
+    }
+
+    pub fn mouse_input_stop(&mut self) {
+        self.sink = None;
+        if let Some(mouse_device) = self.mouse_device.take() {
+            let device = mouse_device.lock().unwrap();
+            let _ = destroy_uinput_device(&device);
+        }
+        self.position = None;
+    }
+
+    // 设置步进参数：max_step为每次上报的最大计数，gain为低速时每计数对应的像素
+    pub fn set_stepping(&mut self, max_step: i32, pointer_gain: f32, step_interval: Duration) {
+        self.max_step = max_step.max(1);
+        self.pointer_gain = if pointer_gain > 0.0 { pointer_gain } else { 1.0 };
+        self.step_interval = step_interval;
+    }
+
+    pub fn position(&self) -> Option<(i32, i32)> {
+        self.position
+    }
+
+    pub fn move_relative(&mut self, dx: i32, dy: i32) -> io::Result<()> {
+        self.emit(&[(EV_REL, REL_X, dx), (EV_REL, REL_Y, dy)])?;
+        if let Some((x, y)) = self.position {
+            let px = x + (dx as f32 * self.pointer_gain).round() as i32;
+            let py = y + (dy as f32 * self.pointer_gain).round() as i32;
+            self.position = Some(self.clamp_to_screen(px, py));
+        }
+        Ok(())
+    }
+
+    // 移动到屏幕绝对坐标：位置未知时先推到左上角归零，再以小步长匀速移动，
+    // 避免触发指针加速导致落点偏移
+    pub fn move_to(&mut self, x: i32, y: i32) -> io::Result<()> {
+        let result = self.step_to(x, y);
+        if result.is_err() {
+            // 中途失败时不知道已经移动了多少，下次移动重新归零
+            self.position = None;
+        }
+        result
+    }
+
+    fn step_to(&mut self, x: i32, y: i32) -> io::Result<()> {
+        let (x, y) = self.clamp_to_screen(x, y);
+        if self.position.is_none() {
+            self.home()?;
+        }
+        let (cur_x, cur_y) = self.position.unwrap_or((0, 0));
+
+        let counts_x = ((x - cur_x) as f32 / self.pointer_gain).round() as i32;
+        let counts_y = ((y - cur_y) as f32 / self.pointer_gain).round() as i32;
+        let steps = ((counts_x.abs().max(counts_y.abs()) + self.max_step - 1) / self.max_step).max(1);
+        debug!("move_to: ({}, {}) -> ({}, {}) in {} steps", cur_x, cur_y, x, y, steps);
+
+        let mut sent_x = 0;
+        let mut sent_y = 0;
+        for i in 1..=steps {
+            // 按累计误差分配每步位移，保证路径为直线且总量精确
+            let target_x = counts_x * i / steps;
+            let target_y = counts_y * i / steps;
+            self.emit(&[(EV_REL, REL_X, target_x - sent_x), (EV_REL, REL_Y, target_y - sent_y)])?;
+            sent_x = target_x;
+            sent_y = target_y;
+            thread::sleep(self.step_interval);
+        }
+
+        self.position = Some((x, y));
+        Ok(())
+    }
+
+    pub fn press_button(&mut self, button: MouseButton) -> io::Result<()> {
+        self.emit(&[(EV_KEY, button.code(), 1)])
+    }
+
+    pub fn release_button(&mut self, button: MouseButton) -> io::Result<()> {
+        self.emit(&[(EV_KEY, button.code(), 0)])
+    }
+
+    pub fn click(&mut self, button: MouseButton) -> io::Result<()> {
+        self.press_button(button)?;
+        thread::sleep(Duration::from_millis(CLICK_DELAY_MS));
+        self.release_button(button)
+    }
+
+    pub fn click_at(&mut self, x: i32, y: i32, button: MouseButton) -> io::Result<()> {
+        self.move_to(x, y)?;
+        self.click(button)
+    }
+
+    pub fn drag(&mut self, start_x: i32, start_y: i32, end_x: i32, end_y: i32, button: MouseButton) -> io::Result<()> {
+        self.move_to(start_x, start_y)?;
+        self.press_button(button)?;
+        let result = self.move_to(end_x, end_y);
+        // 无论移动是否成功都要松开按键，避免按键卡住
+        self.release_button(button)?;
+        result
+    }
+
+    // 滚动指定刻度数，vertical为正向上滚，horizontal为正向右滚
+    pub fn scroll(&mut self, vertical: i32, horizontal: i32) -> io::Result<()> {
+        let mut events = Vec::with_capacity(4);
+        if vertical != 0 {
+            events.push((EV_REL, REL_WHEEL_HI_RES, vertical * WHEEL_HI_RES_PER_DETENT));
+            events.push((EV_REL, REL_WHEEL, vertical));
+        }
+        if horizontal != 0 {
+            events.push((EV_REL, REL_HWHEEL_HI_RES, horizontal * WHEEL_HI_RES_PER_DETENT));
+            events.push((EV_REL, REL_HWHEEL, horizontal));
+        }
+        if events.is_empty() {
+            return Ok(());
+        }
+        self.emit(&events)
+    }
+
+    // 把指针推到左上角，之后的位置估计以(0, 0)为起点
+    fn home(&mut self) -> io::Result<()> {
+        let max_counts = (self.screen_width.max(self.screen_height) as f32 / self.pointer_gain) as i32;
+        let steps = max_counts * 2 / HOMING_STEP + 1;
+        debug!("move_to: homing pointer with {} steps", steps);
+        for _ in 0..steps {
+            self.emit(&[(EV_REL, REL_X, -HOMING_STEP), (EV_REL, REL_Y, -HOMING_STEP)])?;
+            thread::sleep(HOMING_INTERVAL);
+        }
+        self.position = Some((0, 0));
+        Ok(())
+    }
+
+    fn clamp_to_screen(&self, x: i32, y: i32) -> (i32, i32) {
+        (
+            x.clamp(0, (self.screen_width - 1).max(0)),
+            y.clamp(0, (self.screen_height - 1).max(0)),
+        )
+    }
+
+    // uinput以O_NONBLOCK打开，与注入路径一样在EAGAIN时重试
+    fn emit(&mut self, events: &[(u16, u16, i32)]) -> io::Result<()> {
+        let sink = self.sink.as_ref().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotConnected, "mouse device is not set up")
+        })?;
+
+        let mut guard = sink.lock().unwrap();
+        let mut device = RetryingSink::new(&mut *guard);
+        for &(event_type, code, value) in events {
+            device.write_event(event_type, code, value)?;
+        }
+        device.write_event(EV_SYN, SYN_REPORT, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event_sink::MemorySink;
+
+    fn mouse(width: i32, height: i32) -> (MouseSimulation, Arc<Mutex<MemorySink>>) {
+        let sink = Arc::new(Mutex::new(MemorySink::new()));
+        let mut mouse = MouseSimulation::new_with_sink(sink.clone(), width, height);
+        mouse.set_stepping(8, 1.0, Duration::ZERO);
+        (mouse, sink)
+    }
+
+    // 每帧的(REL_X, REL_Y)
+    fn rel_frames(sink: &Mutex<MemorySink>) -> Vec<(i32, i32)> {
+        let events = std::mem::take(&mut sink.lock().unwrap().events);
+        events
+            .split(|&event| event == (EV_SYN, SYN_REPORT, 0))
+            .filter(|frame| !frame.is_empty())
+            .map(|frame| match frame {
+                [(EV_REL, REL_X, dx), (EV_REL, REL_Y, dy)] => (*dx, *dy),
+                other => panic!("unexpected frame {:?}", other),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn move_to_homes_once_then_splits_into_small_steps() {
+        let (mut mouse, sink) = mouse(100, 50);
+        mouse.move_to(20, 10).unwrap();
+        // 100个计数归零需要2帧，20个计数按8拆成3步，按累计误差分配
+        assert_eq!(rel_frames(&sink), vec![(-127, -127), (-127, -127), (6, 3), (7, 3), (7, 4)]);
+        assert_eq!(mouse.position(), Some((20, 10)));
+
+        // 位置已知后不再归零
+        mouse.move_to(4, 10).unwrap();
+        assert_eq!(rel_frames(&sink), vec![(-8, 0), (-8, 0)]);
+        assert_eq!(mouse.position(), Some((4, 10)));
+    }
+
+    #[test]
+    fn move_to_clamps_to_screen() {
+        let (mut mouse, sink) = mouse(100, 50);
+        mouse.move_to(500, -30).unwrap();
+        assert_eq!(mouse.position(), Some((99, 0)));
+        let steps = &rel_frames(&sink)[2..];
+        assert_eq!(steps.len(), 13);
+        assert!(steps.iter().all(|&(dx, dy)| (0..=8).contains(&dx) && dy == 0), "{:?}", steps);
+        assert_eq!(steps.iter().map(|&(dx, _)| dx).sum::<i32>(), 99);
+    }
+
+    // 写入若干事件后一直返回EAGAIN
+    struct FullSink {
+        remaining: usize,
+    }
+
+    impl EventSink for FullSink {
+        fn write_event(&mut self, _: u16, _: u16, _: i32) -> io::Result<()> {
+            if self.remaining == 0 {
+                return Err(io::ErrorKind::WouldBlock.into());
+            }
+            self.remaining -= 1;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn failed_move_forgets_position() {
+        let sink = Arc::new(Mutex::new(FullSink { remaining: 10 }));
+        let mut mouse = MouseSimulation::new_with_sink(sink, 100, 50);
+        mouse.set_stepping(8, 1.0, Duration::ZERO);
+        let error = mouse.move_to(20, 10).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::WouldBlock);
+        assert_eq!(mouse.position(), None);
+    }
+}
@@ -0,0 +1,430 @@
+use crate::event_sink::EventSink;
+use crate::uinput::InputDevice;
+use crate::uinput_defs::*;
+use log::{info, warn};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+pub enum Rule {
+    // ABS_MT_SLOT超出设备声明的slot范围，或Type A设备使用了ABS_MT_SLOT
+    SlotRange,
+    // tracking id生命周期：释放空slot、与其他slot的活动触点重复、更新空slot的轴
+    TrackingIdLifecycle,
+    // 活动slot重复写入相同的tracking id
+    RedundantTrackingId,
+    // SYN_REPORT时BTN_TOUCH与活动触点数量不一致，或一帧内重复写BTN_TOUCH
+    BtnTouch,
+    // 值超出AbsInfo范围或使用了设备没有声明的轴
+    AbsRange,
+    // Type A的SYN_MT_REPORT位置错误
+    MtReportPlacement,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Violation {
+    pub frame: usize,
+    pub rule: Rule,
+    pub message: String,
+}
+
+// 被校验设备声明的能力；abs_infos为空时不检查取值范围
+#[derive(Debug, Clone, Default)]
+pub struct ValidatorSpec {
+    pub type_b: Option<bool>,
+    pub slots: Option<i32>,
+    pub abs_infos: BTreeMap<u16, AbsInfo>,
+}
+
+impl ValidatorSpec {
+    pub fn from_device(device: &InputDevice) -> Self {
+        let abs_infos = device.info.abs_infos.clone();
+        let type_b = if abs_infos.is_empty() { None } else { Some(abs_infos.contains_key(&ABS_MT_SLOT)) };
+        Self {
+            type_b,
+            slots: abs_infos.get(&ABS_MT_SLOT).map(|abs| abs.maximum + 1),
+            abs_infos,
+        }
+    }
+
+    // 不知道设备能力时（例如录制文件）只检查协议本身
+    pub fn permissive() -> Self {
+        Self::default()
+    }
+}
+
+// 逐个接收事件并检查多点触控协议规则，违规按帧号记录
+#[derive(Debug)]
+pub struct Validator {
+    spec: ValidatorSpec,
+    frame: usize,
+    type_a: bool,
+    current_slot: i32,
+    // 收到过ABS_MT_SLOT，确认是Type B
+    saw_slot: bool,
+    // Type B：slot -> 活动触点的tracking id
+    active: HashMap<i32, i32>,
+    btn_touch: bool,
+    btn_touch_writes: usize,
+    // Type A：当前触点是否已有数据，本帧的触点数和空SYN_MT_REPORT数
+    contact_open: bool,
+    contacts: usize,
+    empty_reports: usize,
+    // SYN_DROPPED之后到下一个SYN_REPORT的事件被内核丢弃
+    dropping: bool,
+    // 丢帧后不知道真实的触点状态，直到所有触点抬起前不检查生命周期和BTN_TOUCH
+    resyncing: bool,
+    violations: Vec<Violation>,
+}
+
+impl Validator {
+    pub fn new(spec: ValidatorSpec) -> Self {
+        Self {
+            type_a: spec.type_b == Some(false),
+            spec,
+            frame: 0,
+            current_slot: 0,
+            saw_slot: false,
+            active: HashMap::new(),
+            btn_touch: false,
+            btn_touch_writes: 0,
+            contact_open: false,
+            contacts: 0,
+            empty_reports: 0,
+            dropping: false,
+            resyncing: false,
+            violations: Vec::new(),
+        }
+    }
+
+    pub fn violations(&self) -> &[Violation] {
+        &self.violations
+    }
+
+    pub fn frames(&self) -> usize {
+        self.frame
+    }
+
+    fn report(&mut self, rule: Rule, message: String) {
+        warn!("frame {}: {:?}: {}", self.frame, rule, message);
+        self.violations.push(Violation {
+            frame: self.frame,
+            rule,
+            message,
+        });
+    }
+
+    pub fn push(&mut self, event_type: u16, code: u16, value: i32) {
+        match (event_type, code) {
+            (EV_SYN, SYN_DROPPED) => self.dropping = true,
+            (EV_SYN, SYN_REPORT) if self.dropping => {
+                info!("frame {}: SYN_DROPPED, resyncing", self.frame);
+                self.dropping = false;
+                self.resyncing = true;
+                self.active.clear();
+                self.next_frame();
+            }
+            _ if self.dropping => {}
+            (EV_SYN, SYN_REPORT) => self.end_frame(),
+            (EV_SYN, SYN_MT_REPORT) => self.mt_report(),
+            (EV_KEY, BTN_TOUCH) => {
+                self.btn_touch_writes += 1;
+                if self.btn_touch_writes > 1 {
+                    self.report(Rule::BtnTouch, format!("BTN_TOUCH written {} times in one frame", self.btn_touch_writes));
+                }
+                self.btn_touch = value != 0;
+            }
+            (EV_ABS, code) => {
+                self.check_range(code, value);
+                if code == ABS_MT_SLOT {
+                    self.slot(value);
+                } else if (ABS_MT_TOUCH_MAJOR..=ABS_MT_TOOL_Y).contains(&code) {
+                    self.mt_axis(code, value);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn check_range(&mut self, code: u16, value: i32) {
+        if self.spec.abs_infos.is_empty() {
+            return;
+        }
+        let name = abs_code_name(code).map_or_else(|| format!("ABS_0x{:02x}", code), str::to_string);
+        let Some(abs) = self.spec.abs_infos.get(&code).copied() else {
+            self.report(Rule::AbsRange, format!("{} is not advertised by the device", name));
+            return;
+        };
+        // slot越界由SlotRange规则报告
+        if code == ABS_MT_SLOT || (code == ABS_MT_TRACKING_ID && value == -1) {
+            return;
+        }
+        if value < abs.minimum || value > abs.maximum {
+            self.report(Rule::AbsRange, format!("{}={} outside [{}, {}]", name, value, abs.minimum, abs.maximum));
+        }
+    }
+
+    fn slot(&mut self, value: i32) {
+        if self.type_a {
+            self.report(Rule::SlotRange, "ABS_MT_SLOT on a Type A stream".to_string());
+            return;
+        }
+        if value < 0 || self.spec.slots.is_some_and(|slots| value >= slots) {
+            self.report(Rule::SlotRange, format!("slot {} outside [0, {})", value, self.spec.slots.unwrap_or(0)));
+        }
+        self.current_slot = value;
+        self.saw_slot = true;
+    }
+
+    fn mt_axis(&mut self, code: u16, value: i32) {
+        if self.type_a {
+            self.contact_open = true;
+            return;
+        }
+
+        let slot = self.current_slot;
+        self.contact_open = true;
+        if code != ABS_MT_TRACKING_ID {
+            // 协议未确定时，这可能是Type A触点的数据
+            let type_b = self.saw_slot || self.spec.type_b == Some(true);
+            if type_b && !self.resyncing && !self.active.contains_key(&slot) {
+                let name = abs_code_name(code).unwrap_or("ABS_MT_?");
+                self.report(Rule::TrackingIdLifecycle, format!("{} on slot {} without an active contact", name, slot));
+            }
+            return;
+        }
+
+        if value < 0 {
+            if self.active.remove(&slot).is_none() && !self.resyncing {
+                self.report(Rule::TrackingIdLifecycle, format!("release of slot {} which has no active contact", slot));
+            }
+            return;
+        }
+        match self.active.get(&slot) {
+            Some(&id) if id == value => {
+                self.report(Rule::RedundantTrackingId, format!("tracking id {} re-sent on active slot {}", value, slot));
+            }
+            _ => {
+                if let Some((&other, _)) = self.active.iter().find(|(&other, &id)| other != slot && id == value) {
+                    self.report(
+                        Rule::TrackingIdLifecycle,
+                        format!("tracking id {} on slot {} is still active on slot {}", value, slot, other),
+                    );
+                }
+                self.active.insert(slot, value);
+            }
+        }
+    }
+
+    fn mt_report(&mut self) {
+        if !self.type_a {
+            if self.spec.type_b == Some(true) {
+                self.report(Rule::MtReportPlacement, "SYN_MT_REPORT on a Type B stream".to_string());
+                return;
+            }
+            // 没有设备信息时，第一次出现SYN_MT_REPORT即按Type A处理
+            self.type_a = true;
+            self.active.clear();
+        }
+        if self.contact_open {
+            self.contacts += 1;
+            self.contact_open = false;
+        } else {
+            self.empty_reports += 1;
+        }
+    }
+
+    fn end_frame(&mut self) {
+        let contacts = if self.type_a {
+            if self.contact_open {
+                self.report(Rule::MtReportPlacement, "contact data not terminated by SYN_MT_REPORT before SYN_REPORT".to_string());
+            }
+            // 只有抬起全部触点时才允许空的SYN_MT_REPORT，且只能有一个
+            if self.empty_reports > 0 && self.contacts > 0 {
+                self.report(Rule::MtReportPlacement, "empty SYN_MT_REPORT in a frame with contacts".to_string());
+            } else if self.empty_reports > 1 {
+                self.report(Rule::MtReportPlacement, format!("{} empty SYN_MT_REPORTs in one frame", self.empty_reports));
+            }
+            self.contacts
+        } else {
+            self.active.len()
+        };
+
+        if self.resyncing {
+            self.resyncing = self.btn_touch || contacts > 0;
+        } else if self.btn_touch != (contacts > 0) {
+            self.report(Rule::BtnTouch, format!("BTN_TOUCH={} with {} active contacts", self.btn_touch as i32, contacts));
+        }
+        self.next_frame();
+    }
+
+    fn next_frame(&mut self) {
+        self.frame += 1;
+        self.btn_touch_writes = 0;
+        self.contact_open = false;
+        self.contacts = 0;
+        self.empty_reports = 0;
+    }
+
+    // 按规则汇总
+    pub fn summary(&self) -> String {
+        if self.violations.is_empty() {
+            return format!("{} frames, no protocol violations", self.frame);
+        }
+        let mut counts: BTreeMap<Rule, usize> = BTreeMap::new();
+        for violation in &self.violations {
+            *counts.entry(violation.rule).or_default() += 1;
+        }
+        let counts: Vec<String> = counts.iter().map(|(rule, count)| format!("{:?}={}", rule, count)).collect();
+        format!("{} frames, {} violations ({})", self.frame, self.violations.len(), counts.join(", "))
+    }
+}
+
+pub type SharedValidator = Arc<Mutex<Validator>>;
+
+// 透传到内部sink的同时校验事件流；validator为None时只透传，
+// 注入线程和转发线程可以共享同一个校验器
+#[derive(Debug)]
+pub struct ValidatingSink<S> {
+    inner: S,
+    validator: Option<SharedValidator>,
+}
+
+impl<S: EventSink> ValidatingSink<S> {
+    pub fn new(inner: S, spec: ValidatorSpec) -> Self {
+        Self::shared(inner, Some(Arc::new(Mutex::new(Validator::new(spec)))))
+    }
+
+    pub fn shared(inner: S, validator: Option<SharedValidator>) -> Self {
+        Self { inner, validator }
+    }
+
+    pub fn validator(&self) -> Option<&SharedValidator> {
+        self.validator.as_ref()
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: EventSink> EventSink for ValidatingSink<S> {
+    fn write_event(&mut self, event_type: u16, code: u16, value: i32) -> std::io::Result<()> {
+        if let Some(validator) = &self.validator {
+            validator.lock().unwrap().push(event_type, code, value);
+        }
+        self.inner.write_event(event_type, code, value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event_sink::MemorySink;
+
+    fn type_b_spec() -> ValidatorSpec {
+        let abs = |maximum| AbsInfo { maximum, ..AbsInfo::default() };
+        ValidatorSpec {
+            type_b: Some(true),
+            slots: Some(10),
+            abs_infos: [
+                (ABS_MT_SLOT, abs(9)),
+                (ABS_MT_TRACKING_ID, abs(65535)),
+                (ABS_MT_POSITION_X, abs(1080)),
+                (ABS_MT_POSITION_Y, abs(2340)),
+            ]
+            .into_iter()
+            .collect(),
+        }
+    }
+
+    fn run(spec: ValidatorSpec, events: &[(u16, u16, i32)]) -> Vec<(usize, Rule)> {
+        let mut sink = ValidatingSink::new(MemorySink::new(), spec);
+        for &(event_type, code, value) in events {
+            sink.write_event(event_type, code, value).unwrap();
+        }
+        let validator = Arc::clone(sink.validator().unwrap());
+        assert_eq!(sink.into_inner().events, events);
+        let validator = validator.lock().unwrap();
+        validator.violations().iter().map(|v| (v.frame, v.rule)).collect()
+    }
+
+    #[test]
+    fn clean_type_b_stream_passes() {
+        let events = [
+            (EV_ABS, ABS_MT_SLOT, 9),
+            (EV_ABS, ABS_MT_TRACKING_ID, 1),
+            (EV_ABS, ABS_MT_POSITION_X, 100),
+            (EV_ABS, ABS_MT_POSITION_Y, 200),
+            (EV_KEY, BTN_TOUCH, 1),
+            (EV_SYN, SYN_REPORT, 0),
+            (EV_ABS, ABS_MT_POSITION_X, 110),
+            (EV_SYN, SYN_REPORT, 0),
+            (EV_ABS, ABS_MT_TRACKING_ID, -1),
+            (EV_KEY, BTN_TOUCH, 0),
+            (EV_SYN, SYN_REPORT, 0),
+        ];
+        assert!(run(type_b_spec(), &events).is_empty());
+    }
+
+    #[test]
+    fn reports_type_b_violations_with_frame_numbers() {
+        let events = [
+            (EV_ABS, ABS_MT_SLOT, 10),
+            (EV_ABS, ABS_MT_SLOT, 0),
+            (EV_ABS, ABS_MT_TRACKING_ID, 5),
+            (EV_ABS, ABS_MT_POSITION_X, 5000),
+            (EV_ABS, ABS_MT_PRESSURE, 10),
+            (EV_SYN, SYN_REPORT, 0),
+            (EV_ABS, ABS_MT_TRACKING_ID, 5),
+            (EV_ABS, ABS_MT_SLOT, 1),
+            (EV_ABS, ABS_MT_TRACKING_ID, 5),
+            (EV_KEY, BTN_TOUCH, 1),
+            (EV_KEY, BTN_TOUCH, 1),
+            (EV_SYN, SYN_REPORT, 0),
+            (EV_ABS, ABS_MT_SLOT, 2),
+            (EV_ABS, ABS_MT_TRACKING_ID, -1),
+            (EV_ABS, ABS_MT_POSITION_Y, 5),
+            (EV_SYN, SYN_REPORT, 0),
+        ];
+        assert_eq!(
+            run(type_b_spec(), &events),
+            vec![
+                (0, Rule::SlotRange),
+                (0, Rule::AbsRange),
+                (0, Rule::AbsRange),
+                (0, Rule::BtnTouch),
+                (1, Rule::RedundantTrackingId),
+                (1, Rule::TrackingIdLifecycle),
+                (1, Rule::BtnTouch),
+                (2, Rule::TrackingIdLifecycle),
+                (2, Rule::TrackingIdLifecycle),
+            ]
+        );
+    }
+
+    #[test]
+    fn checks_type_a_mt_report_placement() {
+        let events = [
+            (EV_ABS, ABS_MT_POSITION_X, 10),
+            (EV_SYN, SYN_MT_REPORT, 0),
+            (EV_ABS, ABS_MT_POSITION_X, 20),
+            (EV_SYN, SYN_MT_REPORT, 0),
+            (EV_KEY, BTN_TOUCH, 1),
+            (EV_SYN, SYN_REPORT, 0),
+            (EV_ABS, ABS_MT_POSITION_X, 10),
+            (EV_SYN, SYN_MT_REPORT, 0),
+            (EV_SYN, SYN_MT_REPORT, 0),
+            (EV_ABS, ABS_MT_POSITION_X, 20),
+            (EV_SYN, SYN_REPORT, 0),
+            (EV_SYN, SYN_MT_REPORT, 0),
+            (EV_KEY, BTN_TOUCH, 0),
+            (EV_SYN, SYN_REPORT, 0),
+        ];
+        assert_eq!(
+            run(ValidatorSpec::permissive(), &events),
+            vec![(1, Rule::MtReportPlacement), (1, Rule::MtReportPlacement)]
+        );
+    }
+}
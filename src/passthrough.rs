@@ -0,0 +1,676 @@
+use crate::device_scanner::read_abs_infos;
+use crate::event_decoder::decode_record;
+use crate::event_sink::{EventSink, RetryingSink};
+use crate::input_filter::{AxisRange, FilterChain, FilterConfig};
+use crate::metrics::SharedMetrics;
+use crate::mt_validator::{SharedValidator, ValidatingSink};
+use crate::tracking_id::TrackingIdAllocator;
+use crate::uinput::{DeviceInfo, InputDevice};
+use crate::uinput_defs::*;
+use log::{debug, error, info, warn};
+use std::{
+    collections::{BTreeSet, HashMap},
+    io::Read,
+    os::unix::io::AsRawFd,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Instant,
+};
+
+const POLL_TIMEOUT_MS: i32 = 200;
+
+// 转发时保留的MT轴，其余轴虚拟设备没有声明
+const FORWARDED_MT_AXES: [u16; 8] = [
+    ABS_MT_POSITION_X,
+    ABS_MT_POSITION_Y,
+    ABS_MT_TOUCH_MAJOR,
+    ABS_MT_TOUCH_MINOR,
+    ABS_MT_WIDTH_MAJOR,
+    ABS_MT_WIDTH_MINOR,
+    ABS_MT_ORIENTATION,
+    ABS_MT_PRESSURE,
+];
+
+// 真实手指和注入手指共用的输出状态：按合并后的触点数生成BTN_TOUCH和BTN_TOOL_*，
+// 并记录虚拟设备当前选中的slot，两个写入线程都只在需要时切换ABS_MT_SLOT；
+// 注入触点保留的slot和真实手指占用的slot也记录在这里，两边不会写同一个slot
+#[derive(Debug, Default)]
+pub struct TouchButtonState {
+    real: usize,
+    synthetic: usize,
+    touch: bool,
+    tool: Option<u16>,
+    pub slot: Option<i32>,
+    reserved: BTreeSet<usize>,
+    real_slots: BTreeSet<usize>,
+}
+
+impl TouchButtonState {
+    // 为注入触点保留slot，真实手指正在使用时失败；保留到虚拟设备重建为止
+    pub fn reserve(&mut self, slot: usize) -> bool {
+        if self.real_slots.contains(&slot) {
+            return false;
+        }
+        self.reserved.insert(slot);
+        true
+    }
+
+    pub fn reserved(&self) -> Vec<usize> {
+        self.reserved.iter().copied().collect()
+    }
+
+    pub fn set_real(&mut self, contacts: usize) -> Vec<(u16, u16, i32)> {
+        self.real = contacts;
+        self.update()
+    }
+
+    pub fn set_synthetic(&mut self, contacts: usize) -> Vec<(u16, u16, i32)> {
+        self.synthetic = contacts;
+        self.update()
+    }
+
+    fn update(&mut self) -> Vec<(u16, u16, i32)> {
+        let contacts = self.real + self.synthetic;
+        let mut out = Vec::new();
+        if self.touch != (contacts > 0) {
+            self.touch = contacts > 0;
+            out.push((EV_KEY, BTN_TOUCH, self.touch as i32));
+        }
+        let tool = tool_for(contacts);
+        if tool != self.tool {
+            if let Some(old) = self.tool {
+                out.push((EV_KEY, old, 0));
+            }
+            if let Some(new) = tool {
+                out.push((EV_KEY, new, 1));
+            }
+            self.tool = tool;
+        }
+        out
+    }
+}
+
+// 手指数量对应的BTN_TOOL_*，五指及以上都是QUINTTAP
+fn tool_for(contacts: usize) -> Option<u16> {
+    match contacts {
+        0 => None,
+        1 => Some(BTN_TOOL_FINGER),
+        2 => Some(BTN_TOOL_DOUBLETAP),
+        3 => Some(BTN_TOOL_TRIPLETAP),
+        4 => Some(BTN_TOOL_QUADTAP),
+        _ => Some(BTN_TOOL_QUINTTAP),
+    }
+}
+
+// 轴的(最小值, 最大值)
+type Range = (i32, i32);
+
+// 真实设备和虚拟设备的轴范围不同时按比例换算，两边都声明了有效范围的轴才换算
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AxisScaling {
+    ranges: HashMap<u16, (Range, Range)>,
+}
+
+impl AxisScaling {
+    pub fn between(source: &DeviceInfo, target: &DeviceInfo) -> Self {
+        let range = |info: &DeviceInfo, code: u16| {
+            info.abs_infos
+                .get(&code)
+                .filter(|abs| abs.maximum > abs.minimum)
+                .map(|abs| (abs.minimum, abs.maximum))
+        };
+        let ranges = FORWARDED_MT_AXES
+            .iter()
+            .filter_map(|&code| Some((code, (range(source, code)?, range(target, code)?))))
+            .filter(|(_, (from, to))| from != to)
+            .collect();
+        Self { ranges }
+    }
+
+    pub fn is_identity(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    // 超出真实设备范围的值先截断，结果四舍五入
+    fn apply(&self, code: u16, value: i32) -> i32 {
+        let Some(&((from_min, from_max), (to_min, to_max))) = self.ranges.get(&code) else {
+            return value;
+        };
+        let from_len = from_max as i64 - from_min as i64;
+        let to_len = to_max as i64 - to_min as i64;
+        let offset = value.clamp(from_min, from_max) as i64 - from_min as i64;
+        (to_min as i64 + (offset * to_len + from_len / 2) / from_len) as i32
+    }
+}
+
+// 把真实设备的slot映射到虚拟设备上不与注入触点冲突的slot
+#[derive(Debug)]
+pub struct SlotRemapper {
+    slots: usize,
+    real_slot: i32,
+    mapping: HashMap<i32, usize>,
+    tracking_ids: Arc<TrackingIdAllocator>,
+    // 虚拟slot上的真实手指持有的tracking id，抬起时交还分配器
+    held_ids: HashMap<usize, i32>,
+    scaling: AxisScaling,
+}
+
+impl SlotRemapper {
+    // slots为虚拟设备的slot数量，注入触点保留的slot记录在TouchButtonState中，
+    // tracking id与注入触点共用分配器，转发的轴值按scaling换算到虚拟设备的范围
+    pub fn new(slots: usize, current_real_slot: i32, tracking_ids: Arc<TrackingIdAllocator>, scaling: AxisScaling) -> Self {
+        Self {
+            slots,
+            real_slot: current_real_slot,
+            mapping: HashMap::new(),
+            tracking_ids,
+            held_ids: HashMap::new(),
+            scaling,
+        }
+    }
+
+    fn allocate(&mut self, real_slot: i32, buttons: &mut TouchButtonState) -> Option<usize> {
+        if let Some(&slot) = self.mapping.get(&real_slot) {
+            return Some(slot);
+        }
+        let slot = (0..self.slots).find(|slot| !buttons.reserved.contains(slot) && !buttons.real_slots.contains(slot))?;
+        self.mapping.insert(real_slot, slot);
+        buttons.real_slots.insert(slot);
+        Some(slot)
+    }
+
+    // 把一个真实设备的帧（不含SYN_REPORT）转换成虚拟设备的事件，
+    // 当前slot从共享状态读取，注入线程切换过slot时会重新选择；调用方需要持有虚拟设备的锁直到写完
+    pub fn translate_frame(&mut self, frame: &[(u16, u16, i32)], buttons: &mut TouchButtonState) -> Vec<(u16, u16, i32)> {
+        let mut out = Vec::new();
+        let mut current = buttons.slot;
+        let mut select = |out: &mut Vec<(u16, u16, i32)>, slot: usize| {
+            if current != Some(slot as i32) {
+                out.push((EV_ABS, ABS_MT_SLOT, slot as i32));
+                current = Some(slot as i32);
+            }
+        };
+
+        for &(event_type, code, value) in frame {
+            match (event_type, code) {
+                (EV_ABS, ABS_MT_SLOT) => self.real_slot = value,
+                (EV_ABS, ABS_MT_TRACKING_ID) if value >= 0 => {
+                    let Some(slot) = self.allocate(self.real_slot, buttons) else {
+                        warn!("SlotRemapper: no free slot for real slot {}, dropping contact", self.real_slot);
+                        continue;
+                    };
+                    select(&mut out, slot);
+                    if let Some(old) = self.held_ids.remove(&slot) {
+                        self.tracking_ids.release(old);
+                    }
+                    let tracking_id = self.tracking_ids.allocate();
+                    self.held_ids.insert(slot, tracking_id);
+                    out.push((EV_ABS, ABS_MT_TRACKING_ID, tracking_id));
+                }
+                (EV_ABS, ABS_MT_TRACKING_ID) => {
+                    if let Some(slot) = self.mapping.remove(&self.real_slot) {
+                        buttons.real_slots.remove(&slot);
+                        if let Some(tracking_id) = self.held_ids.remove(&slot) {
+                            self.tracking_ids.release(tracking_id);
+                        }
+                        select(&mut out, slot);
+                        out.push((EV_ABS, ABS_MT_TRACKING_ID, -1));
+                    }
+                }
+                (EV_ABS, code) if FORWARDED_MT_AXES.contains(&code) => {
+                    if let Some(&slot) = self.mapping.get(&self.real_slot) {
+                        select(&mut out, slot);
+                        out.push((EV_ABS, code, self.scaling.apply(code, value)));
+                    }
+                }
+                // BTN_TOUCH和BTN_TOOL_*按合并后的触点数重新生成，
+                // 单点ABS_X/Y、EV_MSC等虚拟设备没有声明的事件不转发
+                _ => {}
+            }
+        }
+        buttons.slot = current;
+        out.extend(buttons.set_real(self.mapping.len()));
+        out
+    }
+
+    // 抬起所有真实手指，用于SYN_DROPPED之后和停止转发时
+    pub fn lift_all(&mut self, buttons: &mut TouchButtonState) -> Vec<(u16, u16, i32)> {
+        let mut slots: Vec<usize> = self.mapping.drain().map(|(_, slot)| slot).collect();
+        slots.sort_unstable();
+        buttons.real_slots.clear();
+        for (_, tracking_id) in self.held_ids.drain() {
+            self.tracking_ids.release(tracking_id);
+        }
+
+        let mut out = Vec::new();
+        for slot in slots {
+            if buttons.slot != Some(slot as i32) {
+                out.push((EV_ABS, ABS_MT_SLOT, slot as i32));
+                buttons.slot = Some(slot as i32);
+            }
+            out.push((EV_ABS, ABS_MT_TRACKING_ID, -1));
+        }
+        out.extend(buttons.set_real(0));
+        out
+    }
+}
+
+// 独占真实触摸屏，把它的事件与注入触点合并后从虚拟设备发出
+#[derive(Debug)]
+pub struct Passthrough {
+    stop: Arc<AtomicBool>,
+    handle: Option<thread::JoinHandle<()>>,
+    real_device: Arc<Mutex<InputDevice>>,
+}
+
+impl Passthrough {
+    pub fn start(
+        real_device: Arc<Mutex<InputDevice>>,
+        uinput_device: Arc<Mutex<InputDevice>>,
+        buttons: Arc<Mutex<TouchButtonState>>,
+        tracking_ids: Arc<TrackingIdAllocator>,
+        filters: Option<&FilterConfig>,
+        validator: Option<SharedValidator>,
+        metrics: SharedMetrics,
+    ) -> std::io::Result<Self> {
+        let (file, current_slot, range, real_info) = {
+            let mut device = real_device.lock().unwrap();
+            if device.info.mock {
+                return Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "mock device has no event node to grab"));
+            }
+            device.grab()?;
+            let file = Arc::clone(&device.file);
+            let fd = file.lock().unwrap().as_raw_fd();
+            let current_slot = read_abs_infos(fd, &[ABS_MT_SLOT]).get(&ABS_MT_SLOT).map_or(0, |abs| abs.value);
+            (file, current_slot, AxisRange::of(&device), device.info.clone())
+        };
+
+        let (slots, scaling) = {
+            let device = uinput_device.lock().unwrap();
+            (device.slots as usize, AxisScaling::between(&real_info, &device.info))
+        };
+        if !scaling.is_identity() {
+            info!("Passthrough::start: rescaling forwarded axes to the virtual device: {:?}", scaling);
+        }
+        let remapper = SlotRemapper::new(slots, current_slot, tracking_ids, scaling);
+        let reserved_slots = buttons.lock().unwrap().reserved();
+        let filters = filters.map(|config| FilterChain::from_config(config, range, current_slot));
+        info!("Passthrough::start: forwarding real slot {} onwards, reserved slots {:?}, {} filters",
+                 current_slot, reserved_slots, filters.as_ref().map_or(0, FilterChain::len));
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_clone = Arc::clone(&stop);
+        let writer = FrameWriter {
+            uinput_device,
+            buttons,
+            validator,
+            metrics,
+        };
+        let handle = thread::spawn(move || {
+            forward_loop(file, writer, remapper, filters, stop_clone);
+        });
+
+        Ok(Self {
+            stop,
+            handle: Some(handle),
+            real_device,
+        })
+    }
+}
+
+impl Drop for Passthrough {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+        if let Err(e) = self.real_device.lock().unwrap().release() {
+            warn!("Passthrough::drop: failed to release device: {}", e);
+        }
+    }
+}
+
+// 转发线程写虚拟设备时用到的共享状态，帧数、错误和重试与注入线程计入同一份统计
+struct FrameWriter<S> {
+    uinput_device: Arc<Mutex<S>>,
+    buttons: Arc<Mutex<TouchButtonState>>,
+    validator: Option<SharedValidator>,
+    metrics: SharedMetrics,
+}
+
+impl<S: EventSink> FrameWriter<S> {
+    // 在设备锁内生成并写完整帧：避免与注入线程的帧交错，共享状态里的当前slot也与实际写入一致；
+    // read_at为读到真实事件的时刻，用来统计转发延迟
+    fn write(&self, read_at: Option<Instant>, build: impl FnOnce(&mut TouchButtonState) -> Vec<(u16, u16, i32)>) {
+        let mut guard = self.uinput_device.lock().unwrap();
+        let events = build(&mut self.buttons.lock().unwrap());
+        if events.is_empty() {
+            return;
+        }
+        // 与注入路径一样在EAGAIN时重试，丢掉TRACKING_ID -1会留下按住不放的手指
+        let mut retrying = RetryingSink::new(&mut *guard);
+        let mut uinput = ValidatingSink::shared(&mut retrying, self.validator.clone());
+        let mut failed = 0;
+        let mut last_error = None;
+        for &(event_type, code, value) in events.iter().chain([(EV_SYN, SYN_REPORT, 0)].iter()) {
+            if let Err(e) = uinput.write_event(event_type, code, value) {
+                failed += 1;
+                last_error = Some(e);
+            }
+        }
+        drop(uinput);
+        if let Some(e) = last_error {
+            error!("forward_loop: {} of {} events failed to write: {}", failed, events.len() + 1, e);
+        }
+
+        let mut metrics = self.metrics.lock().unwrap();
+        metrics.write_errors += failed;
+        metrics.eagain_retries += retrying.retries;
+        metrics.record_frame(events.len(), read_at.map(|at| at.elapsed()));
+    }
+}
+
+fn forward_loop(
+    file: Arc<Mutex<std::fs::File>>,
+    writer: FrameWriter<InputDevice>,
+    mut remapper: SlotRemapper,
+    mut filters: Option<FilterChain>,
+    stop: Arc<AtomicBool>,
+) {
+    debug!("forward_loop: started");
+    let record_size = std::mem::size_of::<InputEvent>();
+    let mut buffer = vec![0u8; record_size * 64];
+    let mut pending: Vec<u8> = Vec::new();
+    let mut frame: Vec<(u16, u16, i32)> = Vec::new();
+    let mut dropping = false;
+    // 最近一帧的事件时间戳和读到它的时刻，用来把过滤器的截止时间换算成poll超时
+    let mut clock: Option<(u64, Instant)> = None;
+    let fd = file.lock().unwrap().as_raw_fd();
+
+    while !stop.load(Ordering::SeqCst) {
+        let now_us = |(time_us, at): (u64, Instant)| time_us + at.elapsed().as_micros() as u64;
+        let deadline = filters.as_ref().and_then(FilterChain::deadline).zip(clock);
+        let timeout = deadline.map_or(POLL_TIMEOUT_MS, |(deadline, clock)| {
+            let remaining_us = deadline.saturating_sub(now_us(clock));
+            remaining_us.div_ceil(1000).min(POLL_TIMEOUT_MS as u64) as i32
+        });
+        let mut pfd = libc::pollfd {
+            fd,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let ready = unsafe { libc::poll(&mut pfd, 1, timeout) };
+
+        // 手指静止时真实设备不再产生事件，到期的缓存更新在这里输出
+        if let (Some(filters), Some((deadline, clock))) = (&mut filters, deadline) {
+            let now = now_us(clock);
+            if deadline <= now {
+                let flushed = filters.flush(now);
+                writer.write(None, |buttons| remapper.translate_frame(&flushed, buttons));
+            }
+        }
+        if ready <= 0 {
+            continue;
+        }
+        if pfd.revents & (libc::POLLERR | libc::POLLHUP | libc::POLLNVAL) != 0 {
+            warn!("forward_loop: device gone (revents=0x{:x})", pfd.revents);
+            break;
+        }
+
+        let n = match file.lock().unwrap().read(&mut buffer) {
+            Ok(n) if n > 0 => n,
+            Ok(_) => continue,
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => continue,
+            Err(e) => {
+                error!("forward_loop: read failed: {}", e);
+                break;
+            }
+        };
+        let read_at = Instant::now();
+        pending.extend_from_slice(&buffer[..n]);
+
+        let complete = pending.len() / record_size * record_size;
+        for record in pending[..complete].chunks_exact(record_size) {
+            let event = decode_record(record);
+            let (event_type, code, value) = (event.event_type, event.code, event.value);
+            match (event_type, code) {
+                (EV_SYN, SYN_DROPPED) => {
+                    // 内核缓冲区溢出，丢弃到下一个SYN_REPORT并抬起所有真实手指
+                    warn!("forward_loop: SYN_DROPPED, lifting real contacts");
+                    frame.clear();
+                    dropping = true;
+                    writer.write(None, |buttons| remapper.lift_all(buttons));
+                    if let Some(filters) = &mut filters {
+                        let slot = read_abs_infos(fd, &[ABS_MT_SLOT]).get(&ABS_MT_SLOT).map_or(0, |abs| abs.value);
+                        filters.reset(slot);
+                    }
+                }
+                (EV_SYN, SYN_REPORT) => {
+                    if dropping {
+                        dropping = false;
+                    } else {
+                        if let Some(filters) = &mut filters {
+                            frame = filters.apply(&frame, event.time_us());
+                        }
+                        clock = Some((event.time_us(), read_at));
+                        writer.write(Some(read_at), |buttons| remapper.translate_frame(&frame, buttons));
+                    }
+                    frame.clear();
+                }
+                _ if !dropping => frame.push((event_type, code, value)),
+                _ => {}
+            }
+        }
+        pending.drain(..complete);
+    }
+
+    // 停止时不留下按住的真实手指
+    writer.write(None, |buttons| remapper.lift_all(buttons));
+    debug!("forward_loop: stopped");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn real_contacts_skip_reserved_slots() {
+        let mut buttons = TouchButtonState::default();
+        assert!(buttons.reserve(0) && buttons.reserve(9));
+        // 注入触点先分配了一个id，真实手指接着往后分配
+        let tracking_ids = Arc::new(TrackingIdAllocator::default());
+        assert_eq!(tracking_ids.allocate(), 0);
+        let mut remapper = SlotRemapper::new(10, 0, Arc::clone(&tracking_ids), AxisScaling::default());
+
+        let down = remapper.translate_frame(
+            &[
+                (EV_ABS, ABS_MT_SLOT, 0),
+                (EV_ABS, ABS_MT_TRACKING_ID, 42),
+                (EV_ABS, ABS_MT_POSITION_X, 100),
+                (EV_ABS, ABS_MT_POSITION_Y, 200),
+                (EV_KEY, BTN_TOUCH, 1),
+                (EV_KEY, BTN_TOOL_FINGER, 1),
+            ],
+            &mut buttons,
+        );
+        assert_eq!(
+            down,
+            vec![
+                (EV_ABS, ABS_MT_SLOT, 1),
+                (EV_ABS, ABS_MT_TRACKING_ID, 1),
+                (EV_ABS, ABS_MT_POSITION_X, 100),
+                (EV_ABS, ABS_MT_POSITION_Y, 200),
+                (EV_KEY, BTN_TOUCH, 1),
+                (EV_KEY, BTN_TOOL_FINGER, 1),
+            ]
+        );
+
+        // slot没有变化时不重复选择
+        let moved = remapper.translate_frame(&[(EV_ABS, ABS_MT_POSITION_X, 110)], &mut buttons);
+        assert_eq!(moved, vec![(EV_ABS, ABS_MT_POSITION_X, 110)]);
+
+        // 注入线程切换了slot之后要重新选中映射后的slot
+        buttons.slot = Some(9);
+        let up = remapper.translate_frame(&[(EV_ABS, ABS_MT_TRACKING_ID, -1), (EV_KEY, BTN_TOUCH, 0)], &mut buttons);
+        assert_eq!(
+            up,
+            vec![
+                (EV_ABS, ABS_MT_SLOT, 1),
+                (EV_ABS, ABS_MT_TRACKING_ID, -1),
+                (EV_KEY, BTN_TOUCH, 0),
+                (EV_KEY, BTN_TOOL_FINGER, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn buttons_follow_combined_contact_count() {
+        let mut buttons = TouchButtonState::default();
+        assert_eq!(buttons.set_synthetic(1), vec![(EV_KEY, BTN_TOUCH, 1), (EV_KEY, BTN_TOOL_FINGER, 1)]);
+        assert_eq!(buttons.set_real(2), vec![(EV_KEY, BTN_TOOL_FINGER, 0), (EV_KEY, BTN_TOOL_TRIPLETAP, 1)]);
+        assert_eq!(buttons.set_synthetic(0), vec![(EV_KEY, BTN_TOOL_TRIPLETAP, 0), (EV_KEY, BTN_TOOL_DOUBLETAP, 1)]);
+        assert_eq!(buttons.set_real(0), vec![(EV_KEY, BTN_TOUCH, 0), (EV_KEY, BTN_TOOL_DOUBLETAP, 0)]);
+    }
+
+    #[test]
+    fn contacts_beyond_free_slots_are_dropped_and_lift_all_releases() {
+        let mut buttons = TouchButtonState::default();
+        buttons.reserve(1);
+        let mut remapper = SlotRemapper::new(2, 0, Arc::default(), AxisScaling::default());
+
+        let frame = [
+            (EV_ABS, ABS_MT_SLOT, 0),
+            (EV_ABS, ABS_MT_TRACKING_ID, 1),
+            (EV_ABS, ABS_MT_SLOT, 1),
+            (EV_ABS, ABS_MT_TRACKING_ID, 2),
+            (EV_ABS, ABS_MT_POSITION_X, 5),
+            (EV_KEY, BTN_TOUCH, 1),
+        ];
+        let out = remapper.translate_frame(&frame, &mut buttons);
+        assert_eq!(
+            out,
+            vec![
+                (EV_ABS, ABS_MT_SLOT, 0),
+                (EV_ABS, ABS_MT_TRACKING_ID, 0),
+                (EV_KEY, BTN_TOUCH, 1),
+                (EV_KEY, BTN_TOOL_FINGER, 1),
+            ]
+        );
+
+        buttons.slot = None;
+        let lifted = remapper.lift_all(&mut buttons);
+        assert_eq!(
+            lifted,
+            vec![
+                (EV_ABS, ABS_MT_SLOT, 0),
+                (EV_ABS, ABS_MT_TRACKING_ID, -1),
+                (EV_KEY, BTN_TOUCH, 0),
+                (EV_KEY, BTN_TOOL_FINGER, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn forwarded_axes_are_rescaled_to_the_virtual_device() {
+        let axes = |ranges: &[(u16, i32, i32)]| DeviceInfo {
+            abs_infos: ranges
+                .iter()
+                .map(|&(code, minimum, maximum)| (code, AbsInfo { minimum, maximum, ..AbsInfo::default() }))
+                .collect(),
+            ..DeviceInfo::default()
+        };
+        // 4096x4096的数字化仪，虚拟设备是1080x2340
+        let real = axes(&[(ABS_MT_POSITION_X, 0, 4095), (ABS_MT_POSITION_Y, 0, 4095), (ABS_MT_TOUCH_MAJOR, 0, 31)]);
+        let virtual_info = axes(&[
+            (ABS_MT_SLOT, 0, 9),
+            (ABS_MT_POSITION_X, 0, 1080),
+            (ABS_MT_POSITION_Y, 0, 2340),
+            (ABS_MT_TOUCH_MAJOR, 0, 100),
+            (ABS_MT_PRESSURE, 0, 255),
+        ]);
+        let scaling = AxisScaling::between(&real, &virtual_info);
+        let mut remapper = SlotRemapper::new(10, 0, Arc::default(), scaling);
+        let mut buttons = TouchButtonState::default();
+
+        let out = remapper.translate_frame(
+            &[
+                (EV_ABS, ABS_MT_TRACKING_ID, 3),
+                (EV_ABS, ABS_MT_POSITION_X, 4095),
+                (EV_ABS, ABS_MT_POSITION_Y, 2048),
+                (EV_ABS, ABS_MT_TOUCH_MAJOR, 31),
+                // 真实设备没有声明的轴原样转发
+                (EV_ABS, ABS_MT_PRESSURE, 40),
+            ],
+            &mut buttons,
+        );
+        assert_eq!(
+            out,
+            vec![
+                (EV_ABS, ABS_MT_SLOT, 0),
+                (EV_ABS, ABS_MT_TRACKING_ID, 0),
+                (EV_ABS, ABS_MT_POSITION_X, 1080),
+                (EV_ABS, ABS_MT_POSITION_Y, 1170),
+                (EV_ABS, ABS_MT_TOUCH_MAJOR, 100),
+                (EV_ABS, ABS_MT_PRESSURE, 40),
+                (EV_KEY, BTN_TOUCH, 1),
+                (EV_KEY, BTN_TOOL_FINGER, 1),
+            ]
+        );
+
+        // 超出真实设备范围的值截断到虚拟设备的范围内
+        let out = remapper.translate_frame(&[(EV_ABS, ABS_MT_POSITION_X, -20), (EV_ABS, ABS_MT_POSITION_Y, 5000)], &mut buttons);
+        assert_eq!(out, vec![(EV_ABS, ABS_MT_POSITION_X, 0), (EV_ABS, ABS_MT_POSITION_Y, 2340)]);
+    }
+
+    // 每个事件第一次写都返回EAGAIN，第二次成功；fail_code的事件总是失败
+    #[derive(Default)]
+    struct FlakySink {
+        busy: bool,
+        fail_code: Option<u16>,
+        written: usize,
+    }
+
+    impl EventSink for FlakySink {
+        fn write_event(&mut self, _event_type: u16, code: u16, _value: i32) -> std::io::Result<()> {
+            if Some(code) == self.fail_code {
+                return Err(std::io::ErrorKind::BrokenPipe.into());
+            }
+            self.busy = !self.busy;
+            if self.busy {
+                return Err(std::io::ErrorKind::WouldBlock.into());
+            }
+            self.written += 1;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn forwarded_frames_are_counted_in_metrics() {
+        let sink = Arc::new(Mutex::new(FlakySink { fail_code: Some(ABS_MT_POSITION_Y), ..FlakySink::default() }));
+        let metrics = SharedMetrics::default();
+        let writer = FrameWriter {
+            uinput_device: Arc::clone(&sink),
+            buttons: Arc::default(),
+            validator: None,
+            metrics: Arc::clone(&metrics),
+        };
+        let mut remapper = SlotRemapper::new(10, 0, Arc::default(), AxisScaling::default());
+
+        let down = [(EV_ABS, ABS_MT_TRACKING_ID, 5), (EV_ABS, ABS_MT_POSITION_X, 10), (EV_ABS, ABS_MT_POSITION_Y, 20)];
+        writer.write(Some(Instant::now()), |buttons| remapper.translate_frame(&down, buttons));
+        writer.write(None, |buttons| remapper.lift_all(buttons));
+        // 没有变化时不写空帧，也不计数
+        writer.write(None, |buttons| remapper.lift_all(buttons));
+
+        let snapshot = metrics.lock().unwrap().snapshot();
+        // SLOT、TRACKING_ID、X、Y、BTN_TOUCH、BTN_TOOL_FINGER，然后TRACKING_ID -1、BTN_TOUCH、BTN_TOOL_FINGER
+        assert_eq!((snapshot.frames, snapshot.events, snapshot.write_errors), (2, 9, 1));
+        // 写成功的事件（含两个SYN_REPORT）各重试了一次
+        assert_eq!(sink.lock().unwrap().written, 10);
+        assert_eq!(snapshot.eagain_retries, 10);
+        assert_eq!(snapshot.latency.count, 1);
+    }
+}
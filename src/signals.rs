@@ -0,0 +1,76 @@
+use crate::gesture::CancelToken;
+use log::{error, info};
+use std::{io, mem, sync::mpsc, thread};
+
+// 守护模式下处理的信号
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DaemonSignal {
+    // SIGUSR1：取消正在执行的手势并抬起所有触点
+    LiftAll,
+    // SIGUSR2：输出注入统计
+    Stats,
+    // SIGTERM/SIGINT：清理虚拟设备后退出
+    Terminate,
+}
+
+// 在专门的线程中用sigwait同步接收信号，不需要在异步信号处理函数里做任何事；
+// 必须在创建其他线程之前调用，新线程会继承屏蔽字，信号只会交给监听线程
+#[derive(Debug)]
+pub struct SignalListener {
+    receiver: mpsc::Receiver<DaemonSignal>,
+}
+
+fn daemon_sigset() -> io::Result<libc::sigset_t> {
+    unsafe {
+        let mut set: libc::sigset_t = mem::zeroed();
+        libc::sigemptyset(&mut set);
+        for signal in [libc::SIGUSR1, libc::SIGUSR2, libc::SIGTERM, libc::SIGINT] {
+            if libc::sigaddset(&mut set, signal) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        Ok(set)
+    }
+}
+
+impl SignalListener {
+    // cancel在监听线程里直接取消，即使主线程正阻塞在手势里也能立即生效
+    pub fn start(cancel: CancelToken) -> io::Result<Self> {
+        let set = daemon_sigset()?;
+        let ret = unsafe { libc::pthread_sigmask(libc::SIG_BLOCK, &set, std::ptr::null_mut()) };
+        if ret != 0 {
+            return Err(io::Error::from_raw_os_error(ret));
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || loop {
+            let mut signal = 0;
+            let ret = unsafe { libc::sigwait(&set, &mut signal) };
+            if ret != 0 {
+                error!("sigwait failed: {}", io::Error::from_raw_os_error(ret));
+                return;
+            }
+            let event = match signal {
+                libc::SIGUSR1 => {
+                    cancel.cancel();
+                    DaemonSignal::LiftAll
+                }
+                libc::SIGUSR2 => DaemonSignal::Stats,
+                _ => DaemonSignal::Terminate,
+            };
+            info!("received signal {} -> {:?}", signal, event);
+            if sender.send(event).is_err() {
+                return;
+            }
+        });
+        Ok(Self { receiver })
+    }
+
+    pub fn try_recv(&self) -> Option<DaemonSignal> {
+        self.receiver.try_recv().ok()
+    }
+
+    pub fn recv_timeout(&self, timeout: std::time::Duration) -> Option<DaemonSignal> {
+        self.receiver.recv_timeout(timeout).ok()
+    }
+}
@@ -0,0 +1,139 @@
+use crate::passthrough::TouchButtonState;
+use crate::touch_input::TouchContactB;
+use crate::uinput_defs::*;
+
+// 注入触点的MT轴，顺序即写入顺序；值小于0表示设备没有这个轴
+fn axes(contact: &TouchContactB) -> [(u16, i32); 8] {
+    [
+        (ABS_MT_POSITION_X, contact.position_x),
+        (ABS_MT_POSITION_Y, contact.position_y),
+        (ABS_MT_TOUCH_MAJOR, contact.touch_major),
+        (ABS_MT_TOUCH_MINOR, contact.touch_minor),
+        (ABS_MT_WIDTH_MAJOR, contact.width_major),
+        (ABS_MT_WIDTH_MINOR, contact.width_minor),
+        (ABS_MT_PRESSURE, contact.pressure),
+        (ABS_MT_ORIENTATION, contact.orientation),
+    ]
+}
+
+// 记录每个slot上次写出的状态，只输出变化的部分，和真实触摸屏驱动的输出一致
+#[derive(Debug)]
+pub struct SlotEmitter {
+    last: Vec<TouchContactB>,
+}
+
+impl SlotEmitter {
+    pub fn new(slots: usize) -> Self {
+        Self {
+            last: vec![TouchContactB::default(); slots],
+        }
+    }
+
+    // 生成一帧事件（不含SYN_REPORT），没有变化时返回空；
+    // 当前slot记录在与转发线程共享的状态里，调用方需要持有虚拟设备的锁直到写完
+    pub fn emit_frame(&mut self, contacts: &[TouchContactB], buttons: &mut TouchButtonState) -> Vec<(u16, u16, i32)> {
+        let mut out = Vec::new();
+        let mut select = |out: &mut Vec<(u16, u16, i32)>, slot: usize| {
+            if buttons.slot != Some(slot as i32) {
+                out.push((EV_ABS, ABS_MT_SLOT, slot as i32));
+                buttons.slot = Some(slot as i32);
+            }
+        };
+
+        let mut active = 0;
+        for (slot, (contact, last)) in contacts.iter().zip(self.last.iter_mut()).enumerate() {
+            if contact.active && contact.tracking_id >= 0 {
+                active += 1;
+                // 新触点的所有轴都要写出
+                if contact.tracking_id != last.tracking_id {
+                    select(&mut out, slot);
+                    out.push((EV_ABS, ABS_MT_TRACKING_ID, contact.tracking_id));
+                    *last = TouchContactB::default();
+                }
+                for ((code, value), (_, previous)) in axes(contact).into_iter().zip(axes(last)) {
+                    if value >= 0 && value != previous {
+                        select(&mut out, slot);
+                        out.push((EV_ABS, code, value));
+                    }
+                }
+                *last = contact.clone();
+            } else if last.tracking_id >= 0 {
+                select(&mut out, slot);
+                out.push((EV_ABS, ABS_MT_TRACKING_ID, -1));
+                *last = TouchContactB::default();
+            }
+        }
+
+        out.extend(buttons.set_synthetic(active));
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contact(tracking_id: i32, x: i32, y: i32) -> TouchContactB {
+        TouchContactB {
+            tracking_id,
+            position_x: x,
+            position_y: y,
+            pressure: 35,
+            active: true,
+            ..TouchContactB::default()
+        }
+    }
+
+    #[test]
+    fn emits_only_changes_and_switches_slot_when_needed() {
+        let mut buttons = TouchButtonState::default();
+        let mut emitter = SlotEmitter::new(3);
+        let mut contacts = vec![TouchContactB::default(); 3];
+
+        contacts[2] = contact(7, 100, 200);
+        assert_eq!(
+            emitter.emit_frame(&contacts, &mut buttons),
+            vec![
+                (EV_ABS, ABS_MT_SLOT, 2),
+                (EV_ABS, ABS_MT_TRACKING_ID, 7),
+                (EV_ABS, ABS_MT_POSITION_X, 100),
+                (EV_ABS, ABS_MT_POSITION_Y, 200),
+                (EV_ABS, ABS_MT_PRESSURE, 35),
+                (EV_KEY, BTN_TOUCH, 1),
+                (EV_KEY, BTN_TOOL_FINGER, 1),
+            ]
+        );
+
+        contacts[2].position_y = 210;
+        assert_eq!(emitter.emit_frame(&contacts, &mut buttons), vec![(EV_ABS, ABS_MT_POSITION_Y, 210)]);
+        assert!(emitter.emit_frame(&contacts, &mut buttons).is_empty());
+
+        contacts[0] = contact(8, 5, 6);
+        contacts[2].position_x = 110;
+        assert_eq!(
+            emitter.emit_frame(&contacts, &mut buttons),
+            vec![
+                (EV_ABS, ABS_MT_SLOT, 0),
+                (EV_ABS, ABS_MT_TRACKING_ID, 8),
+                (EV_ABS, ABS_MT_POSITION_X, 5),
+                (EV_ABS, ABS_MT_POSITION_Y, 6),
+                (EV_ABS, ABS_MT_PRESSURE, 35),
+                (EV_ABS, ABS_MT_SLOT, 2),
+                (EV_ABS, ABS_MT_POSITION_X, 110),
+                (EV_KEY, BTN_TOOL_FINGER, 0),
+                (EV_KEY, BTN_TOOL_DOUBLETAP, 1),
+            ]
+        );
+
+        // 抬起后触点重置为默认值，释放以上次写出的状态为准
+        contacts[2] = TouchContactB::default();
+        assert_eq!(
+            emitter.emit_frame(&contacts, &mut buttons),
+            vec![
+                (EV_ABS, ABS_MT_TRACKING_ID, -1),
+                (EV_KEY, BTN_TOOL_DOUBLETAP, 0),
+                (EV_KEY, BTN_TOOL_FINGER, 1),
+            ]
+        );
+    }
+}
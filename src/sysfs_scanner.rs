@@ -0,0 +1,449 @@
+use crate::device_scanner::{build_input_device, read_abs_infos};
+use crate::uinput::{DeviceInfo, InputDevice};
+use crate::uinput_defs::*;
+use log::{debug, info, warn};
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io,
+    os::unix::fs::OpenOptionsExt,
+    os::unix::io::AsRawFd,
+    path::Path,
+};
+
+const SYSFS_ROOT: &str = "/sys";
+const PROC_DEVICES: &str = "/proc/bus/input/devices";
+// sysfs和/proc中的位图按unsigned long分组、高位在前输出
+const LONG_BITS: usize = std::mem::size_of::<libc::c_ulong>() * 8;
+
+// 打开event节点读取AbsInfo，测试中替换为不访问真实设备的实现
+pub type NodeOpener = fn(&str) -> io::Result<File>;
+
+pub fn open_event_node(path: &str) -> io::Result<File> {
+    fs::OpenOptions::new().read(true).custom_flags(libc::O_NONBLOCK).open(path)
+}
+
+// 不需要打开每个event节点的设备发现：优先读sysfs，失败时读/proc/bus/input/devices
+// sysfs不提供ABS范围，只有event节点可读时才补充AbsInfo，否则标记为abs_unknown
+pub fn scan_sysfs_devices() -> Result<Vec<InputDevice>, Box<dyn std::error::Error>> {
+    match scan_sysfs(Path::new(SYSFS_ROOT), LONG_BITS, open_event_node) {
+        Ok(devices) if !devices.is_empty() => Ok(devices),
+        result => {
+            if let Err(e) = result {
+                warn!("scan_sysfs_devices: failed to read sysfs: {}", e);
+            }
+            info!("scan_sysfs_devices: falling back to {}", PROC_DEVICES);
+            let text = fs::read_to_string(PROC_DEVICES)?;
+            Ok(parse_proc_devices(&text, LONG_BITS, open_event_node))
+        }
+    }
+}
+
+// 解析<root>/class/input/input*目录，word_bits是内核unsigned long的位数
+pub fn scan_sysfs(root: &Path, word_bits: usize, open: NodeOpener) -> io::Result<Vec<InputDevice>> {
+    debug!("scan_sysfs: scanning {}", root.display());
+    let mut dirs: Vec<_> = fs::read_dir(root.join("class/input"))?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_prefix("input"))
+                .is_some_and(|num| num.parse::<u32>().is_ok())
+        })
+        .collect();
+    dirs.sort_by_key(|path| input_number(path));
+
+    Ok(dirs.iter().filter_map(|dir| read_sysfs_input(dir, word_bits, open)).collect())
+}
+
+fn input_number(path: &Path) -> u32 {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|name| name.trim_start_matches(|c: char| !c.is_ascii_digit()).parse().ok())
+        .unwrap_or(u32::MAX)
+}
+
+fn read_sysfs_input(dir: &Path, word_bits: usize, open: NodeOpener) -> Option<InputDevice> {
+    // 没有evdev handler的设备无法注入，跳过
+    let event_node = fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .find(|name| name.starts_with("event"))?;
+
+    let name = read_attr(dir, "name").unwrap_or_else(|| "Unknown".to_string());
+    let input_id = InputId {
+        bus_type: read_hex_attr(dir, "id/bustype"),
+        vendor: read_hex_attr(dir, "id/vendor"),
+        product: read_hex_attr(dir, "id/product"),
+        version: read_hex_attr(dir, "id/version"),
+    };
+    let bitmap = |attr: &str| read_attr(dir, attr).map(|text| parse_bitmap(&text, word_bits)).unwrap_or_default();
+
+    let info = DeviceInfo {
+        driver_version: 0,
+        phys: read_attr(dir, "phys").unwrap_or_default(),
+        uniq: read_attr(dir, "uniq").unwrap_or_default(),
+        sysfs_path: fs::canonicalize(dir)
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_else(|_| dir.to_string_lossy().to_string()),
+        abs_infos: BTreeMap::new(),
+        events: bitmap("capabilities/ev"),
+        properties: bitmap("properties"),
+        keys: bitmap("capabilities/key"),
+        rels: bitmap("capabilities/rel"),
+        switches: bitmap("capabilities/sw"),
+        mock: false,
+        profile: None,
+        event_node: None,
+        abs_unknown: false,
+    };
+    let abs_codes = bitmap("capabilities/abs");
+
+    debug!("scan_sysfs: {} -> {} ({})", dir.display(), event_node, name);
+    Some(finish_device(name, format!("/dev/input/{}", event_node), input_id, info, &abs_codes, open))
+}
+
+// 尝试打开event节点补充AbsInfo，没有权限时保留全0的占位值并标记范围未知
+fn finish_device(
+    name: String,
+    path: String,
+    input_id: InputId,
+    mut info: DeviceInfo,
+    abs_codes: &[u16],
+    open: NodeOpener,
+) -> InputDevice {
+    let file = match open(&path) {
+        Ok(file) => {
+            info.abs_infos = read_abs_infos(file.as_raw_fd(), abs_codes);
+            Some(file)
+        }
+        Err(e) => {
+            if !abs_codes.is_empty() {
+                debug!("scan_sysfs: cannot open {} ({}), ABS ranges unknown", path, e);
+                info.abs_unknown = true;
+            }
+            None
+        }
+    };
+    for &code in abs_codes {
+        info.abs_infos.entry(code).or_default();
+    }
+
+    // 没有打开设备节点时使用/dev/null占位，与mock设备一致
+    let file = file.unwrap_or_else(|| File::open("/dev/null").expect("/dev/null"));
+    build_input_device(name, path, input_id, info, file)
+}
+
+fn read_attr(dir: &Path, attr: &str) -> Option<String> {
+    fs::read_to_string(dir.join(attr)).ok().map(|text| text.trim_end_matches('\n').to_string())
+}
+
+fn read_hex_attr(dir: &Path, attr: &str) -> u16 {
+    read_attr(dir, attr)
+        .and_then(|text| u16::from_str_radix(text.trim(), 16).ok())
+        .unwrap_or(0)
+}
+
+// 解析"1 0 3"这类位图，最后一个字对应最低位
+pub fn parse_bitmap(text: &str, word_bits: usize) -> Vec<u16> {
+    let mut codes = Vec::new();
+    for (index, word) in text.split_whitespace().rev().enumerate() {
+        let Ok(value) = u64::from_str_radix(word, 16) else {
+            continue;
+        };
+        for bit in 0..word_bits.min(64) {
+            if value & (1u64 << bit) != 0 {
+                codes.push((index * word_bits + bit) as u16);
+            }
+        }
+    }
+    codes.sort_unstable();
+    codes
+}
+
+// 解析/proc/bus/input/devices，每个设备是一段以空行分隔的记录
+pub fn parse_proc_devices(text: &str, word_bits: usize, open: NodeOpener) -> Vec<InputDevice> {
+    let mut devices = Vec::new();
+
+    for block in text.split("\n\n") {
+        let mut name = "Unknown".to_string();
+        let mut input_id = InputId::default();
+        let mut event_node = None;
+        let mut info = DeviceInfo::default();
+        let mut abs_codes = Vec::new();
+
+        for line in block.lines() {
+            let Some((kind, rest)) = line.split_once(": ") else {
+                continue;
+            };
+            match kind {
+                "I" => {
+                    for field in rest.split_whitespace() {
+                        let Some((key, value)) = field.split_once('=') else {
+                            continue;
+                        };
+                        let value = u16::from_str_radix(value, 16).unwrap_or(0);
+                        match key {
+                            "Bus" => input_id.bus_type = value,
+                            "Vendor" => input_id.vendor = value,
+                            "Product" => input_id.product = value,
+                            "Version" => input_id.version = value,
+                            _ => {}
+                        }
+                    }
+                }
+                "N" => name = rest.trim_start_matches("Name=").trim_matches('"').to_string(),
+                "P" => info.phys = rest.trim_start_matches("Phys=").to_string(),
+                "U" => info.uniq = rest.trim_start_matches("Uniq=").to_string(),
+                "S" => info.sysfs_path = format!("{}{}", SYSFS_ROOT, rest.trim_start_matches("Sysfs=")),
+                "H" => {
+                    event_node = rest
+                        .trim_start_matches("Handlers=")
+                        .split_whitespace()
+                        .find(|handler| handler.starts_with("event"))
+                        .map(str::to_string);
+                }
+                "B" => {
+                    let Some((key, bits)) = rest.split_once('=') else {
+                        continue;
+                    };
+                    let codes = parse_bitmap(bits, word_bits);
+                    match key {
+                        "PROP" => info.properties = codes,
+                        "EV" => info.events = codes,
+                        "KEY" => info.keys = codes,
+                        "REL" => info.rels = codes,
+                        "ABS" => abs_codes = codes,
+                        "SW" => info.switches = codes,
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(event_node) = event_node {
+            devices.push(finish_device(name, format!("/dev/input/{}", event_node), input_id, info, &abs_codes, open));
+        }
+    }
+    devices
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device_classifier::{classify, DeviceClass};
+    use std::path::PathBuf;
+
+    // 在临时目录中构造假的sysfs树，测试结束时删除
+    struct FakeSysfs {
+        root: PathBuf,
+    }
+
+    impl FakeSysfs {
+        fn new(test_name: &str) -> Self {
+            let root = std::env::temp_dir().join(format!("touchsim-sysfs-{}-{}", std::process::id(), test_name));
+            let _ = fs::remove_dir_all(&root);
+            fs::create_dir_all(root.join("class/input")).unwrap();
+            Self { root }
+        }
+
+        fn add_input(&self, input: &str, event: Option<&str>, attrs: &[(&str, &str)]) {
+            let dir = self.root.join("class/input").join(input);
+            fs::create_dir_all(&dir).unwrap();
+            if let Some(event) = event {
+                fs::create_dir_all(dir.join(event)).unwrap();
+            }
+            for (attr, value) in attrs {
+                let path = dir.join(attr);
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(path, format!("{}\n", value)).unwrap();
+            }
+        }
+    }
+
+    impl Drop for FakeSysfs {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.root);
+        }
+    }
+
+    // 测试不访问主机上的/dev/input，所有节点都打不开
+    fn no_node(_: &str) -> io::Result<File> {
+        Err(io::ErrorKind::PermissionDenied.into())
+    }
+
+    // 内核按unsigned long分组输出位图，64位和32位主机上同一设备的文本不同
+    struct Bitmaps {
+        word_bits: usize,
+        // BTN_TOUCH
+        touch_keys: &'static str,
+        // ABS_MT_SLOT, TOUCH_MAJOR/MINOR, POSITION_X/Y, TRACKING_ID, PRESSURE
+        touch_abs: &'static str,
+        // KEY_VOLUMEDOWN, KEY_VOLUMEUP, KEY_POWER
+        volume_keys: &'static str,
+    }
+
+    const BITMAPS: [Bitmaps; 2] = [
+        Bitmaps {
+            word_bits: 64,
+            touch_keys: "400 0 0 0 0 0",
+            touch_abs: "663800000000000",
+            volume_keys: "1c000000000000 0",
+        },
+        Bitmaps {
+            word_bits: 32,
+            touch_keys: "400 0 0 0 0 0 0 0 0 0 0",
+            touch_abs: "6638000 0",
+            volume_keys: "1c0000 0 0 0",
+        },
+    ];
+
+    fn touchscreen_attrs(bitmaps: &Bitmaps) -> Vec<(&'static str, &'static str)> {
+        vec![
+            ("name", "fts_ts"),
+            ("phys", "fts_ts/input0"),
+            ("uniq", ""),
+            ("id/bustype", "0018"),
+            ("id/vendor", "2808"),
+            ("id/product", "1015"),
+            ("id/version", "0001"),
+            ("capabilities/ev", "b"),
+            ("capabilities/key", bitmaps.touch_keys),
+            ("capabilities/abs", bitmaps.touch_abs),
+            ("properties", "2"),
+        ]
+    }
+
+    #[test]
+    fn parse_bitmap_orders_words_from_least_significant() {
+        assert_eq!(parse_bitmap("b", 64), vec![0, 1, 3]);
+        assert_eq!(parse_bitmap("1 0", 64), vec![64]);
+        assert_eq!(parse_bitmap("1 2", 32), vec![1, 32]);
+        assert!(parse_bitmap("", 64).is_empty());
+        for bitmaps in &BITMAPS {
+            assert_eq!(parse_bitmap(bitmaps.touch_keys, bitmaps.word_bits), vec![BTN_TOUCH]);
+            assert_eq!(
+                parse_bitmap(bitmaps.touch_abs, bitmaps.word_bits),
+                vec![
+                    ABS_MT_SLOT,
+                    ABS_MT_TOUCH_MAJOR,
+                    ABS_MT_TOUCH_MINOR,
+                    ABS_MT_POSITION_X,
+                    ABS_MT_POSITION_Y,
+                    ABS_MT_TRACKING_ID,
+                    ABS_MT_PRESSURE
+                ]
+            );
+            assert_eq!(
+                parse_bitmap(bitmaps.volume_keys, bitmaps.word_bits),
+                vec![KEY_VOLUMEDOWN, KEY_VOLUMEUP, KEY_POWER]
+            );
+        }
+    }
+
+    #[test]
+    fn scan_sysfs_builds_touchscreen_record() {
+        for bitmaps in &BITMAPS {
+            let sysfs = FakeSysfs::new(&format!("touchscreen-{}", bitmaps.word_bits));
+            sysfs.add_input("input3", Some("event3"), &touchscreen_attrs(bitmaps));
+
+            let devices = scan_sysfs(&sysfs.root, bitmaps.word_bits, no_node).unwrap();
+            assert_eq!(devices.len(), 1);
+            let device = &devices[0];
+            assert_eq!(device.name, "fts_ts");
+            assert_eq!(device.path, "/dev/input/event3");
+            assert_eq!(device.input_id.bus_type, 0x18);
+            assert_eq!(device.input_id.vendor, 0x2808);
+            assert_eq!(device.input_id.product, 0x1015);
+            assert_eq!(device.info.phys, "fts_ts/input0");
+            assert_eq!(device.info.events, vec![EV_SYN, EV_KEY, EV_ABS]);
+            assert_eq!(device.info.keys, vec![BTN_TOUCH]);
+            assert_eq!(device.info.properties, vec![INPUT_PROP_DIRECT]);
+            assert!(device.info.abs_infos.contains_key(&ABS_MT_SLOT));
+            assert!(device.info.abs_infos.contains_key(&ABS_MT_TRACKING_ID));
+            assert!(device.has_touch_major && device.has_touch_minor && device.has_pressure);
+            assert!(!device.has_orientation);
+            assert!(device.info.abs_unknown);
+            assert_eq!(classify(&device.info).touchscreen_type_b(), Some(true));
+        }
+    }
+
+    #[test]
+    fn scan_sysfs_skips_inputs_without_event_handler_and_sorts() {
+        for bitmaps in &BITMAPS {
+            let sysfs = FakeSysfs::new(&format!("sorting-{}", bitmaps.word_bits));
+            let keys = [("name", "gpio-keys"), ("capabilities/ev", "3"), ("capabilities/key", bitmaps.volume_keys)];
+            sysfs.add_input("input10", Some("event10"), &keys);
+            sysfs.add_input("input2", Some("event2"), &touchscreen_attrs(bitmaps));
+            sysfs.add_input("input5", None, &[("name", "no-handler")]);
+            sysfs.add_input("mice", None, &[]);
+
+            let devices = scan_sysfs(&sysfs.root, bitmaps.word_bits, no_node).unwrap();
+            let names: Vec<&str> = devices.iter().map(|d| d.name.as_str()).collect();
+            assert_eq!(names, vec!["fts_ts", "gpio-keys"]);
+            assert_eq!(devices[1].info.keys, vec![KEY_VOLUMEDOWN, KEY_VOLUMEUP, KEY_POWER]);
+            // 没有ABS轴的设备不需要范围
+            assert!(!devices[1].info.abs_unknown);
+            assert!(classify(&devices[1].info).classes.contains(&DeviceClass::Keyboard));
+        }
+    }
+
+    #[test]
+    fn scan_sysfs_reports_missing_class_dir() {
+        let root = std::env::temp_dir().join(format!("touchsim-sysfs-{}-missing", std::process::id()));
+        assert!(scan_sysfs(&root, 64, no_node).is_err());
+    }
+
+    #[test]
+    fn parse_proc_devices_matches_sysfs_record() {
+        for bitmaps in &BITMAPS {
+            let text = format!(
+                "\
+I: Bus=0018 Vendor=2808 Product=1015 Version=0001
+N: Name=\"fts_ts\"
+P: Phys=fts_ts/input0
+S: Sysfs=/devices/platform/soc/fts/input/input3
+U: Uniq=
+H: Handlers=event3
+B: PROP=2
+B: EV=b
+B: KEY={}
+B: ABS={}
+
+I: Bus=0019 Vendor=0001 Product=0001 Version=0100
+N: Name=\"gpio-keys\"
+P: Phys=gpio-keys/input0
+S: Sysfs=/devices/platform/gpio-keys/input/input0
+U: Uniq=
+H: Handlers=kbd event0
+B: PROP=0
+B: EV=3
+B: KEY={}
+
+I: Bus=0000 Vendor=0000 Product=0000 Version=0000
+N: Name=\"no-handler\"
+H: Handlers=
+",
+                bitmaps.touch_keys, bitmaps.touch_abs, bitmaps.volume_keys
+            );
+            let devices = parse_proc_devices(&text, bitmaps.word_bits, no_node);
+            assert_eq!(devices.len(), 2);
+
+            let touch = &devices[0];
+            assert_eq!(touch.name, "fts_ts");
+            assert_eq!(touch.path, "/dev/input/event3");
+            assert_eq!(touch.input_id.vendor, 0x2808);
+            assert_eq!(touch.info.sysfs_path, "/sys/devices/platform/soc/fts/input/input3");
+            assert_eq!(touch.info.keys, vec![BTN_TOUCH]);
+            assert!(touch.info.abs_unknown);
+            assert_eq!(classify(&touch.info).touchscreen_type_b(), Some(true));
+
+            let keys = &devices[1];
+            assert_eq!(keys.path, "/dev/input/event0");
+            assert_eq!(keys.info.keys, vec![KEY_VOLUMEDOWN, KEY_VOLUMEUP, KEY_POWER]);
+        }
+    }
+}
@@ -0,0 +1,615 @@
+use crate::event_sink::{EventSink, RetryingSink};
+use crate::input_filter::AxisRange;
+use crate::metrics::SharedMetrics;
+use crate::mt_validator::{SharedValidator, ValidatingSink};
+use crate::passthrough::TouchButtonState;
+use crate::slot_emitter::SlotEmitter;
+use crate::touch_input::{TouchContactA, TouchContactB};
+use crate::tracking_id::TrackingIdAllocator;
+use crate::uinput_defs::*;
+use log::{debug, error, warn};
+use std::{
+    sync::{mpsc, Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+// 发给分发线程的命令，按发送顺序执行；两次Frame之间的触点变化作为一帧原子写出
+#[derive(Debug)]
+pub enum TouchCommand {
+    // 坐标已换算为虚拟设备坐标
+    ContactDown { slot: usize, x: i32, y: i32 },
+    ContactMove { slot: usize, x: i32, y: i32 },
+    ContactUp { slot: usize },
+    // 在同一帧里抬起所有注入的触点，用于紧急中止
+    LiftAll,
+    Frame,
+    // 写出还没提交的变化后回复当前统计
+    Flush(mpsc::Sender<DispatcherStats>),
+    // 抬起所有触点后退出
+    Stop,
+}
+
+// 命令的发送端，发送时记下时间，分发线程据此统计从发出命令到写完一帧的延迟；
+// 手势播放也通过它记录调度延迟
+#[derive(Debug, Clone)]
+pub struct CommandSender {
+    sender: mpsc::Sender<(Instant, TouchCommand)>,
+    metrics: SharedMetrics,
+}
+
+impl CommandSender {
+    pub fn channel(metrics: SharedMetrics) -> (Self, mpsc::Receiver<(Instant, TouchCommand)>) {
+        let (sender, receiver) = mpsc::channel();
+        (Self { sender, metrics }, receiver)
+    }
+
+    pub fn send(&self, command: TouchCommand) -> Result<(), mpsc::SendError<TouchCommand>> {
+        self.sender
+            .send((Instant::now(), command))
+            .map_err(|mpsc::SendError((_, command))| mpsc::SendError(command))
+    }
+
+    pub fn record_lateness(&self, lateness: Duration) {
+        self.metrics.lock().unwrap().lateness.record(lateness);
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DispatcherStats {
+    pub frames: u64,
+    pub events: u64,
+    pub write_errors: u64,
+    pub active_contacts: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Protocol {
+    TypeA,
+    TypeB,
+}
+
+// 注入触点的附加轴，小于0表示设备没有这个轴
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ContactAxes {
+    pub touch_major: i32,
+    pub touch_minor: i32,
+    pub width_major: i32,
+    pub width_minor: i32,
+    pub orientation: i32,
+    pub pressure: i32,
+}
+
+impl Default for ContactAxes {
+    fn default() -> Self {
+        Self {
+            touch_major: -1,
+            touch_minor: -1,
+            width_major: -1,
+            width_minor: -1,
+            orientation: -1,
+            pressure: -1,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct EngineConfig {
+    pub protocol: Protocol,
+    pub slots: usize,
+    pub axes: ContactAxes,
+    // 虚拟设备的坐标范围，超出范围的坐标截断到边缘
+    pub range: AxisRange,
+    pub tracking_ids: Arc<TrackingIdAllocator>,
+    // 与转发线程共享的BTN_TOUCH/BTN_TOOL_*和当前slot，只用于Type B
+    pub buttons: Arc<Mutex<TouchButtonState>>,
+    pub validator: Option<SharedValidator>,
+    // 由TouchSimulation持有，虚拟设备重建后继续累计
+    pub metrics: SharedMetrics,
+}
+
+// 分发线程的句柄，drop时停止线程并等待退出
+#[derive(Debug)]
+pub struct TouchEngine {
+    sender: CommandSender,
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+impl TouchEngine {
+    pub fn spawn<S: EventSink + 'static>(sink: Arc<Mutex<S>>, config: EngineConfig) -> Self {
+        let (sender, receiver) = CommandSender::channel(Arc::clone(&config.metrics));
+        let handle = thread::spawn(move || Dispatcher::new(sink, config).run(receiver));
+        Self {
+            sender,
+            handle: Some(handle),
+        }
+    }
+
+    // 手势播放和异步接口通过发送端的副本提交命令
+    pub fn sender(&self) -> CommandSender {
+        self.sender.clone()
+    }
+
+    // 分发线程已经退出时返回false
+    pub fn send(&self, command: TouchCommand) -> bool {
+        self.sender.send(command).is_ok()
+    }
+
+    pub fn flush(&self) -> Option<DispatcherStats> {
+        let (reply, receiver) = mpsc::channel();
+        if !self.send(TouchCommand::Flush(reply)) {
+            return None;
+        }
+        receiver.recv().ok()
+    }
+}
+
+impl Drop for TouchEngine {
+    fn drop(&mut self) {
+        let _ = self.sender.send(TouchCommand::Stop);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Contacts {
+    TypeA { contacts: Vec<TouchContactA>, btn_down: bool },
+    TypeB { contacts: Vec<TouchContactB>, emitter: SlotEmitter },
+}
+
+struct Dispatcher<S> {
+    sink: Arc<Mutex<S>>,
+    contacts: Contacts,
+    axes: ContactAxes,
+    range: AxisRange,
+    tracking_ids: Arc<TrackingIdAllocator>,
+    buttons: Arc<Mutex<TouchButtonState>>,
+    validator: Option<SharedValidator>,
+    // 上一帧之后是否有触点变化
+    dirty: bool,
+    // 还没写出的变化中最早的命令的发送时间
+    pending_since: Option<Instant>,
+    stats: DispatcherStats,
+    metrics: SharedMetrics,
+}
+
+impl<S: EventSink> Dispatcher<S> {
+    fn new(sink: Arc<Mutex<S>>, config: EngineConfig) -> Self {
+        let contacts = match config.protocol {
+            Protocol::TypeA => Contacts::TypeA {
+                contacts: vec![TouchContactA::default(); config.slots],
+                btn_down: false,
+            },
+            Protocol::TypeB => Contacts::TypeB {
+                contacts: vec![TouchContactB::default(); config.slots],
+                emitter: SlotEmitter::new(config.slots),
+            },
+        };
+        Self {
+            sink,
+            contacts,
+            axes: config.axes,
+            range: config.range,
+            tracking_ids: config.tracking_ids,
+            buttons: config.buttons,
+            validator: config.validator,
+            dirty: false,
+            pending_since: None,
+            stats: DispatcherStats::default(),
+            metrics: config.metrics,
+        }
+    }
+
+    fn run(mut self, receiver: mpsc::Receiver<(Instant, TouchCommand)>) {
+        debug!("dispatcher started");
+        // 所有发送端都drop后也会退出
+        for (sent, command) in receiver {
+            if matches!(
+                command,
+                TouchCommand::ContactDown { .. }
+                    | TouchCommand::ContactMove { .. }
+                    | TouchCommand::ContactUp { .. }
+                    | TouchCommand::LiftAll
+            ) {
+                self.pending_since.get_or_insert(sent);
+            }
+            match command {
+                TouchCommand::ContactDown { slot, x, y } => self.contact_down(slot, x, y),
+                TouchCommand::ContactMove { slot, x, y } => self.contact_move(slot, x, y),
+                TouchCommand::ContactUp { slot } => self.contact_up(slot),
+                TouchCommand::LiftAll => self.lift_all(),
+                TouchCommand::Frame => self.frame(),
+                TouchCommand::Flush(reply) => {
+                    if self.dirty {
+                        self.frame();
+                    }
+                    let _ = reply.send(self.stats);
+                }
+                TouchCommand::Stop => break,
+            }
+        }
+        // 不留下按住的手指，虚拟设备停止前对应的slot都会收到tracking id -1
+        self.lift_all();
+        debug!("dispatcher stopped ({} frames, {} events)", self.stats.frames, self.stats.events);
+    }
+
+    fn contact_down(&mut self, slot: usize, x: i32, y: i32) {
+        // 先检查slot，被拒绝的命令不占用共享分配器的tracking id
+        if slot >= self.slots() {
+            warn!("ContactDown on slot {} out of range", slot);
+            return;
+        }
+        // 没有抬起就再次按下时换一个新的id，旧的id交还分配器
+        if let Some(old) = self.tracking_id(slot) {
+            self.tracking_ids.release(old);
+        }
+        let tracking_id = self.tracking_ids.allocate();
+        let axes = self.axes;
+        let (x, y) = self.range.clamp(x, y);
+        match &mut self.contacts {
+            Contacts::TypeA { contacts, .. } => {
+                contacts[slot] = TouchContactA {
+                    pos_x: x,
+                    pos_y: y,
+                    tracking_id,
+                    active: true,
+                };
+            }
+            Contacts::TypeB { contacts, .. } => {
+                contacts[slot] = TouchContactB {
+                    touch_major: axes.touch_major,
+                    touch_minor: axes.touch_minor,
+                    width_major: axes.width_major,
+                    width_minor: axes.width_minor,
+                    orientation: axes.orientation,
+                    position_x: x,
+                    position_y: y,
+                    tracking_id,
+                    pressure: axes.pressure,
+                    active: true,
+                };
+            }
+        }
+        self.dirty = true;
+    }
+
+    fn contact_move(&mut self, slot: usize, x: i32, y: i32) {
+        let (x, y) = self.range.clamp(x, y);
+        let moved = match &mut self.contacts {
+            Contacts::TypeA { contacts, .. } => contacts.get_mut(slot).filter(|c| c.active).map(|contact| {
+                contact.pos_x = x;
+                contact.pos_y = y;
+            }),
+            Contacts::TypeB { contacts, .. } => contacts.get_mut(slot).filter(|c| c.active).map(|contact| {
+                contact.position_x = x;
+                contact.position_y = y;
+            }),
+        };
+        if moved.is_none() {
+            warn!("ContactMove on inactive slot {}", slot);
+            return;
+        }
+        self.dirty = true;
+    }
+
+    fn slots(&self) -> usize {
+        match &self.contacts {
+            Contacts::TypeA { contacts, .. } => contacts.len(),
+            Contacts::TypeB { contacts, .. } => contacts.len(),
+        }
+    }
+
+    // slot上按下的触点持有的tracking id
+    fn tracking_id(&self, slot: usize) -> Option<i32> {
+        match &self.contacts {
+            Contacts::TypeA { contacts, .. } => contacts.get(slot).filter(|c| c.active).map(|c| c.tracking_id),
+            Contacts::TypeB { contacts, .. } => contacts.get(slot).filter(|c| c.active).map(|c| c.tracking_id),
+        }
+    }
+
+    fn contact_up(&mut self, slot: usize) {
+        let Some(tracking_id) = self.tracking_id(slot) else {
+            warn!("ContactUp on inactive slot {}", slot);
+            return;
+        };
+        self.tracking_ids.release(tracking_id);
+        match &mut self.contacts {
+            Contacts::TypeA { contacts, .. } => contacts[slot] = TouchContactA::default(),
+            Contacts::TypeB { contacts, .. } => contacts[slot] = TouchContactB::default(),
+        }
+        self.dirty = true;
+    }
+
+    // 丢弃还没提交的变化，直接写出全部抬起的一帧
+    fn lift_all(&mut self) {
+        for slot in 0..self.slots() {
+            if let Some(tracking_id) = self.tracking_id(slot) {
+                self.tracking_ids.release(tracking_id);
+            }
+        }
+        match &mut self.contacts {
+            Contacts::TypeA { contacts, .. } => contacts.fill(TouchContactA::default()),
+            Contacts::TypeB { contacts, .. } => contacts.fill(TouchContactB::default()),
+        }
+        self.frame();
+    }
+
+    // 在设备锁内生成并写完整帧，转发线程的帧不会插进来
+    fn frame(&mut self) {
+        self.dirty = false;
+        let pending_since = self.pending_since.take();
+        let mut guard = self.sink.lock().unwrap();
+        let events = match &mut self.contacts {
+            Contacts::TypeA { contacts, btn_down } => type_a_frame(contacts, btn_down),
+            Contacts::TypeB { contacts, emitter } => emitter.emit_frame(contacts, &mut self.buttons.lock().unwrap()),
+        };
+        self.stats.active_contacts = match &self.contacts {
+            Contacts::TypeA { contacts, .. } => contacts.iter().filter(|c| c.active).count(),
+            Contacts::TypeB { contacts, .. } => contacts.iter().filter(|c| c.active).count(),
+        };
+        // 没有变化时不发空帧
+        if events.is_empty() {
+            return;
+        }
+
+        let mut retrying = RetryingSink::new(&mut *guard);
+        let mut sink = ValidatingSink::shared(&mut retrying, self.validator.clone());
+        let mut write_errors = 0;
+        for &(event_type, code, value) in events.iter().chain(&[(EV_SYN, SYN_REPORT, 0)]) {
+            if let Err(e) = sink.write_event(event_type, code, value) {
+                error!("failed to write {}: {}", event_code_name(event_type, code).unwrap_or("?"), e);
+                write_errors += 1;
+            }
+        }
+        drop(sink);
+        self.stats.write_errors += write_errors;
+        self.stats.events += events.len() as u64;
+        self.stats.frames += 1;
+
+        let mut metrics = self.metrics.lock().unwrap();
+        metrics.write_errors += write_errors;
+        metrics.eagain_retries += retrying.retries;
+        metrics.record_frame(events.len(), pending_since.map(|sent| sent.elapsed()));
+    }
+}
+
+// Type A每帧重新上报所有触点，以SYN_MT_REPORT分隔；坐标在按下和移动时已经截断到设备范围内
+fn type_a_frame(contacts: &[TouchContactA], btn_down: &mut bool) -> Vec<(u16, u16, i32)> {
+    let mut out = Vec::new();
+    let mut active = 0;
+    for contact in contacts {
+        if contact.active {
+            out.push((EV_ABS, ABS_MT_POSITION_X, contact.pos_x));
+            out.push((EV_ABS, ABS_MT_POSITION_Y, contact.pos_y));
+            out.push((EV_ABS, ABS_MT_TRACKING_ID, contact.tracking_id));
+            out.push((EV_SYN, SYN_MT_REPORT, 0));
+            active += 1;
+        }
+    }
+
+    if active == 0 && *btn_down {
+        *btn_down = false;
+        out.push((EV_SYN, SYN_MT_REPORT, 0));
+        out.push((EV_KEY, BTN_TOUCH, 0));
+    } else if active > 0 && !*btn_down {
+        *btn_down = true;
+        out.push((EV_KEY, BTN_TOUCH, 1));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event_sink::MemorySink;
+
+    const RANGE: AxisRange = AxisRange { x_min: 0, x_max: 1080, y_min: 0, y_max: 2340 };
+
+    fn spawn(protocol: Protocol) -> (TouchEngine, Arc<Mutex<MemorySink>>) {
+        let sink = Arc::new(Mutex::new(MemorySink::new()));
+        let config = EngineConfig {
+            protocol,
+            slots: 10,
+            axes: ContactAxes::default(),
+            range: RANGE,
+            tracking_ids: Arc::default(),
+            buttons: Arc::default(),
+            validator: None,
+            metrics: Arc::default(),
+        };
+        (TouchEngine::spawn(Arc::clone(&sink), config), sink)
+    }
+
+    fn take_events(sink: &Arc<Mutex<MemorySink>>) -> Vec<(u16, u16, i32)> {
+        std::mem::take(&mut sink.lock().unwrap().events)
+    }
+
+    #[test]
+    fn commands_between_frames_are_written_as_one_frame() {
+        let (engine, sink) = spawn(Protocol::TypeB);
+        engine.send(TouchCommand::ContactDown { slot: 9, x: 10, y: 20 });
+        engine.send(TouchCommand::ContactDown { slot: 3, x: 30, y: 40 });
+        engine.send(TouchCommand::Frame);
+        engine.send(TouchCommand::ContactMove { slot: 9, x: 11, y: 20 });
+        engine.send(TouchCommand::ContactMove { slot: 9, x: 12, y: 20 });
+        engine.send(TouchCommand::ContactUp { slot: 3 });
+
+        // Flush提交最后的变化并回复统计
+        let stats = engine.flush().unwrap();
+        assert_eq!(stats, DispatcherStats { frames: 2, events: 16, write_errors: 0, active_contacts: 1 });
+        assert_eq!(
+            take_events(&sink),
+            vec![
+                (EV_ABS, ABS_MT_SLOT, 3),
+                (EV_ABS, ABS_MT_TRACKING_ID, 1),
+                (EV_ABS, ABS_MT_POSITION_X, 30),
+                (EV_ABS, ABS_MT_POSITION_Y, 40),
+                (EV_ABS, ABS_MT_SLOT, 9),
+                (EV_ABS, ABS_MT_TRACKING_ID, 0),
+                (EV_ABS, ABS_MT_POSITION_X, 10),
+                (EV_ABS, ABS_MT_POSITION_Y, 20),
+                (EV_KEY, BTN_TOUCH, 1),
+                (EV_KEY, BTN_TOOL_DOUBLETAP, 1),
+                (EV_SYN, SYN_REPORT, 0),
+                (EV_ABS, ABS_MT_SLOT, 3),
+                (EV_ABS, ABS_MT_TRACKING_ID, -1),
+                (EV_ABS, ABS_MT_SLOT, 9),
+                (EV_ABS, ABS_MT_POSITION_X, 12),
+                (EV_KEY, BTN_TOOL_DOUBLETAP, 0),
+                (EV_KEY, BTN_TOOL_FINGER, 1),
+                (EV_SYN, SYN_REPORT, 0),
+            ]
+        );
+
+        // 没有变化的Flush不写空帧
+        assert_eq!(engine.flush().unwrap().frames, 2);
+        assert!(take_events(&sink).is_empty());
+    }
+
+    #[test]
+    fn type_a_reports_every_contact_each_frame() {
+        let (engine, sink) = spawn(Protocol::TypeA);
+        engine.send(TouchCommand::ContactDown { slot: 9, x: 10, y: 20 });
+        engine.send(TouchCommand::Frame);
+        engine.send(TouchCommand::ContactUp { slot: 9 });
+        engine.send(TouchCommand::Frame);
+        engine.flush();
+        assert_eq!(
+            take_events(&sink),
+            vec![
+                (EV_ABS, ABS_MT_POSITION_X, 10),
+                (EV_ABS, ABS_MT_POSITION_Y, 20),
+                (EV_ABS, ABS_MT_TRACKING_ID, 0),
+                (EV_SYN, SYN_MT_REPORT, 0),
+                (EV_KEY, BTN_TOUCH, 1),
+                (EV_SYN, SYN_REPORT, 0),
+                (EV_SYN, SYN_MT_REPORT, 0),
+                (EV_KEY, BTN_TOUCH, 0),
+                (EV_SYN, SYN_REPORT, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejected_commands_neither_allocate_ids_nor_write_frames() {
+        let (engine, sink) = spawn(Protocol::TypeA);
+        engine.send(TouchCommand::ContactDown { slot: 12, x: 10, y: 20 });
+        engine.send(TouchCommand::ContactUp { slot: 4 });
+        assert_eq!(engine.flush().unwrap().frames, 0);
+        assert!(take_events(&sink).is_empty());
+
+        // 被拒绝的按下没有占用tracking id
+        engine.send(TouchCommand::ContactDown { slot: 0, x: 10, y: 20 });
+        engine.flush();
+        assert_eq!(take_events(&sink)[2], (EV_ABS, ABS_MT_TRACKING_ID, 0));
+    }
+
+    #[test]
+    fn wrapped_tracking_ids_skip_contacts_still_held() {
+        let sink = Arc::new(Mutex::new(MemorySink::new()));
+        // 转发线程的真实手指一直按住，持有id 0
+        let tracking_ids = Arc::new(TrackingIdAllocator::new(2));
+        assert_eq!(tracking_ids.allocate(), 0);
+        let engine = TouchEngine::spawn(Arc::clone(&sink), EngineConfig {
+            protocol: Protocol::TypeB,
+            slots: 10,
+            axes: ContactAxes::default(),
+            range: RANGE,
+            tracking_ids,
+            buttons: Arc::default(),
+            validator: None,
+            metrics: Arc::default(),
+        });
+
+        let mut ids = Vec::new();
+        for _ in 0..4 {
+            engine.send(TouchCommand::ContactDown { slot: 9, x: 10, y: 20 });
+            engine.send(TouchCommand::Frame);
+            engine.send(TouchCommand::ContactUp { slot: 9 });
+            engine.flush();
+            let events = take_events(&sink);
+            ids.push(events.iter().find(|e| e.1 == ABS_MT_TRACKING_ID).unwrap().2);
+        }
+        assert_eq!(ids, vec![1, 2, 1, 2]);
+    }
+
+    #[test]
+    fn lift_all_releases_every_contact_in_one_frame() {
+        let (engine, sink) = spawn(Protocol::TypeB);
+        engine.send(TouchCommand::ContactDown { slot: 9, x: 10, y: 20 });
+        engine.send(TouchCommand::ContactDown { slot: 3, x: 30, y: 40 });
+        engine.send(TouchCommand::Frame);
+        engine.send(TouchCommand::ContactMove { slot: 9, x: 11, y: 20 });
+        engine.flush();
+        take_events(&sink);
+
+        // 未提交的移动被丢弃
+        engine.send(TouchCommand::ContactMove { slot: 3, x: 31, y: 40 });
+        engine.send(TouchCommand::LiftAll);
+        assert_eq!(engine.flush().unwrap().active_contacts, 0);
+        assert_eq!(
+            take_events(&sink),
+            vec![
+                (EV_ABS, ABS_MT_SLOT, 3),
+                (EV_ABS, ABS_MT_TRACKING_ID, -1),
+                (EV_ABS, ABS_MT_SLOT, 9),
+                (EV_ABS, ABS_MT_TRACKING_ID, -1),
+                (EV_KEY, BTN_TOUCH, 0),
+                (EV_KEY, BTN_TOOL_DOUBLETAP, 0),
+                (EV_SYN, SYN_REPORT, 0),
+            ]
+        );
+
+        // 停止时已经没有触点，不会再写帧
+        drop(engine);
+        assert!(take_events(&sink).is_empty());
+    }
+
+    // 每隔一个事件返回一次EAGAIN
+    #[derive(Default)]
+    struct BusySink {
+        busy: bool,
+        written: usize,
+    }
+
+    impl EventSink for BusySink {
+        fn write_event(&mut self, _: u16, _: u16, _: i32) -> std::io::Result<()> {
+            self.busy = !self.busy;
+            if self.busy {
+                return Err(std::io::ErrorKind::WouldBlock.into());
+            }
+            self.written += 1;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn frames_record_latency_and_eagain_retries() {
+        let sink = Arc::new(Mutex::new(BusySink::default()));
+        let metrics = SharedMetrics::default();
+        let engine = TouchEngine::spawn(Arc::clone(&sink), EngineConfig {
+            protocol: Protocol::TypeA,
+            slots: 10,
+            axes: ContactAxes::default(),
+            range: RANGE,
+            tracking_ids: Arc::default(),
+            buttons: Arc::default(),
+            validator: None,
+            metrics: Arc::clone(&metrics),
+        });
+        engine.send(TouchCommand::ContactDown { slot: 1, x: 10, y: 20 });
+        engine.send(TouchCommand::Frame);
+        // Type A重复上报的帧没有对应的命令，不计入延迟
+        engine.send(TouchCommand::Frame);
+        engine.flush();
+
+        let snapshot = metrics.lock().unwrap().snapshot();
+        assert_eq!((snapshot.frames, snapshot.events, snapshot.write_errors), (2, 9, 0));
+        // 每个事件（含两个SYN_REPORT）都重试了一次
+        assert_eq!(snapshot.eagain_retries, 11);
+        assert_eq!(snapshot.latency.count, 1);
+        assert_eq!(sink.lock().unwrap().written, 11);
+    }
+}
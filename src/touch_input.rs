@@ -1,9 +1,20 @@
-use crate::uinput::{get_input_devices, new_type_a_dev_random, new_type_a_dev_same, new_type_b_dev_same, InputDevice};
-use crate::uinput_defs::*;
+use crate::device_scanner::{find_device_by_identity, probe_device, scan_input_devices, DeviceIdentity};
+use crate::device_profile::DeviceProfile;
+use crate::device_watcher::DeviceEvent;
+use crate::gesture::{CancelToken, CoordinateMap, GestureError, GestureParams, GesturePlayback, GestureStep, Rotation};
+use crate::input_filter::{AxisRange, FilterConfig};
+use crate::metrics::{MetricsSnapshot, SharedMetrics};
+use crate::mt_validator::{SharedValidator, Validator, ValidatorSpec};
+use crate::passthrough::{Passthrough, TouchButtonState};
+use crate::touch_engine::{ContactAxes, DispatcherStats, EngineConfig, Protocol, TouchCommand, TouchEngine};
+use crate::tracking_id::TrackingIdAllocator;
+use crate::uinput::{new_type_a_dev_random, new_type_a_dev_same, new_type_b_dev_same, InputDevice};
+use log::{debug, error, info, trace, warn};
+use serde::{Deserialize, Serialize};
 use std::{
+    path::Path,
     thread,
-    time::Duration,
-    sync::{Arc, Mutex, mpsc},
+    sync::{Arc, Mutex},
 };
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -19,6 +30,7 @@ const FAKE_CONTACT: usize = 9;
 pub struct TouchContactA {
     pub pos_x: i32,
     pub pos_y: i32,
+    pub tracking_id: i32,
     pub active: bool,
 }
 
@@ -36,11 +48,38 @@ pub struct TouchContactB {
     pub active: bool,
 }
 
+// 注入触点的尺寸：长短轴按参考设备x轴范围的百分比计算，方向和压力是绝对值；
+// 参考设备没有对应的轴时不上报
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ContactSize {
+    pub touch_major_percent: i32,
+    pub touch_minor_percent: i32,
+    pub width_major_percent: i32,
+    pub width_minor_percent: i32,
+    pub orientation: i32,
+    pub pressure: i32,
+}
+
+impl Default for ContactSize {
+    fn default() -> Self {
+        Self {
+            touch_major_percent: 14,
+            touch_minor_percent: 10,
+            width_major_percent: 14,
+            width_minor_percent: 10,
+            orientation: 50,
+            pressure: 35,
+        }
+    }
+}
+
 impl Default for TouchContactA {
     fn default() -> Self {
         Self {
             pos_x: -1,
             pos_y: -1,
+            tracking_id: -1,
             active: false,
         }
     }
@@ -78,11 +117,27 @@ pub struct TouchSimulation {
     fake_pressure: i32,
     touch_device: Option<Arc<Mutex<InputDevice>>>,
     uinput_device: Option<Arc<Mutex<InputDevice>>>,
-    sync_channel: Option<mpsc::Sender<bool>>,
-    stop_channel: Option<mpsc::Sender<bool>>,
-    touch_contacts_a: Vec<TouchContactA>,
-    touch_contacts_b: Vec<TouchContactB>,
-    touch_contacts_b_arc: Option<Arc<Mutex<Vec<TouchContactB>>>>, // 用于线程间共享
+    engine: Option<TouchEngine>, // 分发线程句柄，触点状态只在分发线程中
+    reference_identity: Option<DeviceIdentity>, // 参考设备的稳定标识，热插拔后用于重新查找
+    device_lost: bool,
+    button_state: Arc<Mutex<TouchButtonState>>, // 与转发线程共享的BTN_TOUCH状态
+    tracking_ids: Arc<TrackingIdAllocator>, // 注入触点和转发的真实手指共用
+    passthrough: Option<Passthrough>,
+    passthrough_filters: Option<FilterConfig>, // 转发前对真实事件执行的过滤器
+    validate: bool,
+    validator: Option<SharedValidator>, // 校验写入虚拟设备的事件流，按虚拟设备的能力检查
+    cancel: CancelToken, // 取消正在播放的同步手势，重建虚拟设备后仍然有效
+    rotation: Rotation,
+    gesture: GestureParams,
+    contact_size: ContactSize,
+    profile: Option<DeviceProfile>, // 参考设备的型号修正，影响虚拟设备参数和坐标换算
+    metrics: SharedMetrics, // 注入延迟和吞吐统计，重建虚拟设备后继续累计
+}
+
+impl Default for TouchSimulation {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl TouchSimulation {
@@ -101,16 +156,26 @@ impl TouchSimulation {
             fake_pressure: -1,
             touch_device: None,
             uinput_device: None,
-            sync_channel: None,
-            stop_channel: None,
-            touch_contacts_a: Vec::new(),
-            touch_contacts_b: Vec::new(),
-            touch_contacts_b_arc: None,
+            engine: None,
+            reference_identity: None,
+            device_lost: false,
+            button_state: Arc::new(Mutex::new(TouchButtonState::default())),
+            tracking_ids: Arc::default(),
+            passthrough: None,
+            passthrough_filters: None,
+            validate: false,
+            validator: None,
+            cancel: CancelToken::new(),
+            rotation: Rotation::Rotation0,
+            gesture: GestureParams::default(),
+            contact_size: ContactSize::default(),
+            profile: None,
+            metrics: SharedMetrics::default(),
         }
     }
 
     pub fn new_with_device(input_device: InputDevice) -> Self {
-        let sim = Self {
+        Self {
             curr_mode: TypeMode::TypeB,
             touch_send: false,
             touch_start: false,
@@ -124,17 +189,26 @@ impl TouchSimulation {
             fake_pressure: -1,
             touch_device: Some(Arc::new(Mutex::new(input_device))),
             uinput_device: None,
-            sync_channel: None,
-            stop_channel: None,
-            touch_contacts_a: Vec::new(),
-            touch_contacts_b: Vec::new(),
-            touch_contacts_b_arc: None,
-        };
-        sim
+            engine: None,
+            reference_identity: None,
+            device_lost: false,
+            button_state: Arc::new(Mutex::new(TouchButtonState::default())),
+            tracking_ids: Arc::default(),
+            passthrough: None,
+            passthrough_filters: None,
+            validate: false,
+            validator: None,
+            cancel: CancelToken::new(),
+            rotation: Rotation::Rotation0,
+            gesture: GestureParams::default(),
+            contact_size: ContactSize::default(),
+            profile: None,
+            metrics: SharedMetrics::default(),
+        }
     }
 
     pub fn touch_input_setup(&mut self, mode: TypeMode, width: i32, height: i32) -> bool {
-        println!("touch_input_setup: mode={:?}, width={}, height={}", mode, width, height);
+        info!("touch_input_setup: mode={:?}, width={}, height={}", mode, width, height);
         
         // 如果已经有设备信息，直接使用它
         if let Some(device_arc) = self.touch_device.clone() {
@@ -143,24 +217,24 @@ impl TouchSimulation {
             drop(device_guard); // 释放锁，避免借用冲突
             
             let result = self.touch_input_start(mode, width, height, device_clone);
-            println!("touch_input_start result: {}", result);
+            debug!("touch_input_start result: {}", result);
             result
         } else {
             // 如果没有设备信息，尝试扫描获取
-            match get_input_devices() {
+            match scan_input_devices() {
                 Ok(devices) => {
-                    println!("Found {} input devices", devices.len());
+                    debug!("Found {} input devices", devices.len());
                     if !devices.is_empty() {
                         let result = self.touch_input_start(mode, width, height, devices[0].clone());
-                        println!("touch_input_start result: {}", result);
+                        debug!("touch_input_start result: {}", result);
                         result
                     } else {
-                        println!("No input devices found");
+                        error!("No input devices found");
                         false
                     }
                 }
                 Err(e) => {
-                    println!("Error getting input devices: {}", e);
+                    error!("Error getting input devices: {}", e);
                     false
                 }
             }
@@ -168,57 +242,53 @@ impl TouchSimulation {
     }
 
     pub fn touch_input_start(&mut self, mode: TypeMode, width: i32, height: i32, in_dev: InputDevice) -> bool {
+        // 没有坐标范围时所有坐标都会映射到1x1，抓取的也只是/dev/null
+        if in_dev.info.abs_unknown {
+            error!(
+                "touch_input_start: ABS ranges of {} ({}) are unknown because the node could not be opened; \
+                 run with read access to {} or without --sysfs",
+                in_dev.path, in_dev.name, in_dev.path
+            );
+            return false;
+        }
         if !self.touch_start {
             self.curr_mode = mode;
 
-            // 保存设备路径供后续使用（如果需要的话）
-            let _device_path = in_dev.path.clone();
+            // 保存设备标识，参考设备重新出现时据此重建
+            self.reference_identity = Some(DeviceIdentity::of(&in_dev));
+            self.device_lost = false;
             
             // Init Things
             self.touch_device = Some(Arc::new(Mutex::new(in_dev)));
             self.display_width = width;
             self.display_height = height;
 
-            let (sync_sender, sync_receiver) = mpsc::channel();
-            let (stop_sender, stop_receiver) = mpsc::channel();
-            self.sync_channel = Some(sync_sender);
-            self.stop_channel = Some(stop_sender);
-
-            if mode == TypeMode::TypeA || mode == TypeMode::TypeARnd {
+            let options = self.profile.as_ref().map(DeviceProfile::virtual_touch_options).unwrap_or_default();
+            let protocol = if mode == TypeMode::TypeA || mode == TypeMode::TypeARnd {
                 // 始终创建虚拟设备用于写入，真实设备只用于参数参考
                 let uinput_dev = if mode == TypeMode::TypeARnd {
-                    match new_type_a_dev_random(&*self.touch_device.as_ref().unwrap().lock().unwrap()) {
+                    match new_type_a_dev_random(&self.touch_device.as_ref().unwrap().lock().unwrap(), &options) {
                         Ok(dev) => dev,
                         Err(_) => return false,
                     }
                 } else {
-                    match new_type_a_dev_same(&*self.touch_device.as_ref().unwrap().lock().unwrap()) {
+                    match new_type_a_dev_same(&self.touch_device.as_ref().unwrap().lock().unwrap(), &options) {
                         Ok(dev) => dev,
                         Err(_) => return false,
                     }
                 };
                 self.uinput_device = Some(Arc::new(Mutex::new(uinput_dev)));
-                println!("touch_input_start: created virtual uinput device for TypeA mode");
-
-                // Set Default Values in Touch Contacts Array
-                let device = self.touch_device.as_ref().unwrap().lock().unwrap();
-                self.touch_contacts_a = vec![TouchContactA::default(); device.slots as usize];
-
-                // Start event dispatcher thread
-                let uinput_clone = Arc::clone(self.uinput_device.as_ref().unwrap());
-                let contacts_clone = self.touch_contacts_a.clone();
-                thread::spawn(move || {
-                    event_dispatcher_a(uinput_clone, contacts_clone, sync_receiver, stop_receiver);
-                });
+                info!("touch_input_start: created virtual uinput device for TypeA mode");
+                Protocol::TypeA
             } else {
                 // 始终创建虚拟设备用于写入，真实设备只用于参数参考
-                let uinput_dev = match new_type_b_dev_same(&*self.touch_device.as_ref().unwrap().lock().unwrap()) {
+                let uinput_dev = match new_type_b_dev_same(&self.touch_device.as_ref().unwrap().lock().unwrap(), &options) {
                     Ok(dev) => {
-                        println!("touch_input_start: successfully created virtual uinput device for TypeB mode");
+                        info!("touch_input_start: successfully created virtual uinput device for TypeB mode");
                         dev
                     },
                     Err(e) => {
-                        println!("touch_input_start: failed to create uinput device: {}", e);
+                        error!("touch_input_start: failed to create uinput device: {}", e);
                         return false;
                     }
                 };
@@ -226,49 +296,58 @@ impl TouchSimulation {
 
                 let device = self.touch_device.as_ref().unwrap().lock().unwrap();
                 
+                let size = self.contact_size;
                 if device.has_touch_major {
-                    self.fake_touch_major = (device.touch_x_max * 14) / 100;
+                    self.fake_touch_major = (device.touch_x_max * size.touch_major_percent) / 100;
                 }
                 if device.has_touch_minor {
-                    self.fake_touch_minor = (device.touch_x_max * 10) / 100;
+                    self.fake_touch_minor = (device.touch_x_max * size.touch_minor_percent) / 100;
                 }
                 if device.has_width_major {
-                    self.fake_width_major = (device.touch_x_max * 14) / 100;
+                    self.fake_width_major = (device.touch_x_max * size.width_major_percent) / 100;
                 }
                 if device.has_width_minor {
-                    self.fake_width_minor = (device.touch_x_max * 10) / 100;
+                    self.fake_width_minor = (device.touch_x_max * size.width_minor_percent) / 100;
                 }
                 if device.has_orientation {
-                    self.fake_orientation = 50; // Default orientation
+                    self.fake_orientation = size.orientation;
                 }
                 if device.has_pressure {
-                    self.fake_pressure = (100 * 35) / 100;
+                    self.fake_pressure = self.profile.as_ref().map_or(size.pressure, |p| p.scale_pressure(size.pressure));
                 }
-
-                // Set Default Values in Touch Contacts Array
-                let contacts_vec = vec![TouchContactB::default(); device.slots as usize];
-                
-                // 使用Arc+Mutex共享同一份数据，而不是克隆副本
-                let contacts_arc = Arc::new(Mutex::new(contacts_vec));
-                
-                // Start event dispatcher thread
-                let uinput_clone = Arc::clone(self.uinput_device.as_ref().unwrap());
-                let contacts_arc_clone = Arc::clone(&contacts_arc);
-                let fake_values = (
-                    self.fake_touch_major,
-                    self.fake_touch_minor,
-                    self.fake_width_major,
-                    self.fake_width_minor,
-                    self.fake_orientation,
-                    self.fake_pressure,
-                );
-                thread::spawn(move || {
-                    event_dispatcher_b(uinput_clone, contacts_arc_clone, fake_values, sync_receiver, stop_receiver);
-                });
-                
-                // 保存Arc引用以便主线程使用
-                self.touch_contacts_b_arc = Some(contacts_arc);
-            }
+                Protocol::TypeB
+            };
+
+            // 触点数量以虚拟设备为准，参考设备可能是没有slot的Type A面板
+            let uinput_device = Arc::clone(self.uinput_device.as_ref().unwrap());
+            let (slots, range) = {
+                let device = uinput_device.lock().unwrap();
+                (device.slots as usize, AxisRange::of(&device))
+            };
+            self.tracking_ids = Arc::new(TrackingIdAllocator::for_device(&uinput_device.lock().unwrap()));
+            self.button_state = Arc::new(Mutex::new(TouchButtonState::default()));
+            // 同步接口的触点，转发时真实手指不会分配到这个slot
+            self.button_state.lock().unwrap().reserve(FAKE_CONTACT);
+            let validator = self.start_validator();
+
+            // 分发线程独占触点状态，通过命令通道接收更新
+            self.engine = Some(TouchEngine::spawn(uinput_device, EngineConfig {
+                protocol,
+                slots,
+                axes: ContactAxes {
+                    touch_major: self.fake_touch_major,
+                    touch_minor: self.fake_touch_minor,
+                    width_major: self.fake_width_major,
+                    width_minor: self.fake_width_minor,
+                    orientation: self.fake_orientation,
+                    pressure: self.fake_pressure,
+                },
+                range,
+                tracking_ids: Arc::clone(&self.tracking_ids),
+                buttons: Arc::clone(&self.button_state),
+                validator,
+                metrics: Arc::clone(&self.metrics),
+            }));
 
             self.touch_start = true;
         }
@@ -277,9 +356,11 @@ impl TouchSimulation {
 
     pub fn touch_input_stop(&mut self) {
         if self.touch_start {
-            if let Some(stop_sender) = &self.stop_channel {
-                let _ = stop_sender.send(true);
-            }
+            // 先停止转发并释放真实设备
+            self.passthrough = None;
+
+            // drop时发送Stop，分发线程抬起所有触点后退出
+            self.engine = None;
 
             if let Some(uinput_device) = &self.uinput_device {
                 let mut device = uinput_device.lock().unwrap();
@@ -288,325 +369,317 @@ impl TouchSimulation {
 
             self.uinput_device = None;
             self.touch_device = None;
-            self.sync_channel = None;
-            self.stop_channel = None;
-            self.touch_contacts_a.clear();
-            self.touch_contacts_b.clear();
-            self.touch_contacts_b_arc = None;
+            self.touch_send = false;
             self.touch_start = false;
         }
     }
 
-    pub fn send_touch_move(&mut self, x: i32, y: i32) {
-        if !self.touch_start {
-            return;
+    // 独占真实触摸屏并把它的事件转发到虚拟设备，注入触点使用的slot不会分配给真实手指
+    pub fn passthrough_start(&mut self) -> bool {
+        if !self.touch_start || self.passthrough.is_some() {
+            return self.passthrough.is_some();
+        }
+        if self.curr_mode != TypeMode::TypeB {
+            warn!("passthrough_start: only supported in TypeB mode");
+            return false;
         }
 
-        if !self.touch_send {
-            self.touch_send = true;
+        let (Some(touch_device), Some(uinput_device)) = (&self.touch_device, &self.uinput_device) else {
+            return false;
+        };
+        match Passthrough::start(
+            Arc::clone(touch_device),
+            Arc::clone(uinput_device),
+            Arc::clone(&self.button_state),
+            Arc::clone(&self.tracking_ids),
+            self.passthrough_filters.as_ref(),
+            self.validator.clone(),
+            Arc::clone(&self.metrics),
+        ) {
+            Ok(passthrough) => {
+                self.passthrough = Some(passthrough);
+                true
+            }
+            Err(e) => {
+                error!("passthrough_start: failed to grab reference device: {}", e);
+                false
+            }
         }
+    }
 
-        let device = self.touch_device.as_ref().unwrap().lock().unwrap();
-        let x = (x * device.touch_x_max / self.display_width) + device.touch_x_min;
-        let y = (y * device.touch_y_max / self.display_height) + device.touch_y_min;
-        
-        println!("send_touch_move: converted coordinates: x={}, y={}", x, y);
+    // 下次开启转发时生效，重建设备后保留
+    pub fn set_passthrough_filters(&mut self, filters: Option<FilterConfig>) {
+        self.passthrough_filters = filters;
+    }
+
+    // 下次启动虚拟设备时生效，每个虚拟设备使用新的校验器
+    pub fn set_validation(&mut self, enabled: bool) {
+        self.validate = enabled;
+    }
+
+    pub fn validator(&self) -> Option<&SharedValidator> {
+        self.validator.as_ref()
+    }
 
-        if self.curr_mode == TypeMode::TypeA || self.curr_mode == TypeMode::TypeARnd {
-            println!("send_touch_move: updating TypeA contact {}", FAKE_CONTACT);
-            self.touch_contacts_a[FAKE_CONTACT].pos_x = x;
-            self.touch_contacts_a[FAKE_CONTACT].pos_y = y;
-            self.touch_contacts_a[FAKE_CONTACT].active = true;
+    fn start_validator(&mut self) -> Option<SharedValidator> {
+        self.validator = if self.validate {
+            let spec = ValidatorSpec::from_device(&self.uinput_device.as_ref().unwrap().lock().unwrap());
+            Some(Arc::new(Mutex::new(Validator::new(spec))))
         } else {
-            println!("send_touch_move: updating TypeB contact {}", FAKE_CONTACT);
-            
-            // 使用Arc共享数据
-            if let Some(contacts_arc) = &self.touch_contacts_b_arc {
-                let mut contacts = contacts_arc.lock().unwrap();
-                let contact = &mut contacts[FAKE_CONTACT];
-                
-                if device.has_touch_major {
-                    contact.touch_major = self.fake_touch_major;
-                    println!("send_touch_move: set touch_major = {}", self.fake_touch_major);
-                }
-                if device.has_touch_minor {
-                    contact.touch_minor = self.fake_touch_minor;
-                    println!("send_touch_move: set touch_minor = {}", self.fake_touch_minor);
-                }
-                if device.has_width_major {
-                    contact.width_major = self.fake_width_major;
-                    println!("send_touch_move: set width_major = {}", self.fake_width_major);
-                }
-                if device.has_width_minor {
-                    contact.width_minor = self.fake_width_minor;
-                    println!("send_touch_move: set width_minor = {}", self.fake_width_minor);
-                }
-                if device.has_orientation {
-                    contact.orientation = self.fake_orientation;
-                    println!("send_touch_move: set orientation = {}", self.fake_orientation);
+            None
+        };
+        self.validator.clone()
+    }
+
+    pub fn passthrough_stop(&mut self) {
+        self.passthrough = None;
+    }
+
+    pub fn is_passthrough(&self) -> bool {
+        self.passthrough.is_some()
+    }
+
+    pub fn reference_identity(&self) -> Option<&DeviceIdentity> {
+        self.reference_identity.as_ref()
+    }
+
+    pub fn is_device_lost(&self) -> bool {
+        self.device_lost
+    }
+
+    // 处理热插拔事件，参考设备重新出现时重建虚拟设备，返回是否发生了重建
+    pub fn handle_device_event(&mut self, event: &DeviceEvent) -> bool {
+        let Some(identity) = self.reference_identity.clone() else {
+            return false;
+        };
+
+        match event {
+            DeviceEvent::Removed(path) => {
+                let is_reference = self
+                    .touch_device
+                    .as_ref()
+                    .is_some_and(|device| device.lock().unwrap().path == *path);
+                if is_reference {
+                    warn!("handle_device_event: reference device {} removed", path);
+                    self.device_lost = true;
                 }
-                if device.has_pressure {
-                    contact.pressure = self.fake_pressure;
-                    println!("send_touch_move: set pressure = {}", self.fake_pressure);
+                false
+            }
+            DeviceEvent::Added(path) => {
+                // 参考设备还在时不重建；等待节点可读由DeviceWatcher的线程完成
+                if !self.device_lost {
+                    debug!("handle_device_event: ignoring {}, reference device is present", path);
+                    return false;
                 }
-                if contact.tracking_id < 0 {
-                    contact.tracking_id = device.slots - 2;
-                    println!("send_touch_move: set tracking_id = {}", contact.tracking_id);
+                let Some(device) = probe_device(Path::new(path)) else {
+                    return false;
+                };
+                if !identity.matches(&device) {
+                    return false;
                 }
+                info!("handle_device_event: reference device reappeared at {}", path);
+                self.rebuild_with_device(device)
+            }
+        }
+    }
 
-                contact.position_x = x;
-                contact.position_y = y;
-                contact.active = true;
-                println!("send_touch_move: TypeB contact updated - active: true, pos_x: {}, pos_y: {}", x, y);
-            } else {
-                println!("send_touch_move: ERROR - touch_contacts_b_arc is None!");
+    // 按稳定标识重新扫描参考设备并重建虚拟设备
+    pub fn rebuild_device(&mut self) -> bool {
+        let Some(identity) = self.reference_identity.clone() else {
+            warn!("rebuild_device: no reference device identity");
+            return false;
+        };
+        match find_device_by_identity(&identity) {
+            Some(device) => self.rebuild_with_device(device),
+            None => {
+                warn!("rebuild_device: device {:?} not found", identity);
+                false
             }
         }
+    }
+
+    fn rebuild_with_device(&mut self, device: InputDevice) -> bool {
+        info!("rebuild_with_device: rebuilding on {}", device.path);
+        let mode = self.curr_mode;
+        let width = self.display_width;
+        let height = self.display_height;
+        let passthrough = self.passthrough.is_some();
+        self.touch_input_stop();
+        if !self.touch_input_start(mode, width, height, device) {
+            return false;
+        }
+        if passthrough && !self.passthrough_start() {
+            error!("rebuild_with_device: failed to restart passthrough");
+        }
+        true
+    }
+
+    pub fn send_touch_move(&mut self, x: i32, y: i32) {
+        if !self.touch_start {
+            return;
+        }
+        let Some(engine) = &self.engine else {
+            return;
+        };
+        let first = !self.touch_send;
+
+        let Some(map) = self.coordinate_map() else {
+            return;
+        };
+        let (x, y) = map.to_device(x, y);
+        
+        trace!("send_touch_move: converted coordinates: x={}, y={}", x, y);
 
-        if let Some(sync_sender) = &self.sync_channel {
-            let _ = sync_sender.send(true);
+        // 第一次移动即按下，按下时分配新的tracking id
+        let command = if first {
+            TouchCommand::ContactDown { slot: FAKE_CONTACT, x, y }
+        } else {
+            TouchCommand::ContactMove { slot: FAKE_CONTACT, x, y }
+        };
+        if !engine.send(command) || !engine.send(TouchCommand::Frame) {
+            error!("send_touch_move: ERROR - dispatcher is not running");
         }
+        self.touch_send = true;
 
-        thread::sleep(Duration::from_millis(15));
+        thread::sleep(self.gesture.step_interval);
     }
 
     pub fn send_touch_up(&mut self) {
-        println!("send_touch_up: touch_start={}, touch_send={}", self.touch_start, self.touch_send);
+        trace!("send_touch_up: touch_start={}, touch_send={}", self.touch_start, self.touch_send);
         if !self.touch_start || !self.touch_send {
-            println!("send_touch_up: early return - touch_start={}, touch_send={}", self.touch_start, self.touch_send);
+            debug!("send_touch_up: early return - touch_start={}, touch_send={}", self.touch_start, self.touch_send);
             return;
         }
 
         self.touch_send = false;
 
-        if self.curr_mode == TypeMode::TypeA || self.curr_mode == TypeMode::TypeARnd {
-            println!("send_touch_up: TypeA mode, deactivating contact {}", FAKE_CONTACT);
-            self.touch_contacts_a[FAKE_CONTACT].pos_x = -1;
-            self.touch_contacts_a[FAKE_CONTACT].pos_y = -1;
-            self.touch_contacts_a[FAKE_CONTACT].active = false;
-        } else {
-            println!("send_touch_up: TypeB mode, deactivating contact {}", FAKE_CONTACT);
-            
-            if let Some(contacts_arc) = &self.touch_contacts_b_arc {
-                let mut contacts = contacts_arc.lock().unwrap();
-                let contact = &mut contacts[FAKE_CONTACT];
-                let device = self.touch_device.as_ref().unwrap().lock().unwrap();
-                
-                if device.has_touch_major {
-                    contact.touch_major = -1;
-                }
-                if device.has_touch_minor {
-                    contact.touch_minor = -1;
-                }
-                if device.has_width_major {
-                    contact.width_major = -1;
-                }
-                if device.has_width_minor {
-                    contact.width_minor = -1;
-                }
-                if device.has_orientation {
-                    contact.orientation = 0;
-                }
-                if device.has_pressure {
-                    contact.pressure = 0;
-                }
-
-                contact.tracking_id = -1;
-                contact.position_x = -1;
-                contact.position_y = -1;
-                contact.active = false;
-            } else {
-                println!("send_touch_up: ERROR - touch_contacts_b_arc is None!");
+        if let Some(engine) = &self.engine {
+            trace!("send_touch_up: releasing contact {}", FAKE_CONTACT);
+            if !engine.send(TouchCommand::ContactUp { slot: FAKE_CONTACT }) || !engine.send(TouchCommand::Frame) {
+                error!("send_touch_up: ERROR - dispatcher is not running");
             }
         }
 
-        if let Some(sync_sender) = &self.sync_channel {
-            println!("send_touch_up: sending sync signal");
-            let _ = sync_sender.send(true);
-        }
+        thread::sleep(self.gesture.step_interval);
+    }
 
-        thread::sleep(Duration::from_millis(15));
+    // 等分发线程写完已提交的命令，返回它的统计
+    pub fn flush(&self) -> Option<DispatcherStats> {
+        self.engine.as_ref()?.flush()
     }
-}
 
-// Event dispatcher for Type A
-fn event_dispatcher_a(
-    uinput_device: Arc<Mutex<InputDevice>>,
-    touch_contacts: Vec<TouchContactA>,
-    sync_receiver: mpsc::Receiver<bool>,
-    stop_receiver: mpsc::Receiver<bool>,
-) {
-    println!("event_dispatcher_a: started");
-    let mut is_btn_down = false;
-
-    loop {
-        // Check for stop signal
-        if stop_receiver.try_recv().is_ok() {
-            println!("event_dispatcher_a: received stop signal");
-            break;
-        }
+    // 当前的虚拟触摸设备，与分发线程共享同一个fd；info.event_node是内核分配的event节点
+    pub fn virtual_device(&self) -> Option<InputDevice> {
+        Some(self.uinput_device.as_ref()?.lock().unwrap().clone())
+    }
 
-        // Check for sync signal
-        if sync_receiver.try_recv().is_ok() {
-            println!("event_dispatcher_a: received sync signal");
-            let mut active_slots = 0;
-            let mut uinput = uinput_device.lock().unwrap();
-
-            for (idx, contact) in touch_contacts.iter().enumerate() {
-                if contact.active && contact.pos_x > 0 && contact.pos_y > 0 {
-                    println!("event_dispatcher_a: processing active contact {}", idx);
-                    let _ = uinput.write_event(EV_ABS, ABS_MT_POSITION_X, contact.pos_x);
-                    let _ = uinput.write_event(EV_ABS, ABS_MT_POSITION_Y, contact.pos_y);
-                    let _ = uinput.write_event(EV_ABS, ABS_MT_TRACKING_ID, idx as i32);
-                    let _ = uinput.write_event(EV_SYN, SYN_MT_REPORT, 0);
-
-                    active_slots += 1;
-                }
-            }
+    // 先等分发线程写完已提交的命令，统计里包含它们
+    pub fn metrics(&self) -> MetricsSnapshot {
+        self.flush();
+        self.metrics.lock().unwrap().snapshot()
+    }
 
-            if active_slots == 0 && is_btn_down {
-                println!("event_dispatcher_a: button up");
-                is_btn_down = false;
-                let _ = uinput.write_event(EV_SYN, SYN_MT_REPORT, 0);
-                let _ = uinput.write_event(EV_KEY, BTN_TOUCH, 0);
-            } else if active_slots > 0 && !is_btn_down {
-                println!("event_dispatcher_a: button down");
-                is_btn_down = true;
-                let _ = uinput.write_event(EV_KEY, BTN_TOUCH, 1);
-            }
+    fn coordinate_map(&self) -> Option<CoordinateMap> {
+        let device = self.touch_device.as_ref()?.lock().unwrap();
+        Some(CoordinateMap {
+            display_width: self.display_width,
+            display_height: self.display_height,
+            rotation: self.rotation,
+            axes: self.profile.as_ref().map(|p| p.axes).unwrap_or_default(),
+            x_min: device.touch_x_min,
+            x_max: device.touch_x_max,
+            y_min: device.touch_y_min,
+            y_max: device.touch_y_max,
+        })
+    }
 
-            if active_slots == 0 && is_btn_down {
-                println!("event_dispatcher_a: button up");
-                is_btn_down = false;
-                println!("event_dispatcher_a: writing BTN_TOUCH: 0");
-                let _ = uinput.write_event(EV_KEY, BTN_TOUCH, 0);
-            } else if active_slots > 0 && !is_btn_down {
-                println!("event_dispatcher_a: button down");
-                is_btn_down = true;
-                println!("event_dispatcher_a: writing BTN_TOUCH: 1");
-                let _ = uinput.write_event(EV_KEY, BTN_TOUCH, 1);
-            }
+    pub fn swipe(&mut self, start_x: i32, start_y: i32, end_x: i32, end_y: i32) -> Result<(), GestureError> {
+        self.play(&self.gesture.swipe(start_x, start_y, end_x, end_y))
+    }
 
-            println!("event_dispatcher_a: sending SYN_REPORT");
-            let _ = uinput.write_event(EV_SYN, SYN_REPORT, 0);
-        }
+    pub fn tap(&mut self, x: i32, y: i32) -> Result<(), GestureError> {
+        self.play(&self.gesture.tap(x, y, self.gesture.tap_hold))
+    }
 
-        thread::sleep(Duration::from_millis(1));
+    // 之后生成的手势使用这些参数，异步句柄需要重新获取
+    pub fn set_gesture_params(&mut self, params: GestureParams) {
+        self.gesture = params;
     }
-    println!("event_dispatcher_a: stopped");
-}
 
-// Event dispatcher for Type B
-fn event_dispatcher_b(
-    uinput_device: Arc<Mutex<InputDevice>>,
-    contacts_arc: Arc<Mutex<Vec<TouchContactB>>>,
-    _fake_values: (i32, i32, i32, i32, i32, i32),
-    sync_receiver: mpsc::Receiver<bool>,
-    stop_receiver: mpsc::Receiver<bool>,
-) {
-    println!("event_dispatcher_b: started");
-    let mut is_btn_down = false;
-
-    loop {
-        // Check for stop signal
-        if stop_receiver.try_recv().is_ok() {
-            println!("event_dispatcher_b: received stop signal");
-            break;
-        }
+    pub fn gesture_params(&self) -> GestureParams {
+        self.gesture
+    }
 
-        // Check for sync signal - 使用阻塞接收而不是try_recv
-        match sync_receiver.recv_timeout(Duration::from_millis(100)) {
-            Ok(_) => {
-                println!("event_dispatcher_b: received sync signal");
-                let mut active_slots = 0;
-                let mut uinput = uinput_device.lock().unwrap();
-                
-                // 从Arc获取共享数据
-                let mut contacts_guard = contacts_arc.lock().unwrap();
-                let contacts = &mut *contacts_guard;
-                
-                println!("event_dispatcher_b: checking {} contacts", contacts.len());
-                for (idx, contact) in contacts.iter_mut().enumerate() {
-                    println!("event_dispatcher_b: contact {} - active: {}, tracking_id: {}, pos_x: {}, pos_y: {}",
-                             idx, contact.active, contact.tracking_id, contact.position_x, contact.position_y);
-                    if contact.active {
-                        println!("event_dispatcher_b: processing active contact {}", idx);
-                        active_slots += 1;
-
-                        println!("event_dispatcher_b: writing ABS_MT_SLOT: {}", idx as i32);
-                        let _ = uinput.write_event(EV_ABS, ABS_MT_SLOT, idx as i32);
-
-                        if contact.tracking_id >= 0 {
-                            println!("event_dispatcher_b: writing ABS_MT_TRACKING_ID: {}", contact.tracking_id);
-                            let _ = uinput.write_event(EV_ABS, ABS_MT_TRACKING_ID, contact.tracking_id);
-                        }
-                        if contact.position_x >= 0 {
-                            println!("event_dispatcher_b: writing ABS_MT_POSITION_X: {}", contact.position_x);
-                            let _ = uinput.write_event(EV_ABS, ABS_MT_POSITION_X, contact.position_x);
-                        }
-                        if contact.position_y >= 0 {
-                            println!("event_dispatcher_b: writing ABS_MT_POSITION_Y: {}", contact.position_y);
-                            let _ = uinput.write_event(EV_ABS, ABS_MT_POSITION_Y, contact.position_y);
-                        }
-                        if contact.touch_major >= 0 {
-                            println!("event_dispatcher_b: writing ABS_MT_TOUCH_MAJOR: {}", contact.touch_major);
-                            let _ = uinput.write_event(EV_ABS, ABS_MT_TOUCH_MAJOR, contact.touch_major);
-                        }
-                        if contact.touch_minor >= 0 {
-                            println!("event_dispatcher_b: writing ABS_MT_TOUCH_MINOR: {}", contact.touch_minor);
-                            let _ = uinput.write_event(EV_ABS, ABS_MT_TOUCH_MINOR, contact.touch_minor);
-                        }
-                        if contact.width_major >= 0 {
-                            println!("event_dispatcher_b: writing ABS_MT_WIDTH_MAJOR: {}", contact.width_major);
-                            let _ = uinput.write_event(EV_ABS, ABS_MT_WIDTH_MAJOR, contact.width_major);
-                        }
-                        if contact.width_minor >= 0 {
-                            println!("event_dispatcher_b: writing ABS_MT_WIDTH_MINOR: {}", contact.width_minor);
-                            let _ = uinput.write_event(EV_ABS, ABS_MT_WIDTH_MINOR, contact.width_minor);
-                        }
-                        if contact.pressure >= 0 {
-                            println!("event_dispatcher_b: writing ABS_MT_PRESSURE: {}", contact.pressure);
-                            let _ = uinput.write_event(EV_ABS, ABS_MT_PRESSURE, contact.pressure);
-                        }
-                        if contact.orientation >= 0 {
-                            println!("event_dispatcher_b: writing ABS_MT_ORIENTATION: {}", contact.orientation);
-                            let _ = uinput.write_event(EV_ABS, ABS_MT_ORIENTATION, contact.orientation);
-                        }
-                    } else if !contact.active && contact.tracking_id >= 0 {
-                        println!("event_dispatcher_b: deactivating contact {}", idx);
-                        println!("event_dispatcher_b: writing ABS_MT_SLOT: {}", idx as i32);
-                        let _ = uinput.write_event(EV_ABS, ABS_MT_SLOT, idx as i32);
-                        println!("event_dispatcher_b: writing ABS_MT_TRACKING_ID: -1");
-                        let _ = uinput.write_event(EV_ABS, ABS_MT_TRACKING_ID, -1);
-                        contact.tracking_id = -1;
-                    }
-                }
+    // 显示坐标按这个方向换算，立即生效
+    pub fn set_rotation(&mut self, rotation: Rotation) {
+        self.rotation = rotation;
+    }
 
-                if active_slots == 0 && is_btn_down {
-                    println!("event_dispatcher_b: button up");
-                    is_btn_down = false;
-                    println!("event_dispatcher_b: writing BTN_TOUCH: 0");
-                    let _ = uinput.write_event(EV_KEY, BTN_TOUCH, 0);
-                } else if active_slots > 0 && !is_btn_down {
-                    println!("event_dispatcher_b: button down");
-                    is_btn_down = true;
-                    println!("event_dispatcher_b: writing BTN_TOUCH: 1");
-                    let _ = uinput.write_event(EV_KEY, BTN_TOUCH, 1);
-                }
+    // 在touch_input_start之前设置，虚拟设备重建时生效
+    pub fn set_contact_size(&mut self, size: ContactSize) {
+        self.contact_size = size;
+    }
+
+    // 在touch_input_start之前设置；坐标轴修正立即生效，虚拟设备参数在重建时生效
+    pub fn set_profile(&mut self, profile: Option<DeviceProfile>) {
+        self.profile = profile;
+    }
+
+    pub fn profile(&self) -> Option<&DeviceProfile> {
+        self.profile.as_ref()
+    }
 
-                println!("event_dispatcher_b: sending SYN_REPORT");
-                let _ = uinput.write_event(EV_SYN, SYN_REPORT, 0);
-            },
-            Err(mpsc::RecvTimeoutError::Timeout) => {
-                // 超时，继续循环
-                thread::sleep(Duration::from_millis(1));
-                continue;
-            },
-            Err(mpsc::RecvTimeoutError::Disconnected) => {
-                println!("event_dispatcher_b: sync channel disconnected");
-                break;
-            },
+    // 按步骤阻塞播放手势，使用注入触点的slot；被cancel_token()取消时抬起触点并返回Cancelled
+    pub fn play(&mut self, steps: &[GestureStep]) -> Result<(), GestureError> {
+        if self.touch_send {
+            self.send_touch_up();
         }
+        let (Some(engine), Some(map)) = (&self.engine, self.coordinate_map()) else {
+            return Err(GestureError::EngineStopped);
+        };
+        let since = self.cancel.generation();
+        let mut playback = GesturePlayback::new(engine.sender(), FAKE_CONTACT, map);
+        for step in steps {
+            playback.apply(step)?;
+            if self.cancel.wait(since, step.delay) {
+                playback.cancel();
+                return Err(GestureError::Cancelled);
+            }
+        }
+        Ok(())
     }
-    println!("event_dispatcher_b: stopped");
-}
\ No newline at end of file
+
+    // 在其他线程中取消正在播放的手势
+    pub fn cancel_token(&self) -> CancelToken {
+        self.cancel.clone()
+    }
+
+    // 紧急中止：取消正在播放的手势，并在同一帧里抬起所有注入的触点；
+    // 转发中的真实手指不受影响
+    pub fn lift_all(&mut self) -> bool {
+        self.cancel.cancel();
+        self.touch_send = false;
+        let Some(engine) = &self.engine else {
+            return false;
+        };
+        if !engine.send(TouchCommand::LiftAll) {
+            error!("lift_all: ERROR - dispatcher is not running");
+            return false;
+        }
+        engine.flush().is_some()
+    }
+
+    // 异步接口的句柄，虚拟设备重建后需要重新获取
+    #[cfg(feature = "tokio")]
+    pub fn async_handle(&self) -> Option<crate::async_touch::AsyncTouchHandle> {
+        let slots = self.uinput_device.as_ref()?.lock().unwrap().slots as usize;
+        let handle = crate::async_touch::AsyncTouchHandle::new(
+            self.engine.as_ref()?.sender(),
+            FAKE_CONTACT,
+            self.coordinate_map()?,
+            slots,
+            Arc::clone(&self.button_state),
+        );
+        Some(handle.with_params(self.gesture))
+    }
+}
@@ -0,0 +1,103 @@
+use crate::uinput::InputDevice;
+use crate::uinput_defs::ABS_MT_TRACKING_ID;
+use std::{collections::BTreeSet, sync::Mutex};
+
+const DEFAULT_TRACKING_ID_MAX: i32 = 65535;
+
+// 为每个新触点分配递增的tracking id，超过设备声明的最大值后从0重新开始，跳过仍被触点持有的id；
+// 注入触点和转发的真实手指共用一个分配器，连续两次点击不会拿到相同的id
+#[derive(Debug)]
+pub struct TrackingIdAllocator {
+    state: Mutex<IdState>,
+    max: i32,
+}
+
+#[derive(Debug, Default)]
+struct IdState {
+    next: i32,
+    // 已经分配、还没有release的id
+    active: BTreeSet<i32>,
+}
+
+impl TrackingIdAllocator {
+    pub fn new(max: i32) -> Self {
+        Self {
+            state: Mutex::default(),
+            max: max.max(0),
+        }
+    }
+
+    // 使用虚拟设备声明的ABS_MT_TRACKING_ID范围
+    pub fn for_device(device: &InputDevice) -> Self {
+        let max = device
+            .info
+            .abs_infos
+            .get(&ABS_MT_TRACKING_ID)
+            .map_or(DEFAULT_TRACKING_ID_MAX, |abs| abs.maximum);
+        Self::new(max)
+    }
+
+    pub fn max(&self) -> i32 {
+        self.max
+    }
+
+    // 触点抬起前一直持有分配到的id，抬起时调用release
+    pub fn allocate(&self) -> i32 {
+        let mut state = self.state.lock().unwrap();
+        let following = |id: i32| if id >= self.max { 0 } else { id + 1 };
+        // 所有id都被持有时（触点数超过max + 1）只能复用下一个id
+        let mut id = state.next;
+        for _ in 0..=self.max {
+            if !state.active.contains(&id) {
+                break;
+            }
+            id = following(id);
+        }
+        state.next = following(id);
+        state.active.insert(id);
+        id
+    }
+
+    pub fn release(&self, id: i32) {
+        self.state.lock().unwrap().active.remove(&id);
+    }
+}
+
+impl Default for TrackingIdAllocator {
+    fn default() -> Self {
+        Self::new(DEFAULT_TRACKING_ID_MAX)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allocates_increasing_ids_and_wraps_at_max() {
+        let ids = TrackingIdAllocator::new(2);
+        let allocated: Vec<i32> = (0..5)
+            .map(|_| {
+                let id = ids.allocate();
+                ids.release(id);
+                id
+            })
+            .collect();
+        assert_eq!(allocated, vec![0, 1, 2, 0, 1]);
+    }
+
+    #[test]
+    fn wrapping_skips_ids_still_held() {
+        let ids = TrackingIdAllocator::new(3);
+        // 一直按住的手指拿到0，其余触点反复按下抬起
+        let held = ids.allocate();
+        let mut allocated = Vec::new();
+        for _ in 0..6 {
+            let id = ids.allocate();
+            allocated.push(id);
+            ids.release(id);
+        }
+        assert_eq!(held, 0);
+        assert_eq!(allocated, vec![1, 2, 3, 1, 2, 3]);
+    }
+}
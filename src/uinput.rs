@@ -1,15 +1,56 @@
 use crate::uinput_defs::*;
+use log::{debug, info, trace};
+use serde::Serialize;
+use std::collections::BTreeMap;
 use std::fs::{File, OpenOptions};
 use std::io::Write;
-use std::os::unix::io::AsRawFd;
+use std::os::unix::io::{AsRawFd, RawFd};
 use std::sync::{Arc, Mutex};
 use std::os::unix::fs::OpenOptionsExt;
 
+// 扫描时读取到的设备元数据，虚拟设备和模拟设备保持默认值
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DeviceInfo {
+    pub driver_version: i32,
+    pub phys: String,
+    pub uniq: String,
+    pub sysfs_path: String,
+    pub abs_infos: BTreeMap<u16, AbsInfo>,
+    pub events: Vec<u16>,
+    pub properties: Vec<u16>,
+    pub keys: Vec<u16>,
+    pub rels: Vec<u16>,
+    pub switches: Vec<u16>,
+    // 由模拟描述生成的设备，没有真实的设备节点
+    pub mock: bool,
+    // 匹配到的设备档案
+    pub profile: Option<String>,
+    // 虚拟设备对应的/dev/input/eventN，由UI_GET_SYSNAME查到；扫描到的设备就是path
+    pub event_node: Option<String>,
+    // sysfs发现的设备没能打开event节点，abs_infos只是全0的占位，不能用来映射坐标
+    pub abs_unknown: bool,
+}
+
+// 写入uinput的input_event记录长度：timeval按两个i64序列化
+pub const EVENT_RECORD_SIZE: usize = 24;
+
+// 手动序列化以确保LittleEndian字节序，与Go版本保持一致；时间戳为0，由内核填写。
+// 在栈上完成，写每个事件不再分配内存
+pub fn encode_event(event_type: u16, code: u16, value: i32) -> [u8; EVENT_RECORD_SIZE] {
+    let mut record = [0u8; EVENT_RECORD_SIZE];
+    // TimeVal: sec (i64) + usec (i64) = 16字节，全部为0
+    record[16..18].copy_from_slice(&event_type.to_le_bytes());
+    record[18..20].copy_from_slice(&code.to_le_bytes());
+    record[20..24].copy_from_slice(&value.to_le_bytes());
+    record
+}
+
 // InputDevice struct with actual functionality
 #[derive(Debug)]
 pub struct InputDevice {
     pub name: String,
     pub path: String,
+    pub input_id: InputId,
     pub slots: i32,
     pub touch_x_min: i32,
     pub touch_x_max: i32,
@@ -21,6 +62,7 @@ pub struct InputDevice {
     pub has_width_minor: bool,
     pub has_orientation: bool,
     pub has_pressure: bool,
+    pub info: DeviceInfo,
     pub file: Arc<Mutex<File>>,
 }
 
@@ -29,6 +71,7 @@ impl Clone for InputDevice {
         Self {
             name: self.name.clone(),
             path: self.path.clone(),
+            input_id: self.input_id,
             slots: self.slots,
             touch_x_min: self.touch_x_min,
             touch_x_max: self.touch_x_max,
@@ -40,403 +83,332 @@ impl Clone for InputDevice {
             has_width_minor: self.has_width_minor,
             has_orientation: self.has_orientation,
             has_pressure: self.has_pressure,
-            file: Arc::new(Mutex::new(File::open("/dev/null").unwrap())), // Simplified clone
+            info: self.info.clone(),
+            file: Arc::clone(&self.file), // 共享同一个fd，转发模式需要读取真实设备
         }
     }
 }
 
 impl InputDevice {
+    // 可以直接打开读取的event节点：虚拟设备是内核分配的节点，真实设备是path本身
+    pub fn event_node(&self) -> Option<&str> {
+        self.info
+            .event_node
+            .as_deref()
+            .or_else(|| self.path.starts_with("/dev/input/").then_some(self.path.as_str()))
+    }
+
     pub fn grab(&mut self) -> std::io::Result<()> {
-        println!("InputDevice::grab: grabbing device {}", self.path);
+        debug!("InputDevice::grab: grabbing device {}", self.path);
         let file = self.file.lock().unwrap();
         unsafe {
             let fd = file.as_raw_fd();
-            let result = libc::ioctl(fd, eviocgrab() as libc::c_int, 1);
+            let result = libc::ioctl(fd, eviocgrab() as _, 1);
             if result == -1 {
                 return Err(std::io::Error::last_os_error());
             } else {
-                println!("InputDevice::grab: ioctl result = {}", result);
+                debug!("InputDevice::grab: ioctl result = {}", result);
             }
         }
-        println!("InputDevice::grab: successfully grabbed device");
+        debug!("InputDevice::grab: successfully grabbed device");
         Ok(())
     }
 
     pub fn release(&mut self) -> std::io::Result<()> {
-        println!("InputDevice::release: releasing device {}", self.path);
+        debug!("InputDevice::release: releasing device {}", self.path);
         let file = self.file.lock().unwrap();
         unsafe {
             let fd = file.as_raw_fd();
-            let result = libc::ioctl(fd, eviocgrab() as libc::c_int, 0);
+            let result = libc::ioctl(fd, eviocgrab() as _, 0);
             if result == -1 {
                 return Err(std::io::Error::last_os_error());
             } else {
-                println!("InputDevice::release: ioctl result = {}", result);
+                debug!("InputDevice::release: ioctl result = {}", result);
             }
         }
-        println!("InputDevice::release: successfully released device");
+        debug!("InputDevice::release: successfully released device");
         Ok(())
     }
 
     pub fn write_event(&mut self, event_type: u16, code: u16, value: i32) -> std::io::Result<()> {
-        use crate::uinput_defs::InputEvent;
-        use nix::sys::time::TimeVal;
-        
-        // 使用与Go相同的时间戳格式
-        let event = InputEvent {
-            time: TimeVal::new(0, 0),
-            event_type,
-            code,
-            value,
-        };
-        
+        let record = encode_event(event_type, code, value);
         let mut file = self.file.lock().unwrap();
-        
-        // 手动序列化以确保LittleEndian字节序，与Go版本保持一致
-        let mut buffer = Vec::with_capacity(std::mem::size_of::<InputEvent>());
-        
-        // TimeVal: sec (i64) + usec (i64) = 16字节
-        let time_val = event.time;
-        buffer.extend_from_slice(&(time_val.tv_sec() as i64).to_le_bytes());
-        buffer.extend_from_slice(&(time_val.tv_usec() as i64).to_le_bytes());
-        
-        // event_type: u16 = 2字节
-        buffer.extend_from_slice(&event.event_type.to_le_bytes());
-        
-        // code: u16 = 2字节
-        buffer.extend_from_slice(&event.code.to_le_bytes());
-        
-        // value: i32 = 4字节
-        buffer.extend_from_slice(&event.value.to_le_bytes());
-        
-        // 填充到24字节（如果TimeVal大小不同）
-        let current_size = buffer.len();
-        let target_size = std::mem::size_of::<InputEvent>();
-        if current_size < target_size {
-            buffer.resize(target_size, 0);
-        }
-        
-        println!("write_event: writing event type={}, code={}, value={} to {} (buffer size: {})",
-                 event_type, code, value, self.path, buffer.len());
-        
-        let result = file.write_all(&buffer);
-        if result.is_ok() {
-            println!("write_event: successfully wrote event");
-        } else {
-            println!("write_event: failed to write event: {:?}", result);
-        }
-        result
+        // 每个事件一行，只在trace级别格式化
+        trace!("write_event: type={}, code={}, value={} to {}", event_type, code, value, self.path);
+        file.write_all(&record)
     }
 }
 
-// Helper function for eviocgrab
-fn eviocgrab() -> libc::c_ulong {
-    const EV_IOC_MAGIC: u8 = b'E';
-    const EV_IOC_NR_GRAB: u8 = 0x90;
-    const IOC_WRITE: u32 = 0x1;
-    const IOC_NR_BITS: u32 = 8;
-    const IOC_TYPE_BITS: u32 = 8;
-    const IOC_SIZE_BITS: u32 = 14;
-    const IOC_NR_SHIFT: u32 = 0;
-    const IOC_TYPE_SHIFT: u32 = IOC_NR_SHIFT + IOC_NR_BITS;
-    const IOC_SIZE_SHIFT: u32 = IOC_TYPE_SHIFT + IOC_TYPE_BITS;
-    const IOC_DIR_SHIFT: u32 = IOC_SIZE_SHIFT + IOC_SIZE_BITS;
-
-    ((IOC_WRITE << IOC_DIR_SHIFT) | ((EV_IOC_MAGIC as u32) << IOC_TYPE_SHIFT) | ((EV_IOC_NR_GRAB as u32) << IOC_NR_SHIFT) | (4 << IOC_SIZE_SHIFT)) as libc::c_ulong
+// 虚拟设备的InputId，BUS_VIRTUAL - 参考Go实现，product区分设备类型
+pub const VIRTUAL_TOUCH_ID: InputId = InputId {
+    bus_type: 0x0018,
+    vendor: 0x1234,
+    product: 0x5678,
+    version: 0x0100,
+};
+pub const VIRTUAL_KEY_ID: InputId = InputId { product: 0x5679, ..VIRTUAL_TOUCH_ID };
+pub const VIRTUAL_MOUSE_ID: InputId = InputId { product: 0x567a, ..VIRTUAL_TOUCH_ID };
+
+// 执行一个uinput配置ioctl，失败时返回errno
+fn ui_ioctl(fd: RawFd, request: u32, value: libc::c_int) -> std::io::Result<()> {
+    let result = unsafe { libc::ioctl(fd, request as _, value) };
+    if result == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
 }
 
-// Simplified function to get input devices - 不扫描，直接创建uinput设备
-pub fn get_input_devices() -> Result<Vec<InputDevice>, Box<dyn std::error::Error>> {
-    println!("get_input_devices: creating mock input device (no scanning)");
-    
-    // 创建一个模拟的触摸设备，就像Go实现中如果没有找到设备时的行为
-    let mock_device = InputDevice {
-        name: "MockTouchDevice".to_string(),
-        path: "/dev/input/event0".to_string(),
-        slots: 10,
-        touch_x_min: 0,
-        touch_x_max: 1080,
-        touch_y_min: 0,
-        touch_y_max: 2340,
-        has_touch_major: true,
-        has_touch_minor: true,
-        has_width_major: true,
-        has_width_minor: true,
-        has_orientation: true,
-        has_pressure: true,
-        file: Arc::new(Mutex::new(File::open("/dev/null")?)),
-    };
-    
-    println!("get_input_devices: created mock device");
-    Ok(vec![mock_device])
+const SYSNAME_LEN: usize = 64;
+const VIRTUAL_INPUT_SYSFS: &str = "/sys/devices/virtual/input";
+
+// UI_DEV_CREATE之后查询内核分配的名字（例如input7），得到sysfs路径和其下的eventN节点
+fn locate_virtual_node(device_file: &File) -> std::io::Result<(String, Option<String>)> {
+    let mut name = [0u8; SYSNAME_LEN];
+    let result = unsafe { libc::ioctl(device_file.as_raw_fd(), uigetsysname(SYSNAME_LEN as u32) as _, name.as_mut_ptr()) };
+    if result == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    let len = name.iter().position(|&b| b == 0).unwrap_or(SYSNAME_LEN);
+    let sysfs_path = format!("{}/{}", VIRTUAL_INPUT_SYSFS, String::from_utf8_lossy(&name[..len]));
+    let event_node = std::fs::read_dir(&sysfs_path)?
+        .flatten()
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .find(|entry| entry.starts_with("event"))
+        .map(|event| format!("/dev/input/{}", event));
+    Ok((sysfs_path, event_node))
 }
 
-// Function to create uinput device using proper Linux uinput interface - 参考Go实现
-fn create_uinput_device(name: &str, is_type_b: bool) -> Result<File, Box<dyn std::error::Error>> {
-    println!("create_uinput_device: creating {} uinput device (TypeB: {})", name, is_type_b);
-    
-    // Open uinput device - 参考Go实现使用O_WRONLY|O_NONBLOCK
-    let mut device_file = OpenOptions::new()
+// 记录到DeviceInfo；旧内核不支持UI_GET_SYSNAME时保持为空，不影响设备使用
+fn record_virtual_node(device_file: &File, info: &mut DeviceInfo) {
+    match locate_virtual_node(device_file) {
+        Ok((sysfs_path, event_node)) => {
+            info!("virtual device at {} ({})", event_node.as_deref().unwrap_or("no event node"), sysfs_path);
+            info.sysfs_path = sysfs_path;
+            info.event_node = event_node;
+        }
+        Err(e) => debug!("failed to locate virtual device node: {}", e),
+    }
+}
+
+// 打开/dev/uinput - 参考Go实现使用O_WRONLY|O_NONBLOCK
+fn open_uinput() -> std::io::Result<File> {
+    OpenOptions::new()
         .read(false)
         .write(true)
         .custom_flags(libc::O_NONBLOCK)
-        .open("/dev/uinput")?;
-    
+        .open("/dev/uinput")
+}
+
+// 写入UinputUserDev并执行UI_DEV_CREATE，所有虚拟设备共用
+fn finish_uinput_device(
+    device_file: &mut File,
+    name: &str,
+    id: InputId,
+    abs_min: [i32; ABS_CNT],
+    abs_max: [i32; ABS_CNT],
+) -> std::io::Result<()> {
     let fd = device_file.as_raw_fd();
-    
-    // Enable event types - 参考Go实现
-    unsafe {
-        // Enable EV_KEY - 参考Go实现
-        let result = libc::ioctl(fd, UISETEVBIT() as libc::c_int, EV_KEY as libc::c_int);
-        if result == -1 {
-            return Err(Box::new(std::io::Error::last_os_error()));
-        }
-        
-        // Enable BTN_TOUCH - 参考Go实现
-        let result = libc::ioctl(fd, UISETKEYBIT() as libc::c_int, BTN_TOUCH as libc::c_int);
-        if result == -1 {
-            return Err(Box::new(std::io::Error::last_os_error()));
-        }
-        
-        // Enable EV_ABS - 参考Go实现
-        let result = libc::ioctl(fd, UISETEVBIT() as libc::c_int, EV_ABS as libc::c_int);
-        if result == -1 {
-            return Err(Box::new(std::io::Error::last_os_error()));
-        }
-        
-        if is_type_b {
-            // Type B设备配置 - 参考Go实现
-            let result = libc::ioctl(fd, UISETABSBIT() as libc::c_int, ABS_MT_SLOT as libc::c_int);
-            if result == -1 {
-                return Err(Box::new(std::io::Error::last_os_error()));
-            }
-            let result = libc::ioctl(fd, UISETABSBIT() as libc::c_int, ABS_MT_POSITION_X as libc::c_int);
-            if result == -1 {
-                return Err(Box::new(std::io::Error::last_os_error()));
-            }
-            let result = libc::ioctl(fd, UISETABSBIT() as libc::c_int, ABS_MT_POSITION_Y as libc::c_int);
-            if result == -1 {
-                return Err(Box::new(std::io::Error::last_os_error()));
-            }
-            let result = libc::ioctl(fd, UISETABSBIT() as libc::c_int, ABS_MT_TRACKING_ID as libc::c_int);
-            if result == -1 {
-                return Err(Box::new(std::io::Error::last_os_error()));
-            }
-            let result = libc::ioctl(fd, UISETABSBIT() as libc::c_int, ABS_MT_TOUCH_MAJOR as libc::c_int);
-            if result == -1 {
-                return Err(Box::new(std::io::Error::last_os_error()));
-            }
-            let result = libc::ioctl(fd, UISETABSBIT() as libc::c_int, ABS_MT_TOUCH_MINOR as libc::c_int);
-            if result == -1 {
-                return Err(Box::new(std::io::Error::last_os_error()));
-            }
-            let result = libc::ioctl(fd, UISETABSBIT() as libc::c_int, ABS_MT_WIDTH_MAJOR as libc::c_int);
-            if result == -1 {
-                return Err(Box::new(std::io::Error::last_os_error()));
-            }
-            let result = libc::ioctl(fd, UISETABSBIT() as libc::c_int, ABS_MT_WIDTH_MINOR as libc::c_int);
-            if result == -1 {
-                return Err(Box::new(std::io::Error::last_os_error()));
-            }
-            let result = libc::ioctl(fd, UISETABSBIT() as libc::c_int, ABS_MT_ORIENTATION as libc::c_int);
-            if result == -1 {
-                return Err(Box::new(std::io::Error::last_os_error()));
-            }
-            let result = libc::ioctl(fd, UISETABSBIT() as libc::c_int, ABS_MT_PRESSURE as libc::c_int);
-            if result == -1 {
-                return Err(Box::new(std::io::Error::last_os_error()));
-            }
-        } else {
-            // Type A设备配置 - 参考Go实现
-            let result = libc::ioctl(fd, UISETABSBIT() as libc::c_int, ABS_MT_POSITION_X as libc::c_int);
-            if result == -1 {
-                return Err(Box::new(std::io::Error::last_os_error()));
-            }
-            let result = libc::ioctl(fd, UISETABSBIT() as libc::c_int, ABS_MT_POSITION_Y as libc::c_int);
-            if result == -1 {
-                return Err(Box::new(std::io::Error::last_os_error()));
-            }
-            let result = libc::ioctl(fd, UISETABSBIT() as libc::c_int, ABS_MT_TRACKING_ID as libc::c_int);
-            if result == -1 {
-                return Err(Box::new(std::io::Error::last_os_error()));
-            }
-        }
-        
-        // Enable INPUT_PROP_DIRECT - 参考Go实现
-        let result = libc::ioctl(fd, UISETPROPBIT() as libc::c_int, INPUT_PROP_DIRECT as libc::c_int);
-        if result == -1 {
-            return Err(Box::new(std::io::Error::last_os_error()));
-        }
-        
-        // 配置UinputUserDev - 参考Go实现
-        println!("create_uinput_device: configuring UinputUserDev");
-        
-        // 创建ABS配置数组 - 参考Go实现
-        let mut abs_mins = [0i32; ABS_CNT];
-        let mut abs_maxs = [0i32; ABS_CNT];
-        let abs_fuzz = [0i32; ABS_CNT];
-        let abs_flat = [0i32; ABS_CNT];
-        
-        if is_type_b {
-            // Type B的ABS配置 - 参考Go实现
-            abs_mins[ABS_MT_SLOT as usize] = 0;
-            abs_maxs[ABS_MT_SLOT as usize] = 9; // 10 slots
-            abs_mins[ABS_MT_POSITION_X as usize] = 0;
-            abs_maxs[ABS_MT_POSITION_X as usize] = 1080;
-            abs_mins[ABS_MT_POSITION_Y as usize] = 0;
-            abs_maxs[ABS_MT_POSITION_Y as usize] = 2340;
-            abs_mins[ABS_MT_TRACKING_ID as usize] = 0;
-            abs_maxs[ABS_MT_TRACKING_ID as usize] = 65535;
-            abs_mins[ABS_MT_TOUCH_MAJOR as usize] = 0;
-            abs_maxs[ABS_MT_TOUCH_MAJOR as usize] = 100;
-            abs_mins[ABS_MT_TOUCH_MINOR as usize] = 0;
-            abs_maxs[ABS_MT_TOUCH_MINOR as usize] = 100;
-            abs_mins[ABS_MT_WIDTH_MAJOR as usize] = 0;
-            abs_maxs[ABS_MT_WIDTH_MAJOR as usize] = 100;
-            abs_mins[ABS_MT_WIDTH_MINOR as usize] = 0;
-            abs_maxs[ABS_MT_WIDTH_MINOR as usize] = 100;
-            abs_mins[ABS_MT_ORIENTATION as usize] = 0;
-            abs_maxs[ABS_MT_ORIENTATION as usize] = 90;
-            abs_mins[ABS_MT_PRESSURE as usize] = 0;
-            abs_maxs[ABS_MT_PRESSURE as usize] = 255;
-        } else {
-            // Type A的ABS配置 - 参考Go实现
-            abs_mins[ABS_MT_POSITION_X as usize] = 0;
-            abs_maxs[ABS_MT_POSITION_X as usize] = 1080;
-            abs_mins[ABS_MT_POSITION_Y as usize] = 0;
-            abs_maxs[ABS_MT_POSITION_Y as usize] = 2340;
-            abs_mins[ABS_MT_TRACKING_ID as usize] = 0;
-            abs_maxs[ABS_MT_TRACKING_ID as usize] = 65535;
-        }
-        
-        // 配置EV_SYN - 参考Go实现
-        let result = libc::ioctl(fd, UISETEVBIT() as libc::c_int, EV_SYN as libc::c_int);
-        if result == -1 {
-            return Err(Box::new(std::io::Error::last_os_error()));
-        }
-        
-        // 创建UinputUserDev结构体 - 参考Go实现
-        let mut uidev = UinputUserDev {
-            name: [0; UINPUT_MAX_NAME_SIZE],
-            id: InputId {
-                bus_type: 0x0018, // BUS_VIRTUAL - 参考Go实现
-                vendor: 0x1234,
-                product: 0x5678,
-                version: 0x0100,
-            },
-            effects_max: 0, // 参考Go实现
-            abs_max: abs_maxs,
-            abs_min: abs_mins,
-            abs_fuzz: abs_fuzz,
-            abs_flat: abs_flat,
-        };
-        
-        // 设置设备名称 - 参考Go实现
-        let name_bytes = name.as_bytes();
-        let name_len = name_bytes.len().min(UINPUT_MAX_NAME_SIZE - 1);
-        uidev.name[..name_len].copy_from_slice(&name_bytes[..name_len]);
-        
-        // 写入UinputUserDev - 参考Go实现
-        println!("create_uinput_device: writing UinputUserDev");
-        let uidev_bytes = std::slice::from_raw_parts(&uidev as *const _ as *const u8, std::mem::size_of::<UinputUserDev>());
-        device_file.write_all(uidev_bytes)?;
-        
-        // 创建输入设备 - 参考Go实现
-        println!("create_uinput_device: creating input device");
-        let result = libc::ioctl(fd, UIDEVCREATE() as libc::c_int);
-        if result == -1 {
-            return Err(Box::new(std::io::Error::last_os_error()));
-        }
+
+    // 配置EV_SYN - 参考Go实现
+    ui_ioctl(fd, uisetevbit(), EV_SYN as libc::c_int)?;
+
+    // 创建UinputUserDev结构体 - 参考Go实现
+    let mut uidev = UinputUserDev {
+        name: [0; UINPUT_MAX_NAME_SIZE],
+        id,
+        effects_max: 0, // 参考Go实现
+        abs_max,
+        abs_min,
+        abs_fuzz: [0i32; ABS_CNT],
+        abs_flat: [0i32; ABS_CNT],
+    };
+
+    // 设置设备名称 - 参考Go实现
+    let name_bytes = name.as_bytes();
+    let name_len = name_bytes.len().min(UINPUT_MAX_NAME_SIZE - 1);
+    uidev.name[..name_len].copy_from_slice(&name_bytes[..name_len]);
+
+    // 写入UinputUserDev - 参考Go实现
+    debug!("create_uinput_device: writing UinputUserDev");
+    let uidev_bytes = unsafe {
+        std::slice::from_raw_parts(&uidev as *const _ as *const u8, std::mem::size_of::<UinputUserDev>())
+    };
+    device_file.write_all(uidev_bytes)?;
+
+    // 创建输入设备 - 参考Go实现
+    debug!("create_uinput_device: creating input device");
+    ui_ioctl(fd, uidevcreate(), 0)
+}
+
+// 虚拟设备声明的能力，与扫描真实设备得到的DeviceInfo格式一致
+fn virtual_device_info(events: &[u16], keys: &[u16], rels: &[u16], abs_codes: &[u16], abs_max: &[i32; ABS_CNT]) -> DeviceInfo {
+    DeviceInfo {
+        abs_infos: abs_codes
+            .iter()
+            .map(|&code| {
+                let abs = AbsInfo {
+                    maximum: abs_max[code as usize],
+                    ..AbsInfo::default()
+                };
+                (code, abs)
+            })
+            .collect(),
+        events: events.to_vec(),
+        properties: if abs_codes.is_empty() { Vec::new() } else { vec![INPUT_PROP_DIRECT] },
+        keys: keys.to_vec(),
+        rels: rels.to_vec(),
+        ..DeviceInfo::default()
     }
-    
-    println!("create_uinput_device: successfully created uinput device");
-    Ok(device_file)
 }
 
-// Helper functions for uinput ioctls - 参考Go实现
-fn UISETEVBIT() -> libc::c_ulong {
-    const UI_IOC_MAGIC: u8 = b'U';
-    const UI_IOC_NR_SET_EV_BIT: u8 = 100;
-    const IOC_WRITE: u32 = 0x1;
-    const IOC_NR_BITS: u32 = 8;
-    const IOC_TYPE_BITS: u32 = 8;
-    const IOC_SIZE_BITS: u32 = 14;
-    const IOC_NR_SHIFT: u32 = 0;
-    const IOC_TYPE_SHIFT: u32 = IOC_NR_SHIFT + IOC_NR_BITS;
-    const IOC_SIZE_SHIFT: u32 = IOC_TYPE_SHIFT + IOC_TYPE_BITS;
-    const IOC_DIR_SHIFT: u32 = IOC_SIZE_SHIFT + IOC_SIZE_BITS;
-
-    ((IOC_WRITE << IOC_DIR_SHIFT) | ((UI_IOC_MAGIC as u32) << IOC_TYPE_SHIFT) | ((UI_IOC_NR_SET_EV_BIT as u32) << IOC_NR_SHIFT) | (4 << IOC_SIZE_SHIFT)) as libc::c_ulong
+// 虚拟触摸设备的可调参数，由设备档案提供，缺省值与原来的固定值一致
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VirtualTouchOptions {
+    // 虚拟设备名，随机名称模式下不使用
+    pub name: Option<String>,
+    pub touch_major_max: Option<i32>,
+    pub pressure_max: Option<i32>,
 }
 
-fn UISETKEYBIT() -> libc::c_ulong {
-    const UI_IOC_MAGIC: u8 = b'U';
-    const UI_IOC_NR_SET_KEY_BIT: u8 = 101;
-    const IOC_WRITE: u32 = 0x1;
-    const IOC_NR_BITS: u32 = 8;
-    const IOC_TYPE_BITS: u32 = 8;
-    const IOC_SIZE_BITS: u32 = 14;
-    const IOC_NR_SHIFT: u32 = 0;
-    const IOC_TYPE_SHIFT: u32 = IOC_NR_SHIFT + IOC_NR_BITS;
-    const IOC_SIZE_SHIFT: u32 = IOC_TYPE_SHIFT + IOC_TYPE_BITS;
-    const IOC_DIR_SHIFT: u32 = IOC_SIZE_SHIFT + IOC_SIZE_BITS;
-
-    ((IOC_WRITE << IOC_DIR_SHIFT) | ((UI_IOC_MAGIC as u32) << IOC_TYPE_SHIFT) | ((UI_IOC_NR_SET_KEY_BIT as u32) << IOC_NR_SHIFT) | (4 << IOC_SIZE_SHIFT)) as libc::c_ulong
+// Function to create uinput device using proper Linux uinput interface - 参考Go实现
+fn create_uinput_device(name: &str, is_type_b: bool, options: &VirtualTouchOptions) -> Result<(File, DeviceInfo), Box<dyn std::error::Error>> {
+    debug!("create_uinput_device: creating {} uinput device (TypeB: {})", name, is_type_b);
+
+    let mut device_file = open_uinput()?;
+    let fd = device_file.as_raw_fd();
+
+    // Enable EV_KEY / BTN_TOUCH / EV_ABS - 参考Go实现
+    // Type B按手指数量上报BTN_TOOL_*
+    let keys: &[u16] = if is_type_b {
+        &[BTN_TOUCH, BTN_TOOL_FINGER, BTN_TOOL_DOUBLETAP, BTN_TOOL_TRIPLETAP, BTN_TOOL_QUADTAP, BTN_TOOL_QUINTTAP]
+    } else {
+        &[BTN_TOUCH]
+    };
+    ui_ioctl(fd, uisetevbit(), EV_KEY as libc::c_int)?;
+    for &key in keys {
+        ui_ioctl(fd, uisetkeybit(), key as libc::c_int)?;
+    }
+    ui_ioctl(fd, uisetevbit(), EV_ABS as libc::c_int)?;
+
+    let abs_codes: &[u16] = if is_type_b {
+        // Type B设备配置 - 参考Go实现
+        &[
+            ABS_MT_SLOT,
+            ABS_MT_POSITION_X,
+            ABS_MT_POSITION_Y,
+            ABS_MT_TRACKING_ID,
+            ABS_MT_TOUCH_MAJOR,
+            ABS_MT_TOUCH_MINOR,
+            ABS_MT_WIDTH_MAJOR,
+            ABS_MT_WIDTH_MINOR,
+            ABS_MT_ORIENTATION,
+            ABS_MT_PRESSURE,
+        ]
+    } else {
+        // Type A设备配置 - 参考Go实现
+        &[ABS_MT_POSITION_X, ABS_MT_POSITION_Y, ABS_MT_TRACKING_ID]
+    };
+    for &code in abs_codes {
+        ui_ioctl(fd, uisetabsbit(), code as libc::c_int)?;
+    }
+
+    // Enable INPUT_PROP_DIRECT - 参考Go实现
+    ui_ioctl(fd, uisetpropbit(), INPUT_PROP_DIRECT as libc::c_int)?;
+
+    // 配置UinputUserDev - 参考Go实现
+    debug!("create_uinput_device: configuring UinputUserDev");
+
+    // 创建ABS配置数组 - 参考Go实现
+    let abs_mins = [0i32; ABS_CNT];
+    let mut abs_maxs = [0i32; ABS_CNT];
+
+    abs_maxs[ABS_MT_POSITION_X as usize] = 1080;
+    abs_maxs[ABS_MT_POSITION_Y as usize] = 2340;
+    abs_maxs[ABS_MT_TRACKING_ID as usize] = 65535;
+    if is_type_b {
+        // Type B的ABS配置 - 参考Go实现
+        abs_maxs[ABS_MT_SLOT as usize] = 9; // 10 slots
+        let touch_max = options.touch_major_max.unwrap_or(100);
+        abs_maxs[ABS_MT_TOUCH_MAJOR as usize] = touch_max;
+        abs_maxs[ABS_MT_TOUCH_MINOR as usize] = touch_max;
+        abs_maxs[ABS_MT_WIDTH_MAJOR as usize] = touch_max;
+        abs_maxs[ABS_MT_WIDTH_MINOR as usize] = touch_max;
+        abs_maxs[ABS_MT_ORIENTATION as usize] = 90;
+        abs_maxs[ABS_MT_PRESSURE as usize] = options.pressure_max.unwrap_or(255);
+    }
+    finish_uinput_device(&mut device_file, name, VIRTUAL_TOUCH_ID, abs_mins, abs_maxs)?;
+
+    info!("create_uinput_device: successfully created uinput device");
+    let mut info = virtual_device_info(&[EV_SYN, EV_KEY, EV_ABS], keys, &[], abs_codes, &abs_maxs);
+    record_virtual_node(&device_file, &mut info);
+    Ok((device_file, info))
 }
 
-fn UISETABSBIT() -> libc::c_ulong {
-    const UI_IOC_MAGIC: u8 = b'U';
-    const UI_IOC_NR_SET_ABS_BIT: u8 = 103;
-    const IOC_WRITE: u32 = 0x1;
-    const IOC_NR_BITS: u32 = 8;
-    const IOC_TYPE_BITS: u32 = 8;
-    const IOC_SIZE_BITS: u32 = 14;
-    const IOC_NR_SHIFT: u32 = 0;
-    const IOC_TYPE_SHIFT: u32 = IOC_NR_SHIFT + IOC_NR_BITS;
-    const IOC_SIZE_SHIFT: u32 = IOC_TYPE_SHIFT + IOC_TYPE_BITS;
-    const IOC_DIR_SHIFT: u32 = IOC_SIZE_SHIFT + IOC_SIZE_BITS;
-
-    ((IOC_WRITE << IOC_DIR_SHIFT) | ((UI_IOC_MAGIC as u32) << IOC_TYPE_SHIFT) | ((UI_IOC_NR_SET_ABS_BIT as u32) << IOC_NR_SHIFT) | (4 << IOC_SIZE_SHIFT)) as libc::c_ulong
+// 创建只包含按键能力的uinput设备，keys为允许发送的按键码集合
+fn create_uinput_key_device(name: &str, keys: &[u16]) -> Result<(File, DeviceInfo), Box<dyn std::error::Error>> {
+    debug!("create_uinput_key_device: creating {} uinput device with {} keys", name, keys.len());
+
+    let mut device_file = open_uinput()?;
+    let fd = device_file.as_raw_fd();
+
+    ui_ioctl(fd, uisetevbit(), EV_KEY as libc::c_int)?;
+    for &code in keys {
+        ui_ioctl(fd, uisetkeybit(), code as libc::c_int)?;
+    }
+
+    finish_uinput_device(&mut device_file, name, VIRTUAL_KEY_ID, [0i32; ABS_CNT], [0i32; ABS_CNT])?;
+
+    info!("create_uinput_key_device: successfully created uinput device");
+    let mut info = virtual_device_info(&[EV_SYN, EV_KEY], keys, &[], &[], &[0i32; ABS_CNT]);
+    record_virtual_node(&device_file, &mut info);
+    Ok((device_file, info))
 }
 
-fn UISETPROPBIT() -> libc::c_ulong {
-    const UI_IOC_MAGIC: u8 = b'U';
-    const UI_IOC_NR_SET_PROP_BIT: u8 = 110;
-    const IOC_WRITE: u32 = 0x1;
-    const IOC_NR_BITS: u32 = 8;
-    const IOC_TYPE_BITS: u32 = 8;
-    const IOC_SIZE_BITS: u32 = 14;
-    const IOC_NR_SHIFT: u32 = 0;
-    const IOC_TYPE_SHIFT: u32 = IOC_NR_SHIFT + IOC_NR_BITS;
-    const IOC_SIZE_SHIFT: u32 = IOC_TYPE_SHIFT + IOC_TYPE_BITS;
-    const IOC_DIR_SHIFT: u32 = IOC_SIZE_SHIFT + IOC_SIZE_BITS;
-
-    ((IOC_WRITE << IOC_DIR_SHIFT) | ((UI_IOC_MAGIC as u32) << IOC_TYPE_SHIFT) | ((UI_IOC_NR_SET_PROP_BIT as u32) << IOC_NR_SHIFT) | (4 << IOC_SIZE_SHIFT)) as libc::c_ulong
+// 创建相对坐标鼠标设备：REL_X/REL_Y、滚轮（含高精度滚轮）和左中右三键
+fn create_uinput_mouse_device(name: &str) -> Result<(File, DeviceInfo), Box<dyn std::error::Error>> {
+    debug!("create_uinput_mouse_device: creating {} uinput device", name);
+
+    let mut device_file = open_uinput()?;
+    let fd = device_file.as_raw_fd();
+
+    let keys = [BTN_LEFT, BTN_RIGHT, BTN_MIDDLE];
+    let rels = [REL_X, REL_Y, REL_WHEEL, REL_HWHEEL, REL_WHEEL_HI_RES, REL_HWHEEL_HI_RES];
+
+    ui_ioctl(fd, uisetevbit(), EV_KEY as libc::c_int)?;
+    for code in keys {
+        ui_ioctl(fd, uisetkeybit(), code as libc::c_int)?;
+    }
+
+    ui_ioctl(fd, uisetevbit(), EV_REL as libc::c_int)?;
+    for code in rels {
+        ui_ioctl(fd, uisetrelbit(), code as libc::c_int)?;
+    }
+
+    finish_uinput_device(&mut device_file, name, VIRTUAL_MOUSE_ID, [0i32; ABS_CNT], [0i32; ABS_CNT])?;
+
+    info!("create_uinput_mouse_device: successfully created uinput device");
+    let mut info = virtual_device_info(&[EV_SYN, EV_KEY, EV_REL], &keys, &rels, &[], &[0i32; ABS_CNT]);
+    record_virtual_node(&device_file, &mut info);
+    Ok((device_file, info))
 }
 
-fn UIDEVCREATE() -> libc::c_ulong {
-    const UI_IOC_MAGIC: u8 = b'U';
-    const UI_IOC_NR_CREATE_DEV: u8 = 1;
-    const IOC_NONE: u32 = 0x0;
-    const IOC_NR_BITS: u32 = 8;
-    const IOC_TYPE_BITS: u32 = 8;
-    const IOC_SIZE_BITS: u32 = 14;
-    const IOC_NR_SHIFT: u32 = 0;
-    const IOC_TYPE_SHIFT: u32 = IOC_NR_SHIFT + IOC_NR_BITS;
-    const IOC_SIZE_SHIFT: u32 = IOC_TYPE_SHIFT + IOC_TYPE_BITS;
-    const IOC_DIR_SHIFT: u32 = IOC_SIZE_SHIFT + IOC_SIZE_BITS;
-
-    ((IOC_NONE << IOC_DIR_SHIFT) | ((UI_IOC_MAGIC as u32) << IOC_TYPE_SHIFT) | ((UI_IOC_NR_CREATE_DEV as u32) << IOC_NR_SHIFT) | (0 << IOC_SIZE_SHIFT)) as libc::c_ulong
+// 销毁由本进程创建的uinput设备
+pub fn destroy_uinput_device(device: &InputDevice) -> std::io::Result<()> {
+    debug!("destroy_uinput_device: destroying {}", device.name);
+    let file = device.file.lock().unwrap();
+    ui_ioctl(file.as_raw_fd(), uidevdestroy(), 0)
 }
 
 // Simplified function to create Type-B device - 直接创建，不扫描
-pub fn new_type_b_dev_same(_input_dev: &InputDevice) -> Result<InputDevice, Box<dyn std::error::Error>> {
-    println!("new_type_b_dev_same: creating Type B device");
-    let uinput_file = create_uinput_device("TouchSimulation_B", true)?;
+pub fn new_type_b_dev_same(_input_dev: &InputDevice, options: &VirtualTouchOptions) -> Result<InputDevice, Box<dyn std::error::Error>> {
+    debug!("new_type_b_dev_same: creating Type B device");
+    let name = options.name.as_deref().unwrap_or("TouchSimulation_B");
+    let (uinput_file, info) = create_uinput_device(name, true, options)?;
     
     Ok(InputDevice {
-        name: "TouchSimulation_B".to_string(),
+        name: name.to_string(),
         path: "/dev/uinput".to_string(),
+        input_id: VIRTUAL_TOUCH_ID,
         slots: 10,
         touch_x_min: 0,
         touch_x_max: 1080,
@@ -448,18 +420,21 @@ pub fn new_type_b_dev_same(_input_dev: &InputDevice) -> Result<InputDevice, Box<
         has_width_minor: true,
         has_orientation: true,
         has_pressure: true,
+        info,
         file: Arc::new(Mutex::new(uinput_file)),
     })
 }
 
 // Simplified function to create Type-A device with same properties
-pub fn new_type_a_dev_same(_input_dev: &InputDevice) -> Result<InputDevice, Box<dyn std::error::Error>> {
-    println!("new_type_a_dev_same: creating Type A device");
-    let uinput_file = create_uinput_device("TouchSimulation_A", false)?;
+pub fn new_type_a_dev_same(_input_dev: &InputDevice, options: &VirtualTouchOptions) -> Result<InputDevice, Box<dyn std::error::Error>> {
+    debug!("new_type_a_dev_same: creating Type A device");
+    let name = options.name.as_deref().unwrap_or("TouchSimulation_A");
+    let (uinput_file, info) = create_uinput_device(name, false, options)?;
     
     Ok(InputDevice {
-        name: "TouchSimulation_A".to_string(),
+        name: name.to_string(),
         path: "/dev/uinput".to_string(),
+        input_id: VIRTUAL_TOUCH_ID,
         slots: 10,
         touch_x_min: 0,
         touch_x_max: 1080,
@@ -471,21 +446,23 @@ pub fn new_type_a_dev_same(_input_dev: &InputDevice) -> Result<InputDevice, Box<
         has_width_minor: false,
         has_orientation: false,
         has_pressure: false,
+        info,
         file: Arc::new(Mutex::new(uinput_file)),
     })
 }
 
 // Simplified function to create Type-A device with random properties
-pub fn new_type_a_dev_random(_input_dev: &InputDevice) -> Result<InputDevice, Box<dyn std::error::Error>> {
-    println!("new_type_a_dev_random: creating Type A device with random properties");
+pub fn new_type_a_dev_random(_input_dev: &InputDevice, options: &VirtualTouchOptions) -> Result<InputDevice, Box<dyn std::error::Error>> {
+    debug!("new_type_a_dev_random: creating Type A device with random properties");
     // 使用随机名称
     use crate::utils::rand_string_bytes;
     let random_name = rand_string_bytes(7);
-    let uinput_file = create_uinput_device(&random_name, false)?;
+    let (uinput_file, info) = create_uinput_device(&random_name, false, options)?;
     
     Ok(InputDevice {
         name: random_name,
         path: "/dev/uinput".to_string(),
+        input_id: VIRTUAL_TOUCH_ID,
         slots: 10,
         touch_x_min: 0,
         touch_x_max: 1080,
@@ -497,6 +474,57 @@ pub fn new_type_a_dev_random(_input_dev: &InputDevice) -> Result<InputDevice, Bo
         has_width_minor: false,
         has_orientation: false,
         has_pressure: false,
+        info,
         file: Arc::new(Mutex::new(uinput_file)),
     })
-}
\ No newline at end of file
+}
+
+// 创建虚拟按键设备，keys决定设备声明的按键能力
+pub fn new_key_dev(name: &str, keys: &[u16]) -> Result<InputDevice, Box<dyn std::error::Error>> {
+    debug!("new_key_dev: creating key device");
+    let (uinput_file, info) = create_uinput_key_device(name, keys)?;
+
+    Ok(InputDevice {
+        name: name.to_string(),
+        path: "/dev/uinput".to_string(),
+        input_id: VIRTUAL_KEY_ID,
+        slots: 0,
+        touch_x_min: 0,
+        touch_x_max: 0,
+        touch_y_min: 0,
+        touch_y_max: 0,
+        has_touch_major: false,
+        has_touch_minor: false,
+        has_width_major: false,
+        has_width_minor: false,
+        has_orientation: false,
+        has_pressure: false,
+        info,
+        file: Arc::new(Mutex::new(uinput_file)),
+    })
+}
+
+// 创建虚拟鼠标设备
+pub fn new_mouse_dev(name: &str) -> Result<InputDevice, Box<dyn std::error::Error>> {
+    debug!("new_mouse_dev: creating mouse device");
+    let (uinput_file, info) = create_uinput_mouse_device(name)?;
+
+    Ok(InputDevice {
+        name: name.to_string(),
+        path: "/dev/uinput".to_string(),
+        input_id: VIRTUAL_MOUSE_ID,
+        slots: 0,
+        touch_x_min: 0,
+        touch_x_max: 0,
+        touch_y_min: 0,
+        touch_y_max: 0,
+        has_touch_major: false,
+        has_touch_minor: false,
+        has_width_major: false,
+        has_width_minor: false,
+        has_orientation: false,
+        has_pressure: false,
+        info,
+        file: Arc::new(Mutex::new(uinput_file)),
+    })
+}
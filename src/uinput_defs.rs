@@ -1,16 +1,117 @@
 use nix::sys::time::TimeVal;
+use serde::Serialize;
 
 //---------------------------------EVCodes--------------------------------------//
 
 // Ref: input-event-codes.h
 pub const EV_SYN: u16 = 0x00;
 pub const EV_KEY: u16 = 0x01;
+pub const EV_REL: u16 = 0x02;
 pub const EV_ABS: u16 = 0x03;
+pub const EV_MSC: u16 = 0x04;
+pub const EV_SW: u16 = 0x05;
 pub const EV_FF: u16 = 0x15;
+pub const BTN_MISC: u16 = 0x100;
+pub const BTN_LEFT: u16 = 0x110;
+pub const BTN_RIGHT: u16 = 0x111;
+pub const BTN_MIDDLE: u16 = 0x112;
+pub const BTN_JOYSTICK: u16 = 0x120;
+pub const BTN_GAMEPAD: u16 = 0x130;
+pub const BTN_TOOL_PEN: u16 = 0x140;
+pub const BTN_TOOL_FINGER: u16 = 0x145;
+pub const BTN_TOOL_QUINTTAP: u16 = 0x148;
 pub const BTN_TOUCH: u16 = 0x14a;
+pub const BTN_STYLUS: u16 = 0x14b;
+pub const BTN_TOOL_DOUBLETAP: u16 = 0x14d;
+pub const BTN_TOOL_TRIPLETAP: u16 = 0x14e;
+pub const BTN_TOOL_QUADTAP: u16 = 0x14f;
+pub const KEY_ESC: u16 = 1;
+pub const KEY_1: u16 = 2;
+pub const KEY_2: u16 = 3;
+pub const KEY_3: u16 = 4;
+pub const KEY_4: u16 = 5;
+pub const KEY_5: u16 = 6;
+pub const KEY_6: u16 = 7;
+pub const KEY_7: u16 = 8;
+pub const KEY_8: u16 = 9;
+pub const KEY_9: u16 = 10;
+pub const KEY_0: u16 = 11;
+pub const KEY_MINUS: u16 = 12;
+pub const KEY_EQUAL: u16 = 13;
+pub const KEY_BACKSPACE: u16 = 14;
+pub const KEY_TAB: u16 = 15;
+pub const KEY_Q: u16 = 16;
+pub const KEY_W: u16 = 17;
+pub const KEY_E: u16 = 18;
+pub const KEY_R: u16 = 19;
+pub const KEY_T: u16 = 20;
+pub const KEY_Y: u16 = 21;
+pub const KEY_U: u16 = 22;
+pub const KEY_I: u16 = 23;
+pub const KEY_O: u16 = 24;
+pub const KEY_P: u16 = 25;
+pub const KEY_LEFTBRACE: u16 = 26;
+pub const KEY_RIGHTBRACE: u16 = 27;
+pub const KEY_ENTER: u16 = 28;
+pub const KEY_LEFTCTRL: u16 = 29;
+pub const KEY_A: u16 = 30;
+pub const KEY_S: u16 = 31;
+pub const KEY_D: u16 = 32;
+pub const KEY_F: u16 = 33;
+pub const KEY_G: u16 = 34;
+pub const KEY_H: u16 = 35;
+pub const KEY_J: u16 = 36;
+pub const KEY_K: u16 = 37;
+pub const KEY_L: u16 = 38;
+pub const KEY_SEMICOLON: u16 = 39;
+pub const KEY_APOSTROPHE: u16 = 40;
+pub const KEY_GRAVE: u16 = 41;
+pub const KEY_LEFTSHIFT: u16 = 42;
+pub const KEY_BACKSLASH: u16 = 43;
+pub const KEY_Z: u16 = 44;
+pub const KEY_X: u16 = 45;
+pub const KEY_C: u16 = 46;
+pub const KEY_V: u16 = 47;
+pub const KEY_B: u16 = 48;
+pub const KEY_N: u16 = 49;
+pub const KEY_M: u16 = 50;
+pub const KEY_COMMA: u16 = 51;
+pub const KEY_DOT: u16 = 52;
+pub const KEY_SLASH: u16 = 53;
+pub const KEY_SPACE: u16 = 57;
+pub const KEY_HOME: u16 = 102;
+pub const KEY_MUTE: u16 = 113;
+pub const KEY_VOLUMEDOWN: u16 = 114;
+pub const KEY_VOLUMEUP: u16 = 115;
+pub const KEY_POWER: u16 = 116;
+pub const KEY_MENU: u16 = 139;
+pub const KEY_BACK: u16 = 158;
+pub const KEY_HOMEPAGE: u16 = 172;
+pub const KEY_CAMERA: u16 = 212;
+pub const KEY_SEARCH: u16 = 217;
+pub const KEY_APPSELECT: u16 = 0x244;
 pub const SYN_REPORT: u16 = 0;
 pub const SYN_MT_REPORT: u16 = 2;
 pub const SYN_DROPPED: u16 = 3;
+pub const MSC_SERIAL: u16 = 0x00;
+pub const MSC_SCAN: u16 = 0x04;
+pub const MSC_TIMESTAMP: u16 = 0x05;
+pub const REL_X: u16 = 0x00;
+pub const REL_Y: u16 = 0x01;
+pub const REL_HWHEEL: u16 = 0x06;
+pub const REL_WHEEL: u16 = 0x08;
+pub const REL_WHEEL_HI_RES: u16 = 0x0b;
+pub const REL_HWHEEL_HI_RES: u16 = 0x0c;
+pub const REL_MAX: u16 = 0x0f;
+pub const REL_CNT: usize = REL_MAX as usize + 1;
+pub const SW_MAX: u16 = 0x10;
+pub const SW_CNT: usize = SW_MAX as usize + 1;
+pub const ABS_X: u16 = 0x00;
+pub const ABS_Y: u16 = 0x01;
+pub const ABS_PRESSURE: u16 = 0x18;
+pub const ABS_DISTANCE: u16 = 0x19;
+pub const ABS_TILT_X: u16 = 0x1a;
+pub const ABS_TILT_Y: u16 = 0x1b;
 pub const ABS_MT_SLOT: u16 = 0x2f;
 pub const ABS_MT_TOUCH_MAJOR: u16 = 0x30;
 pub const ABS_MT_TOUCH_MINOR: u16 = 0x31;
@@ -32,10 +133,184 @@ pub const ABS_MAX: u16 = 0x3f;
 pub const ABS_CNT: usize = ABS_MAX as usize + 1;
 pub const KEY_MAX: u16 = 0x2ff;
 pub const KEY_CNT: usize = KEY_MAX as usize + 1;
+pub const INPUT_PROP_POINTER: u16 = 0x00;
 pub const INPUT_PROP_DIRECT: u16 = 0x01;
+pub const INPUT_PROP_BUTTONPAD: u16 = 0x02;
 pub const INPUT_PROP_MAX: u16 = 0x1f;
 pub const INPUT_PROP_CNT: usize = INPUT_PROP_MAX as usize + 1;
 
+// ABS轴名称，用于调试输出
+pub fn abs_code_name(code: u16) -> Option<&'static str> {
+    let name = match code {
+        ABS_X => "ABS_X",
+        ABS_Y => "ABS_Y",
+        ABS_PRESSURE => "ABS_PRESSURE",
+        ABS_DISTANCE => "ABS_DISTANCE",
+        ABS_TILT_X => "ABS_TILT_X",
+        ABS_TILT_Y => "ABS_TILT_Y",
+        ABS_MT_SLOT => "ABS_MT_SLOT",
+        ABS_MT_TOUCH_MAJOR => "ABS_MT_TOUCH_MAJOR",
+        ABS_MT_TOUCH_MINOR => "ABS_MT_TOUCH_MINOR",
+        ABS_MT_WIDTH_MAJOR => "ABS_MT_WIDTH_MAJOR",
+        ABS_MT_WIDTH_MINOR => "ABS_MT_WIDTH_MINOR",
+        ABS_MT_ORIENTATION => "ABS_MT_ORIENTATION",
+        ABS_MT_POSITION_X => "ABS_MT_POSITION_X",
+        ABS_MT_POSITION_Y => "ABS_MT_POSITION_Y",
+        ABS_MT_TOOL_TYPE => "ABS_MT_TOOL_TYPE",
+        ABS_MT_BLOB_ID => "ABS_MT_BLOB_ID",
+        ABS_MT_TRACKING_ID => "ABS_MT_TRACKING_ID",
+        ABS_MT_PRESSURE => "ABS_MT_PRESSURE",
+        ABS_MT_DISTANCE => "ABS_MT_DISTANCE",
+        ABS_MT_TOOL_X => "ABS_MT_TOOL_X",
+        ABS_MT_TOOL_Y => "ABS_MT_TOOL_Y",
+        _ => return None,
+    };
+    Some(name)
+}
+
+pub fn ev_type_name(event_type: u16) -> Option<&'static str> {
+    let name = match event_type {
+        EV_SYN => "EV_SYN",
+        EV_KEY => "EV_KEY",
+        EV_REL => "EV_REL",
+        EV_ABS => "EV_ABS",
+        EV_MSC => "EV_MSC",
+        EV_SW => "EV_SW",
+        EV_FF => "EV_FF",
+        _ => return None,
+    };
+    Some(name)
+}
+
+pub fn syn_code_name(code: u16) -> Option<&'static str> {
+    let name = match code {
+        SYN_REPORT => "SYN_REPORT",
+        SYN_MT_REPORT => "SYN_MT_REPORT",
+        SYN_DROPPED => "SYN_DROPPED",
+        _ => return None,
+    };
+    Some(name)
+}
+
+pub fn rel_code_name(code: u16) -> Option<&'static str> {
+    let name = match code {
+        REL_X => "REL_X",
+        REL_Y => "REL_Y",
+        REL_HWHEEL => "REL_HWHEEL",
+        REL_WHEEL => "REL_WHEEL",
+        REL_WHEEL_HI_RES => "REL_WHEEL_HI_RES",
+        REL_HWHEEL_HI_RES => "REL_HWHEEL_HI_RES",
+        _ => return None,
+    };
+    Some(name)
+}
+
+pub fn msc_code_name(code: u16) -> Option<&'static str> {
+    let name = match code {
+        MSC_SERIAL => "MSC_SERIAL",
+        MSC_SCAN => "MSC_SCAN",
+        MSC_TIMESTAMP => "MSC_TIMESTAMP",
+        _ => return None,
+    };
+    Some(name)
+}
+
+// 只包含本项目定义过的按键
+pub fn key_code_name(code: u16) -> Option<&'static str> {
+    let name = match code {
+        BTN_LEFT => "BTN_LEFT",
+        BTN_RIGHT => "BTN_RIGHT",
+        BTN_MIDDLE => "BTN_MIDDLE",
+        BTN_TOOL_PEN => "BTN_TOOL_PEN",
+        BTN_TOOL_FINGER => "BTN_TOOL_FINGER",
+        BTN_TOOL_QUINTTAP => "BTN_TOOL_QUINTTAP",
+        BTN_TOUCH => "BTN_TOUCH",
+        BTN_STYLUS => "BTN_STYLUS",
+        BTN_TOOL_DOUBLETAP => "BTN_TOOL_DOUBLETAP",
+        BTN_TOOL_TRIPLETAP => "BTN_TOOL_TRIPLETAP",
+        BTN_TOOL_QUADTAP => "BTN_TOOL_QUADTAP",
+        KEY_ESC => "KEY_ESC",
+        KEY_1 => "KEY_1",
+        KEY_2 => "KEY_2",
+        KEY_3 => "KEY_3",
+        KEY_4 => "KEY_4",
+        KEY_5 => "KEY_5",
+        KEY_6 => "KEY_6",
+        KEY_7 => "KEY_7",
+        KEY_8 => "KEY_8",
+        KEY_9 => "KEY_9",
+        KEY_0 => "KEY_0",
+        KEY_MINUS => "KEY_MINUS",
+        KEY_EQUAL => "KEY_EQUAL",
+        KEY_BACKSPACE => "KEY_BACKSPACE",
+        KEY_TAB => "KEY_TAB",
+        KEY_Q => "KEY_Q",
+        KEY_W => "KEY_W",
+        KEY_E => "KEY_E",
+        KEY_R => "KEY_R",
+        KEY_T => "KEY_T",
+        KEY_Y => "KEY_Y",
+        KEY_U => "KEY_U",
+        KEY_I => "KEY_I",
+        KEY_O => "KEY_O",
+        KEY_P => "KEY_P",
+        KEY_LEFTBRACE => "KEY_LEFTBRACE",
+        KEY_RIGHTBRACE => "KEY_RIGHTBRACE",
+        KEY_ENTER => "KEY_ENTER",
+        KEY_LEFTCTRL => "KEY_LEFTCTRL",
+        KEY_A => "KEY_A",
+        KEY_S => "KEY_S",
+        KEY_D => "KEY_D",
+        KEY_F => "KEY_F",
+        KEY_G => "KEY_G",
+        KEY_H => "KEY_H",
+        KEY_J => "KEY_J",
+        KEY_K => "KEY_K",
+        KEY_L => "KEY_L",
+        KEY_SEMICOLON => "KEY_SEMICOLON",
+        KEY_APOSTROPHE => "KEY_APOSTROPHE",
+        KEY_GRAVE => "KEY_GRAVE",
+        KEY_LEFTSHIFT => "KEY_LEFTSHIFT",
+        KEY_BACKSLASH => "KEY_BACKSLASH",
+        KEY_Z => "KEY_Z",
+        KEY_X => "KEY_X",
+        KEY_C => "KEY_C",
+        KEY_V => "KEY_V",
+        KEY_B => "KEY_B",
+        KEY_N => "KEY_N",
+        KEY_M => "KEY_M",
+        KEY_COMMA => "KEY_COMMA",
+        KEY_DOT => "KEY_DOT",
+        KEY_SLASH => "KEY_SLASH",
+        KEY_SPACE => "KEY_SPACE",
+        KEY_HOME => "KEY_HOME",
+        KEY_MUTE => "KEY_MUTE",
+        KEY_VOLUMEDOWN => "KEY_VOLUMEDOWN",
+        KEY_VOLUMEUP => "KEY_VOLUMEUP",
+        KEY_POWER => "KEY_POWER",
+        KEY_MENU => "KEY_MENU",
+        KEY_BACK => "KEY_BACK",
+        KEY_HOMEPAGE => "KEY_HOMEPAGE",
+        KEY_CAMERA => "KEY_CAMERA",
+        KEY_SEARCH => "KEY_SEARCH",
+        KEY_APPSELECT => "KEY_APPSELECT",
+        _ => return None,
+    };
+    Some(name)
+}
+
+// 按事件类型查找代码名称
+pub fn event_code_name(event_type: u16, code: u16) -> Option<&'static str> {
+    match event_type {
+        EV_SYN => syn_code_name(code),
+        EV_KEY => key_code_name(code),
+        EV_REL => rel_code_name(code),
+        EV_ABS => abs_code_name(code),
+        EV_MSC => msc_code_name(code),
+        _ => None,
+    }
+}
+
 //---------------------------------IOCTL--------------------------------------//
 
 // Ref: ioctl.h
@@ -78,6 +353,14 @@ pub fn eviocgname() -> u32 {
     _ioc(IOC_READ, b'E' as u32, 0x06, UINPUT_MAX_NAME_SIZE as u32)
 }
 
+pub fn eviocgphys() -> u32 {
+    _ioc(IOC_READ, b'E' as u32, 0x07, UINPUT_MAX_NAME_SIZE as u32)
+}
+
+pub fn eviocguniq() -> u32 {
+    _ioc(IOC_READ, b'E' as u32, 0x08, UINPUT_MAX_NAME_SIZE as u32)
+}
+
 pub fn eviocgprop() -> u32 {
     _ioc(IOC_READ, b'E' as u32, 0x09, INPUT_PROP_CNT as u32)
 }
@@ -101,7 +384,7 @@ pub fn eviocgrab() -> u32 {
 //---------------------------------Input--------------------------------------//
 
 #[repr(C)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
 pub struct InputId {
     pub bus_type: u16,
     pub vendor: u16,
@@ -110,7 +393,7 @@ pub struct InputId {
 }
 
 #[repr(C)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Default, Serialize)]
 pub struct AbsInfo {
     pub value: i32,
     pub minimum: i32,
@@ -154,6 +437,10 @@ pub fn uisetkeybit() -> u32 {
     _iow(b'U' as u32, 101, 4) // sizeof(int)
 }
 
+pub fn uisetrelbit() -> u32 {
+    _iow(b'U' as u32, 102, 4) // sizeof(int)
+}
+
 pub fn uisetabsbit() -> u32 {
     _iow(b'U' as u32, 103, 4) // sizeof(int)
 }
@@ -168,4 +455,9 @@ pub fn uidevcreate() -> u32 {
 
 pub fn uidevdestroy() -> u32 {
     _ioc(IOC_NONE, b'U' as u32, 2, 0)
+}
+
+// UI_GET_SYSNAME(len)，返回虚拟设备在/sys/devices/virtual/input下的名字，Linux 3.15起支持
+pub fn uigetsysname(len: u32) -> u32 {
+    _ior(b'U' as u32, 44, len)
 }
\ No newline at end of file
@@ -0,0 +1,203 @@
+// 分发线程输出的golden文件测试：向引擎发送命令，写到内存sink的事件流与tests/golden下的文件逐行比较。
+// 改动输出格式后用UPDATE_GOLDEN=1 cargo test --test dispatcher_golden重新生成，检查diff后提交
+use std::{
+    env, fs,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+use touch_simulation::event_sink::MemorySink;
+use touch_simulation::input_filter::AxisRange;
+use touch_simulation::touch_engine::{ContactAxes, EngineConfig, Protocol, TouchCommand, TouchEngine};
+use touch_simulation::uinput_defs::{event_code_name, ev_type_name, EV_SYN, SYN_REPORT};
+
+const UPDATE_ENV: &str = "UPDATE_GOLDEN";
+
+use TouchCommand::{ContactDown as Down, ContactMove as Move, ContactUp as Up, Frame, LiftAll};
+
+// 注入触点带全部附加轴，与有面积和压力轴的参考设备一致
+const FULL_AXES: ContactAxes = ContactAxes {
+    touch_major: 151,
+    touch_minor: 108,
+    width_major: 151,
+    width_minor: 108,
+    orientation: 50,
+    pressure: 35,
+};
+
+// 每个事件一行，值用十进制，每帧之后空一行
+fn render(events: &[(u16, u16, i32)]) -> String {
+    let mut out = String::new();
+    for &(event_type, code, value) in events {
+        let type_name = ev_type_name(event_type).map_or_else(|| format!("{:04x}", event_type), str::to_string);
+        let code_name = event_code_name(event_type, code).map_or_else(|| format!("{:04x}", code), str::to_string);
+        out.push_str(&format!("{:<6} {:<20} {}\n", type_name, code_name, value));
+        if (event_type, code) == (EV_SYN, SYN_REPORT) {
+            out.push('\n');
+        }
+    }
+    out
+}
+
+fn dispatch(protocol: Protocol, axes: ContactAxes, commands: Vec<TouchCommand>) -> Vec<(u16, u16, i32)> {
+    let sink = Arc::new(Mutex::new(MemorySink::new()));
+    let engine = TouchEngine::spawn(Arc::clone(&sink), EngineConfig {
+        protocol,
+        slots: 10,
+        axes,
+        range: AxisRange { x_min: 0, x_max: 1080, y_min: 0, y_max: 2340 },
+        tracking_ids: Arc::default(),
+        buttons: Arc::default(),
+        validator: None,
+        metrics: Arc::default(),
+    });
+    for command in commands {
+        engine.send(command);
+    }
+    // drop时抬起剩下的触点，这一帧也包含在输出里
+    drop(engine);
+    let events = std::mem::take(&mut sink.lock().unwrap().events);
+    events
+}
+
+fn check_golden(name: &str, protocol: Protocol, axes: ContactAxes, commands: Vec<TouchCommand>) {
+    let actual = render(&dispatch(protocol, axes, commands));
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(format!("{}.txt", name));
+    if env::var_os(UPDATE_ENV).is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, &actual).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("missing golden file {} ({}), run with {}=1", path.display(), e, UPDATE_ENV));
+    assert!(
+        actual == expected,
+        "{} differs from the dispatcher output, run with {}=1 to update\n--- expected\n{}\n--- actual\n{}",
+        path.display(),
+        UPDATE_ENV,
+        expected,
+        actual
+    );
+}
+
+fn tap_and_drag() -> Vec<TouchCommand> {
+    vec![
+        Down { slot: 0, x: 540, y: 1170 },
+        Frame,
+        Move { slot: 0, x: 540, y: 1180 },
+        Frame,
+        Move { slot: 0, x: 550, y: 1190 },
+        Frame,
+        Up { slot: 0 },
+        Frame,
+    ]
+}
+
+fn multi_contact() -> Vec<TouchCommand> {
+    vec![
+        Down { slot: 0, x: 100, y: 200 },
+        Frame,
+        Down { slot: 4, x: 500, y: 600 },
+        Down { slot: 2, x: 300, y: 400 },
+        Frame,
+        // 只移动一个触点的一个轴
+        Move { slot: 2, x: 310, y: 400 },
+        Frame,
+        // 抬起中间的触点，其余保持
+        Up { slot: 2 },
+        Move { slot: 4, x: 510, y: 610 },
+        Frame,
+        Up { slot: 0 },
+        Frame,
+        Up { slot: 4 },
+        Frame,
+    ]
+}
+
+fn edge_cases() -> Vec<TouchCommand> {
+    vec![
+        // 超出设备范围的坐标截断到边缘，范围边界上的坐标是有效的触点
+        Down { slot: 1, x: -5, y: -10 },
+        Down { slot: 3, x: 0, y: 0 },
+        Frame,
+        // 截断后位置不变的移动：Type B不产生帧，Type A重复上报所有触点
+        Move { slot: 1, x: -5, y: -10 },
+        Frame,
+        // 超出slot范围、移动或抬起未按下的slot都被忽略，不占用tracking id
+        Down { slot: 12, x: 1, y: 1 },
+        Move { slot: 7, x: 2, y: 2 },
+        Up { slot: 7 },
+        Frame,
+        Move { slot: 1, x: 20, y: -1 },
+        Move { slot: 3, x: 1100, y: 2400 },
+        Frame,
+        // 同一帧里按下又抬起
+        Down { slot: 5, x: 60, y: 70 },
+        Up { slot: 5 },
+        Frame,
+        Up { slot: 1 },
+        Up { slot: 3 },
+        Frame,
+    ]
+}
+
+fn lift_all() -> Vec<TouchCommand> {
+    vec![
+        Down { slot: 0, x: 100, y: 100 },
+        Down { slot: 1, x: 200, y: 200 },
+        Down { slot: 2, x: 300, y: 300 },
+        Frame,
+        // 未提交的移动被丢弃
+        Move { slot: 1, x: 210, y: 210 },
+        LiftAll,
+        // 之后可以继续按下，tracking id继续递增
+        Down { slot: 1, x: 400, y: 400 },
+        Frame,
+    ]
+}
+
+#[test]
+fn type_b_tap_and_drag() {
+    check_golden("type_b_tap_and_drag", Protocol::TypeB, ContactAxes::default(), tap_and_drag());
+}
+
+#[test]
+fn type_b_tap_and_drag_with_axes() {
+    check_golden("type_b_tap_and_drag_axes", Protocol::TypeB, FULL_AXES, tap_and_drag());
+}
+
+#[test]
+fn type_a_tap_and_drag() {
+    check_golden("type_a_tap_and_drag", Protocol::TypeA, ContactAxes::default(), tap_and_drag());
+}
+
+#[test]
+fn type_b_multi_contact() {
+    check_golden("type_b_multi_contact", Protocol::TypeB, ContactAxes::default(), multi_contact());
+}
+
+#[test]
+fn type_a_multi_contact() {
+    check_golden("type_a_multi_contact", Protocol::TypeA, ContactAxes::default(), multi_contact());
+}
+
+#[test]
+fn type_b_edge_cases() {
+    check_golden("type_b_edge_cases", Protocol::TypeB, ContactAxes::default(), edge_cases());
+}
+
+#[test]
+fn type_a_edge_cases() {
+    check_golden("type_a_edge_cases", Protocol::TypeA, ContactAxes::default(), edge_cases());
+}
+
+#[test]
+fn type_b_lift_all() {
+    check_golden("type_b_lift_all", Protocol::TypeB, ContactAxes::default(), lift_all());
+}
+
+#[test]
+fn type_a_teardown_lifts_held_contacts() {
+    // 没有抬起就停止，drop时的最后一帧释放所有触点
+    let commands = vec![Down { slot: 0, x: 10, y: 20 }, Down { slot: 1, x: 30, y: 40 }, Frame];
+    check_golden("type_a_teardown", Protocol::TypeA, ContactAxes::default(), commands);
+}
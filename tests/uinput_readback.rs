@@ -0,0 +1,275 @@
+// 通过真实的/dev/uinput创建虚拟触摸屏，注入手势后从对应的/dev/input/eventN读回并检查每一帧；
+// 没有/dev/uinput或没有权限时跳过
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Read},
+    os::unix::fs::OpenOptionsExt,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+use touch_simulation::event_decoder::{decode_record, Frame, FrameDecoder};
+use touch_simulation::gesture::{AxisTransform, CoordinateMap, GestureParams, GesturePlayback, Rotation};
+use touch_simulation::input_filter::AxisRange;
+use touch_simulation::mock_device::MockProfile;
+use touch_simulation::touch_engine::{ContactAxes, EngineConfig, Protocol, TouchCommand, TouchEngine};
+use touch_simulation::uinput::{self, InputDevice, VirtualTouchOptions, EVENT_RECORD_SIZE};
+use touch_simulation::uinput_defs::*;
+
+const READ_TIMEOUT: Duration = Duration::from_secs(2);
+const WIDTH: i32 = 1080;
+const HEIGHT: i32 = 2340;
+
+// 虚拟设备、分发线程和读回的event节点
+struct VirtualTouch {
+    device: Arc<Mutex<InputDevice>>,
+    engine: Option<TouchEngine>,
+    reader: File,
+    decoder: FrameDecoder,
+}
+
+impl VirtualTouch {
+    // 没有uinput时返回None，调用方直接跳过
+    fn new(protocol: Protocol) -> Option<Self> {
+        if OpenOptions::new().write(true).open("/dev/uinput").is_err() {
+            eprintln!("skipping: /dev/uinput is not available");
+            return None;
+        }
+        let reference = MockProfile::default().to_input_device().unwrap();
+        let options = VirtualTouchOptions::default();
+        let device = match protocol {
+            Protocol::TypeA => uinput::new_type_a_dev_same(&reference, &options),
+            Protocol::TypeB => uinput::new_type_b_dev_same(&reference, &options),
+        }
+        .expect("failed to create uinput device");
+
+        // 节点由devtmpfs创建，udev修改权限可能稍晚
+        // 内核不支持UI_GET_SYSNAME或没有/sys时找不到节点
+        let Some(node) = device.event_node().map(str::to_string) else {
+            eprintln!("skipping: cannot locate the event node of the virtual device");
+            let _ = uinput::destroy_uinput_device(&device);
+            return None;
+        };
+        let deadline = Instant::now() + READ_TIMEOUT;
+        let reader = loop {
+            match OpenOptions::new().read(true).custom_flags(libc::O_NONBLOCK).open(&node) {
+                Ok(reader) => break reader,
+                Err(_) if Instant::now() < deadline => thread::sleep(Duration::from_millis(20)),
+                Err(e) => {
+                    eprintln!("skipping: cannot open {}: {}", node, e);
+                    let _ = uinput::destroy_uinput_device(&device);
+                    return None;
+                }
+            }
+        };
+
+        let slots = device.slots as usize;
+        let range = AxisRange::of(&device);
+        let device = Arc::new(Mutex::new(device));
+        let engine = TouchEngine::spawn(Arc::clone(&device), EngineConfig {
+            protocol,
+            slots,
+            axes: ContactAxes::default(),
+            range,
+            tracking_ids: Arc::default(),
+            buttons: Arc::default(),
+            validator: None,
+            metrics: Arc::default(),
+        });
+        Some(Self {
+            device,
+            engine: Some(engine),
+            reader,
+            decoder: FrameDecoder::new(),
+        })
+    }
+
+    fn engine(&self) -> &TouchEngine {
+        self.engine.as_ref().unwrap()
+    }
+
+    // 显示尺寸与虚拟设备的坐标范围相同，坐标不做缩放
+    fn playback(&self, slot: usize) -> GesturePlayback {
+        let map = CoordinateMap {
+            display_width: WIDTH,
+            display_height: HEIGHT,
+            rotation: Rotation::Rotation0,
+            axes: AxisTransform::default(),
+            x_min: 0,
+            x_max: WIDTH,
+            y_min: 0,
+            y_max: HEIGHT,
+        };
+        GesturePlayback::new(self.engine().sender(), slot, map)
+    }
+
+    // 读到满足done的帧为止，返回期间的所有帧
+    fn read_until(&mut self, done: impl Fn(&Frame) -> bool) -> Vec<Frame> {
+        let deadline = Instant::now() + READ_TIMEOUT;
+        let mut frames = Vec::new();
+        let mut buffer = [0u8; EVENT_RECORD_SIZE * 64];
+        loop {
+            match self.reader.read(&mut buffer) {
+                Ok(n) => {
+                    for record in buffer[..n].chunks_exact(EVENT_RECORD_SIZE) {
+                        if let Some(frame) = self.decoder.push(decode_record(record)) {
+                            let finished = done(&frame);
+                            frames.push(frame);
+                            if finished {
+                                return frames;
+                            }
+                        }
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    assert!(Instant::now() < deadline, "timed out, read {} frames: {:?}", frames.len(), frames);
+                    thread::sleep(Duration::from_millis(5));
+                }
+                Err(e) => panic!("read failed: {}", e),
+            }
+        }
+    }
+}
+
+impl Drop for VirtualTouch {
+    fn drop(&mut self) {
+        self.engine = None;
+        let _ = uinput::destroy_uinput_device(&self.device.lock().unwrap());
+    }
+}
+
+fn lifted(frame: &Frame) -> bool {
+    frame.slots.is_empty() && !frame.btn_touch
+}
+
+fn position(frame: &Frame, slot: i32) -> (i32, i32) {
+    let axes = &frame.slots[&slot].axes;
+    (axes[&ABS_MT_POSITION_X], axes[&ABS_MT_POSITION_Y])
+}
+
+#[test]
+fn tap_reports_a_down_frame_and_a_release_frame() {
+    let Some(mut touch) = VirtualTouch::new(Protocol::TypeB) else { return };
+    let sysfs_path = touch.device.lock().unwrap().info.sysfs_path.clone();
+    assert!(sysfs_path.starts_with("/sys/devices/virtual/input/input"), "{}", sysfs_path);
+
+    let mut playback = touch.playback(0);
+    for step in GestureParams::default().tap(540, 1170, Duration::ZERO) {
+        playback.apply(&step).unwrap();
+    }
+
+    let frames = touch.read_until(lifted);
+    assert_eq!(frames.len(), 2, "{:?}", frames);
+    let down = &frames[0];
+    assert!(down.btn_touch);
+    assert_eq!(down.slots.len(), 1);
+    assert!(down.slots[&0].tracking_id >= 0);
+    assert_eq!(position(down, 0), (540, 1170));
+    // 时间戳由内核填写
+    assert!(down.time_us() > 0);
+    assert!(lifted(&frames[1]));
+}
+
+#[test]
+fn swipe_moves_one_contact_along_the_path() {
+    let Some(mut touch) = VirtualTouch::new(Protocol::TypeB) else { return };
+    let steps = GestureParams::default().swipe(300, 200, 300, 1200);
+    let mut playback = touch.playback(0);
+    for step in &steps {
+        playback.apply(step).unwrap();
+    }
+
+    let frames = touch.read_until(lifted);
+    // 第一个Move与Down位置相同，不产生帧；其余每一步一帧
+    assert_eq!(frames.len(), steps.len() - 1, "{:?}", frames);
+    let (moving, release) = frames.split_at(frames.len() - 1);
+    assert!(lifted(&release[0]));
+    let tracking_id = moving[0].slots[&0].tracking_id;
+    let ys: Vec<i32> = moving
+        .iter()
+        .map(|frame| {
+            assert_eq!(frame.slots[&0].tracking_id, tracking_id);
+            let (x, y) = position(frame, 0);
+            assert_eq!(x, 300);
+            y
+        })
+        .collect();
+    assert!(ys.windows(2).all(|pair| pair[0] <= pair[1]), "{:?}", ys);
+    assert_eq!((ys[0], *ys.last().unwrap()), (200, 1200));
+}
+
+#[test]
+fn multi_touch_contacts_share_frames_with_distinct_tracking_ids() {
+    let Some(mut touch) = VirtualTouch::new(Protocol::TypeB) else { return };
+    let engine = touch.engine();
+    engine.send(TouchCommand::ContactDown { slot: 0, x: 100, y: 200 });
+    engine.send(TouchCommand::ContactDown { slot: 1, x: 900, y: 2000 });
+    engine.send(TouchCommand::Frame);
+    engine.send(TouchCommand::ContactUp { slot: 0 });
+    engine.send(TouchCommand::Frame);
+    engine.send(TouchCommand::ContactUp { slot: 1 });
+    engine.send(TouchCommand::Frame);
+
+    let frames = touch.read_until(lifted);
+    assert_eq!(frames.len(), 3, "{:?}", frames);
+    let both = &frames[0];
+    assert_eq!(both.slots.len(), 2);
+    assert_ne!(both.slots[&0].tracking_id, both.slots[&1].tracking_id);
+    assert_eq!((position(both, 0), position(both, 1)), ((100, 200), (900, 2000)));
+    let key = |frame: &Frame, code: u16| {
+        frame.events.iter().find(|e| e.event_type == EV_KEY && e.code == code).map(|e| e.value)
+    };
+    assert_eq!(key(both, BTN_TOOL_DOUBLETAP), Some(1));
+
+    // 抬起一个手指后剩下的触点不变
+    let one = &frames[1];
+    assert_eq!(one.slots.keys().copied().collect::<Vec<_>>(), vec![1]);
+    assert!(one.btn_touch);
+    assert_eq!((key(one, BTN_TOOL_DOUBLETAP), key(one, BTN_TOOL_FINGER)), (Some(0), Some(1)));
+    assert!(lifted(&frames[2]));
+}
+
+#[test]
+fn type_a_tap_is_reported_with_mt_reports() {
+    let Some(mut touch) = VirtualTouch::new(Protocol::TypeA) else { return };
+    let mut playback = touch.playback(0);
+    for step in GestureParams::default().tap(540, 1170, Duration::ZERO) {
+        playback.apply(&step).unwrap();
+    }
+
+    let frames = touch.read_until(|frame| !frame.btn_touch);
+    assert_eq!(frames.len(), 2, "{:?}", frames);
+    assert_eq!(frames[0].contacts.len(), 1);
+    assert_eq!(frames[0].contacts[0][&ABS_MT_POSITION_X], 540);
+    assert_eq!(frames[0].contacts[0][&ABS_MT_POSITION_Y], 1170);
+    assert!(frames[1].contacts.is_empty());
+}
+
+#[test]
+fn teardown_releases_held_contacts_before_the_device_goes_away() {
+    let Some(mut touch) = VirtualTouch::new(Protocol::TypeB) else { return };
+    let engine = touch.engine();
+    engine.send(TouchCommand::ContactDown { slot: 2, x: 10, y: 20 });
+    engine.send(TouchCommand::ContactDown { slot: 5, x: 30, y: 40 });
+    engine.send(TouchCommand::Frame);
+    assert_eq!(touch.read_until(|frame| frame.slots.len() == 2).len(), 1);
+
+    // 停止分发线程时在一帧里抬起所有触点
+    touch.engine = None;
+    let frames = touch.read_until(lifted);
+    assert_eq!(frames.len(), 1, "{:?}", frames);
+
+    // 销毁后读取返回ENODEV
+    uinput::destroy_uinput_device(&touch.device.lock().unwrap()).unwrap();
+    let deadline = Instant::now() + READ_TIMEOUT;
+    let mut buffer = [0u8; EVENT_RECORD_SIZE];
+    loop {
+        match touch.reader.read(&mut buffer) {
+            Err(e) if e.raw_os_error() == Some(libc::ENODEV) => break,
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock && Instant::now() < deadline => {
+                thread::sleep(Duration::from_millis(5))
+            }
+            other => panic!("expected ENODEV after destroy, got {:?}", other),
+        }
+    }
+}